@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use itertools::Itertools;
 use poe_crafting::{
@@ -18,6 +18,16 @@ fn main() {
         item_level: 75,
         rarity: Rarity::Normal,
         mods: vec![],
+        corrupted: false,
+        implicits: vec![],
+        fractured: vec![],
+        desecrated: vec![],
+        veiled_min_level: None,
+        foreseen: None,
+        quality: 0,
+        quality_type: None,
+        sockets: vec![],
+        rolls: BTreeMap::new(),
     };
 
     CURRENCIES
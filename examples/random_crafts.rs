@@ -1,28 +1,51 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    path::Path,
+};
 
 use poe_crafting::{
     CURRENCIES, ITEM_TIERS,
     currency::Currency,
     init,
     item_state::{ItemState, Rarity, get_valid_mods_for_item},
-    util,
+    util::{self, rand::CraftRng},
 };
+use rand::SeedableRng;
 
 fn main() {
     // let data_root = Path::new("/home/adam/repos/data/poe"); // laptop
     let data_root = Path::new("/mnt/nvme_4tb/programming/data/poe2"); // desktop
     init(data_root);
 
+    // Pass a seed to replay a previous run
+    let seed = env::args()
+        .nth(1)
+        .map(|seed| seed.parse::<u64>().expect("Seed must be a u64"))
+        .unwrap_or_else(rand::random);
+    println!("Seed: {seed}");
+    let mut rng = CraftRng::seed_from_u64(seed);
+
     let bases = ITEM_TIERS.keys().collect::<Vec<_>>();
     let weights = vec![1; bases.len()];
 
     for _ in 0..100 {
-        let base_type = util::rand::choice(&bases, &weights);
+        let base_type = util::rand::choice(&mut rng, &bases, &weights);
         let mut item = ItemState {
             base_type: (*base_type).clone(),
             item_level: 75,
             rarity: Rarity::Normal,
             mods: vec![],
+            corrupted: false,
+            implicits: vec![],
+            fractured: vec![],
+            desecrated: vec![],
+            veiled_min_level: None,
+            foreseen: None,
+            quality: 0,
+            quality_type: None,
+            sockets: vec![],
+            rolls: BTreeMap::new(),
         };
         let candidate_tiers = get_valid_mods_for_item(&item);
 
@@ -30,11 +53,14 @@ fn main() {
             // Select a random currency
             let currencies = CURRENCIES
                 .iter()
-                .filter(|c| c.can_be_used(&item, &candidate_tiers, &HashSet::new()))
+                .filter(|c| {
+                    c.can_be_used(&item, &candidate_tiers, &HashSet::new())
+                        .is_ok()
+                })
                 .copied()
                 .collect::<Vec<_>>();
             let weights = vec![1; currencies.len()];
-            let currency = util::rand::choice(&currencies, &weights);
+            let currency = util::rand::choice(&mut rng, &currencies, &weights);
 
             // Select random omens
             let omens = currency
@@ -42,25 +68,28 @@ fn main() {
                 .into_iter()
                 // Only omens that can be used
                 .filter(|&omen| {
-                    currency.can_be_used(
-                        &item,
-                        &candidate_tiers,
-                        &HashSet::from_iter(std::iter::once(omen)),
-                    )
+                    currency
+                        .can_be_used(
+                            &item,
+                            &candidate_tiers,
+                            &HashSet::from_iter(std::iter::once(omen)),
+                        )
+                        .is_ok()
                 })
                 .collect::<Vec<_>>();
             let weights = vec![1; omens.len()];
-            let omen = util::rand::choice(&omens, &weights);
+            let omen = util::rand::choice(&mut rng, &omens, &weights);
             let omens = HashSet::from_iter(std::iter::once(*omen));
 
             // println!("{:?} {:?}", omens, currency);
             let before = item.clone();
-            currency.craft(&mut item, &candidate_tiers, &omens);
+            let outcome = currency.craft(&mut item, &candidate_tiers, &omens, &mut rng);
             if !item.is_valid() {
-                println!("invalid item");
+                println!("invalid item (seed {seed})");
                 before.print_item();
                 println!("- {:?} {:?} ->", omens, currency);
                 item.print_item();
+                println!("{:?}", outcome);
                 panic!()
             }
         }
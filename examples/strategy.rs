@@ -1,12 +1,18 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
 
 use poe_crafting::{
     MODS,
     currency::{Currency, CurrencyType},
     init,
     item_state::{ItemState, Rarity, get_valid_mods_for_item},
+    markov,
     strategy::{Condition, ConditionGroup, ModifierCondition, Strategy},
+    util::rand::CraftRng,
 };
+use rand::SeedableRng;
 
 fn main() {
     // let data_root = Path::new("/home/adam/repos/data/poe"); // laptop
@@ -18,6 +24,16 @@ fn main() {
         item_level: 100,
         rarity: Rarity::Normal,
         mods: vec![],
+        corrupted: false,
+        implicits: vec![],
+        fractured: vec![],
+        desecrated: vec![],
+        veiled_min_level: None,
+        foreseen: None,
+        quality: 0,
+        quality_type: None,
+        sockets: vec![],
+        rolls: BTreeMap::new(),
     };
     let candidate_tiers = get_valid_mods_for_item(&item);
 
@@ -25,43 +41,62 @@ fn main() {
         ModifierCondition {
             mod_group: MODS.opaque("LocalPhysicalDamage"),
             levels: vec![65, 75],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalFireDamage"),
-
             levels: vec![75, 81],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalColdDamage"),
             levels: vec![75, 81],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalLightningDamage"),
             levels: vec![75, 81],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalPhysicalDamagePercent"),
             levels: vec![60, 75, 82],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("IncreasedWeaponElementalDamagePercent"),
             levels: vec![81],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("AdditionalArrows"),
             levels: vec![82],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalIncreasedAttackSpeed"),
             levels: vec![37],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalBaseCriticalStrikeChance"),
             levels: vec![59, 73],
+            fractured: None,
+            min_roll: None,
         },
         ModifierCondition {
             mod_group: MODS.opaque("LocalCriticalStrikeMultiplier"),
             levels: vec![59, 73],
+            fractured: None,
+            min_roll: None,
         },
     ];
 
@@ -69,6 +104,8 @@ fn main() {
         (
             Condition {
                 rarity: Rarity::Normal,
+                corrupted: None,
+                foreseen: None,
                 groups: vec![],
             },
             Some((HashSet::new(), CurrencyType::PerfectTransmute)),
@@ -76,6 +113,8 @@ fn main() {
         (
             Condition {
                 rarity: Rarity::Magic,
+                corrupted: None,
+                foreseen: None,
                 groups: vec![
                     ConditionGroup::AffixCount {
                         suffixes: 0..=1,
@@ -95,6 +134,8 @@ fn main() {
         (
             Condition {
                 rarity: Rarity::Magic,
+                corrupted: None,
+                foreseen: None,
                 groups: vec![ConditionGroup::AffixCount {
                     suffixes: 0..=1,
                     prefixes: 0..=1,
@@ -106,6 +147,8 @@ fn main() {
         (
             Condition {
                 rarity: Rarity::Magic,
+                corrupted: None,
+                foreseen: None,
                 groups: vec![
                     ConditionGroup::AffixCount {
                         suffixes: (0..=1),
@@ -124,6 +167,8 @@ fn main() {
         (
             Condition {
                 rarity: Rarity::Magic,
+                corrupted: None,
+                foreseen: None,
                 groups: vec![ConditionGroup::AffixCount {
                     suffixes: 1..=1,
                     prefixes: 1..=1,
@@ -134,21 +179,28 @@ fn main() {
         ),
     ]);
 
+    let mut rng = CraftRng::seed_from_u64(0);
     for _ in 0..100 {
         println!("------------------------------------------------------------");
         let mut item = item.clone();
 
         while let Some((omens, currency)) = strategy.get_craft(&item) {
-            assert!(
-                currency.can_be_used(&item, &candidate_tiers, omens),
-                "Currency cannot be used in current state!"
-            );
+            if let Err(reason) = currency.can_be_used(&item, &candidate_tiers, omens) {
+                panic!("Currency cannot be used in current state: {reason}");
+            }
 
-            currency.craft(&mut item, &candidate_tiers, omens);
+            currency.craft(&mut item, &candidate_tiers, omens, &mut rng);
             println!("{:?} {}", omens, currency.name());
             // item.print_item();
             // println!();
         }
         item.print_item();
     }
+
+    // Exact evaluation of the same strategy
+    let evaluation = markov::evaluate(&strategy, &item, &candidate_tiers, 1000, &mut rng).unwrap();
+    println!("End probabilities: {:?}", evaluation.end_probabilities);
+    for (currency, count) in &evaluation.expected_currencies {
+        println!("{}: {:.3}", currency.name(), count);
+    }
 }
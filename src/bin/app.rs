@@ -1,10 +1,11 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
 use poe_crafting::{
-    init,
+    FORMATTERS, init,
     item_state::{ItemState, Rarity},
+    language, set_language,
     ui::{
-        Page,
+        Page, dropdown,
         pages::{currency_sim, item_builder, strategy_sim, ui_debug},
     },
 };
@@ -22,6 +23,16 @@ impl Default for MyEguiApp {
                 item_level: 100,
                 rarity: Rarity::Normal,
                 mods: vec![],
+                corrupted: false,
+                implicits: vec![],
+                fractured: vec![],
+                desecrated: vec![],
+                veiled_min_level: None,
+                foreseen: None,
+                quality: 0,
+                quality_type: None,
+                sockets: vec![],
+                rolls: BTreeMap::new(),
             },
             page: Page::ItemBuilder,
         }
@@ -51,6 +62,15 @@ impl eframe::App for MyEguiApp {
                         self.page = page;
                     }
                 }
+
+                ui.separator();
+                ui.label("Language");
+                let mut languages = FORMATTERS.keys().collect::<Vec<_>>();
+                languages.sort();
+                let mut selected = language();
+                if dropdown(ui, &mut selected, &languages, "language", |l| l.clone()).is_some() {
+                    set_language(&selected);
+                }
             })
         });
 
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt::Display};
 
 use itertools::Itertools;
 
@@ -8,6 +8,39 @@ use crate::{
     types::{Affix, ModFamily, Tier, TierId},
 };
 
+/// A filter which narrowed down the pool of mods a craft could add or remove
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterStage {
+    /// Greater/Perfect currencies only rolling tiers at or above this level
+    MinModLevel(u32),
+    /// Only mods of this affix, from omens or the other affix being full
+    Affix(Affix),
+    /// Homogenising Omen
+    Tags,
+    /// Whittling Omen
+    LowestTier,
+    /// Mods sharing a family with one already on the item
+    Families,
+    /// Fractured mods can't be removed
+    Fractured,
+    /// Omen of Light
+    Desecrated,
+}
+
+impl Display for FilterStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MinModLevel(level) => write!(f, "minimum modifier level {level}"),
+            Self::Affix(affix) => write!(f, "{affix:?}es only"),
+            Self::Tags => write!(f, "matching tags only"),
+            Self::LowestTier => write!(f, "lowest tier only"),
+            Self::Families => write!(f, "no repeated families"),
+            Self::Fractured => write!(f, "unfractured only"),
+            Self::Desecrated => write!(f, "desecrated only"),
+        }
+    }
+}
+
 /// Minimum Modifier Level: Added random modifiers are at least this level or higher,
 /// except if a specific modifier type would be excluded entirely from being able to roll.
 /// In other words, at least one tier of each mod will always be eligible to roll, respecting item level.
@@ -92,6 +125,22 @@ pub fn filter_lowest_tier<I: Iterator<Item = OpaqueIndex<Tier>>>(
         .filter(move |&tier_id| TIERS[tier_id].ilvl == min_ilvl)
 }
 
+/// Removes fractured mods, as they're locked on the item
+pub fn filter_out_fractured<I: Iterator<Item = OpaqueIndex<Tier>>>(
+    candidate_mods: I,
+    fractured: &[OpaqueIndex<Tier>],
+) -> impl Iterator<Item = OpaqueIndex<Tier>> {
+    candidate_mods.filter(move |tier_id| !fractured.contains(tier_id))
+}
+
+/// Keeps only desecrated mods, for Omen of Light
+pub fn filter_desecrated<I: Iterator<Item = OpaqueIndex<Tier>>>(
+    candidate_mods: I,
+    desecrated: &[OpaqueIndex<Tier>],
+) -> impl Iterator<Item = OpaqueIndex<Tier>> {
+    candidate_mods.filter(move |tier_id| desecrated.contains(tier_id))
+}
+
 /// Removes tiers which conflict with the given families
 pub fn filter_out_families<I: Iterator<Item = OpaqueIndex<Tier>>>(
     candidate_mods: I,
@@ -101,6 +150,10 @@ pub fn filter_out_families<I: Iterator<Item = OpaqueIndex<Tier>>>(
         let tier = &TIERS[tier_id];
         let modifier = &MODS[tier.mod_id];
 
-        !families.contains(&modifier.family)
+        // Mods without a family never conflict
+        !modifier
+            .families
+            .iter()
+            .any(|family| families.contains(family))
     })
 }
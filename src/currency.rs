@@ -1,35 +1,319 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use itertools::Itertools;
 
 use crate::{
-    MODS, TIERS,
+    ITEM_CLASSES, MODS, SOCKETABLES, TIERS,
     crafting::{
-        filter_affix, filter_better_currency, filter_lowest_tier, filter_out_families, filter_tags,
+        FilterStage, filter_affix, filter_better_currency, filter_desecrated, filter_lowest_tier,
+        filter_out_families, filter_out_fractured, filter_tags,
     },
+    distribution::ItemDistribution,
     hashvec::OpaqueIndex,
-    item_state::{ItemState, Rarity},
-    types::{Affix, BaseItemId, Omen, Tier},
-    util,
+    item_state::{
+        ARMOUR_CLASSES, Foreseen, ItemState, Rarity, WEAPON_CLASSES,
+        get_corrupted_implicits_for_item, get_desecrated_mods_for_item, veiled_tiers,
+    },
+    types::{Affix, BaseItemId, ModFamily, Omen, QualityType, SocketableId, Tier},
+    util::{self, rand::CraftRng},
 };
 
 pub trait Currency {
     fn name(&self) -> &str;
 
-    /// Whether this currency can currently be used on the given item
+    /// Whether this currency can currently be used on the given item, and why not if it can't
     fn can_be_used(
         &self,
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool;
+    ) -> Result<(), CraftError>;
 
-    /// Use this currency on the item.
+    /// Use this currency on the item, drawing any randomness from the given RNG.
+    /// Returns what was changed on the item.
     /// Assumes that it has been verified with Self::can_be_used
     fn craft(
         &self,
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    );
+        rng: &mut CraftRng,
+    ) -> CraftOutcome;
+
+    /// Exact distribution over the results of using this currency on the item.
+    /// None if there are too many outcomes to enumerate.
+    /// Assumes that it has been verified with Self::can_be_used
+    fn outcomes(
+        &self,
+        _item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        None
+    }
+}
+
+/// Why a currency can't be used on an item
+#[derive(Debug, Clone, PartialEq)]
+pub enum CraftError {
+    WrongRarity {
+        expected: Rarity,
+        actual: Rarity,
+    },
+    TooManyMods {
+        max: usize,
+    },
+    TooFewMods {
+        min: usize,
+    },
+    /// Not enough open affix slots, of a specific affix if given
+    NotEnoughRoom {
+        affix: Option<Affix>,
+        needed: usize,
+    },
+    /// Not enough mods left to add or remove once the pool was filtered
+    NotEnoughCandidates {
+        needed: usize,
+        filters: Vec<FilterStage>,
+    },
+    NoMods,
+    /// Can't be used on this base type. Eg. an essence without a mod for it
+    WrongBaseType {
+        base_type: BaseItemId,
+    },
+    FamilyAlreadyPresent {
+        family: ModFamily,
+    },
+    NoVeiledMod,
+    Corrupted,
+    AlreadyFractured,
+    ItemLevelTooHigh {
+        max: u32,
+    },
+    AlreadyDesecrated,
+    /// The omen doesn't affect this currency
+    WrongOmen {
+        omen: Omen,
+    },
+    ConflictingOmens {
+        first: Omen,
+        second: Omen,
+    },
+    MaxQuality {
+        max: u32,
+    },
+    MaxSockets {
+        max: usize,
+    },
+    NoEmptySocket,
+    /// The item's class can't be this rarity. Eg. a rare flask
+    RarityNotAllowed {
+        rarity: Rarity,
+    },
+}
+
+impl Display for CraftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongRarity { expected, actual } => {
+                write!(f, "Item must be {expected:?}, but is {actual:?}")
+            }
+            Self::TooManyMods { max } => write!(f, "Item already has {max} mods"),
+            Self::TooFewMods { min } => write!(f, "Item needs at least {min} mods"),
+            Self::NotEnoughRoom {
+                affix: Some(affix),
+                needed,
+            } => write!(f, "Needs room for {needed} more {affix:?}(es)"),
+            Self::NotEnoughRoom {
+                affix: None,
+                needed,
+            } => write!(f, "Needs room for {needed} more mod(s)"),
+            Self::NotEnoughCandidates { needed, filters } => {
+                write!(f, "Fewer than {needed} possible mod(s)")?;
+                if !filters.is_empty() {
+                    write!(f, " with {}", filters.iter().join(", "))?;
+                }
+                Ok(())
+            }
+            Self::NoMods => write!(f, "Item has no mods"),
+            Self::WrongBaseType { base_type } => write!(f, "Can't be used on {base_type}"),
+            Self::FamilyAlreadyPresent { family } => {
+                write!(f, "Item already has a {family} mod")
+            }
+            Self::NoVeiledMod => write!(f, "Item has no veiled mod"),
+            Self::Corrupted => write!(f, "Item is corrupted"),
+            Self::AlreadyFractured => write!(f, "Item already has a fractured mod"),
+            Self::ItemLevelTooHigh { max } => write!(f, "Item level must be at most {max}"),
+            Self::AlreadyDesecrated => write!(f, "Item already has a desecrated mod"),
+            Self::WrongOmen { omen } => write!(f, "{omen} can't be used with this currency"),
+            Self::ConflictingOmens { first, second } => {
+                write!(f, "{first} can't be used with {second}")
+            }
+            Self::MaxQuality { max } => write!(f, "Item already has {max}% quality"),
+            Self::MaxSockets { max } => write!(f, "Item already has {max} socket(s)"),
+            Self::NoEmptySocket => write!(f, "Item has no empty socket"),
+            Self::RarityNotAllowed { rarity } => write!(f, "Item can't be {rarity:?}"),
+        }
+    }
+}
+
+/// Omens for a currency previewed by Hinekora's Lock, which are used up by the preview
+fn without_lock(omens: &HashSet<Omen>) -> HashSet<Omen> {
+    omens
+        .iter()
+        .copied()
+        .filter(|&omen| omen != Omen::HinekorasLock)
+        .collect()
+}
+
+/// Whether a Sinistral or Dextral omen restricts the currency to this affix
+fn omens_restrict_to(omens: &HashSet<Omen>, affix: Affix) -> bool {
+    omens.iter().any(|omen| omen.affix() == Some(affix))
+}
+
+/// Check that the item's class can be made the rarity a currency would make it
+fn require_allowed_rarity(item: &ItemState, rarity: Rarity) -> Result<(), CraftError> {
+    if item.rules().allows_rarity(rarity) {
+        Ok(())
+    } else {
+        Err(CraftError::RarityNotAllowed { rarity })
+    }
+}
+
+/// Check that the item is the rarity a currency needs
+fn require_rarity(item: &ItemState, expected: Rarity) -> Result<(), CraftError> {
+    if item.rarity == expected {
+        Ok(())
+    } else {
+        Err(CraftError::WrongRarity {
+            expected,
+            actual: item.rarity,
+        })
+    }
+}
+
+/// Check that at least one of the candidate tiers can roll on the item
+fn require_candidates(
+    item: &ItemState,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+    omens: &HashSet<Omen>,
+    filters: Vec<FilterStage>,
+) -> Result<(), CraftError> {
+    if Exalt
+        .weights(item, candidate_tiers, omens)
+        .iter()
+        .any(|&w| w > 0)
+    {
+        Ok(())
+    } else {
+        Err(CraftError::NotEnoughCandidates { needed: 1, filters })
+    }
+}
+
+/// What a single use of a currency did to the item
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CraftOutcome {
+    /// Mods added to the item
+    pub added: Vec<OpaqueIndex<Tier>>,
+    /// Mods removed from the item
+    pub removed: Vec<OpaqueIndex<Tier>>,
+    /// Rarity before and after, if it was changed
+    pub rarity: Option<(Rarity, Rarity)>,
+    /// Whether the item was corrupted
+    pub corrupted: bool,
+    /// Mod which was fractured
+    pub fractured: Option<OpaqueIndex<Tier>>,
+    /// Mods whose values were rolled, including newly added ones
+    pub rerolled: Vec<OpaqueIndex<Tier>>,
+    /// Omens which took effect
+    pub omens: HashSet<Omen>,
+    /// Filters which restricted the pool of mods
+    pub filters: Vec<FilterStage>,
+}
+
+impl CraftOutcome {
+    /// Changes between two states of an item, for when the result was already known
+    fn between(before: &ItemState, after: &ItemState) -> Self {
+        let tiers = |item: &ItemState| {
+            item.implicits
+                .iter()
+                .chain(&item.mods)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let (before_tiers, after_tiers) = (tiers(before), tiers(after));
+
+        Self {
+            added: after_tiers
+                .iter()
+                .copied()
+                .filter(|tier_id| !before_tiers.contains(tier_id))
+                .collect(),
+            removed: before_tiers
+                .iter()
+                .copied()
+                .filter(|tier_id| !after_tiers.contains(tier_id))
+                .collect(),
+            rarity: (before.rarity != after.rarity).then_some((before.rarity, after.rarity)),
+            corrupted: after.corrupted && !before.corrupted,
+            fractured: after
+                .fractured
+                .iter()
+                .copied()
+                .find(|tier_id| !before.fractured.contains(tier_id)),
+            ..Default::default()
+        }
+    }
+
+    fn add_mod(&mut self, item: &mut ItemState, tier_id: OpaqueIndex<Tier>, rng: &mut CraftRng) {
+        item.mods.push(tier_id);
+        self.added.push(tier_id);
+        self.reroll(item, tier_id, rng);
+    }
+
+    fn remove_mod(&mut self, item: &mut ItemState, tier_id: OpaqueIndex<Tier>) {
+        item.remove_mod(tier_id);
+        self.removed.push(tier_id);
+    }
+
+    fn reroll(&mut self, item: &mut ItemState, tier_id: OpaqueIndex<Tier>, rng: &mut CraftRng) {
+        let values = util::rand::roll_values(rng, &TIERS[tier_id].value_ranges);
+        item.rolls.insert(tier_id, values);
+        self.rerolled.push(tier_id);
+    }
+
+    fn set_rarity(&mut self, item: &mut ItemState, rarity: Rarity) {
+        let from = self.rarity.map_or(item.rarity, |(from, _)| from);
+        item.rarity = rarity;
+        self.rarity = Some((from, rarity));
+    }
+
+    fn restrict(&mut self, stage: FilterStage) {
+        if !self.filters.contains(&stage) {
+            self.filters.push(stage);
+        }
+    }
+
+    /// Fold in the outcome of a later step of the same craft
+    fn merge(&mut self, other: Self) {
+        self.added.extend(other.added);
+        self.removed.extend(other.removed);
+        if let Some((from, to)) = other.rarity {
+            let from = self.rarity.map_or(from, |(from, _)| from);
+            self.rarity = Some((from, to));
+        }
+        self.corrupted |= other.corrupted;
+        self.fractured = other.fractured.or(self.fractured);
+        self.rerolled.extend(other.rerolled);
+        self.omens.extend(other.omens);
+        other
+            .filters
+            .into_iter()
+            .for_each(|stage| self.restrict(stage));
+    }
 }
 
 pub struct Transmute;
@@ -41,10 +325,11 @@ impl Currency for Transmute {
     fn can_be_used(
         &self,
         item: &ItemState,
-        _candidate_tiers: &[OpaqueIndex<Tier>],
+        candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Normal
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Normal)?;
+        Augmentation.require_addable(item, candidate_tiers, &HashSet::new())
     }
 
     fn craft(
@@ -52,10 +337,22 @@ impl Currency for Transmute {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
         // Transmute doesn't care about omens
-        Augmentation.craft(item, candidate_tiers, &HashSet::new());
-        item.rarity = Rarity::Magic;
+        let mut outcome = Augmentation.craft(item, candidate_tiers, &HashSet::new(), rng);
+        outcome.set_rarity(item, Rarity::Magic);
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let outcomes = Augmentation.outcomes(item, candidate_tiers, &HashSet::new())?;
+        Some(outcomes.map(|item| item.rarity = Rarity::Magic))
     }
 }
 
@@ -70,7 +367,7 @@ impl Currency for GreaterTransmute {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Transmute.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -79,9 +376,22 @@ impl Currency for GreaterTransmute {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 55);
+        let mut outcome = Transmute.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(55));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 55);
-        Transmute.craft(item, &candidate_tiers, omens);
+        Transmute.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -96,7 +406,7 @@ impl Currency for PerfectTransmute {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Transmute.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -105,9 +415,22 @@ impl Currency for PerfectTransmute {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 70);
+        let mut outcome = Transmute.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(70));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 70);
-        Transmute.craft(item, &candidate_tiers, omens);
+        Transmute.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -120,41 +443,97 @@ impl Currency for Augmentation {
     fn can_be_used(
         &self,
         item: &ItemState,
-        _candidate_tiers: &[OpaqueIndex<Tier>],
-        _omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Magic && item.mods.len() < 2
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Magic)?;
+        let max = item.max_mods();
+        if item.mods.len() >= max {
+            return Err(CraftError::TooManyMods { max });
+        }
+
+        self.require_addable(item, candidate_tiers, omens)
     }
 
     fn craft(
         &self,
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
-        _omens: &HashSet<Omen>,
-    ) {
+        omens: &HashSet<Omen>,
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+        let candidate_tiers = self.filter_addable(item, candidate_tiers, &mut outcome);
+
+        // Roll a mod
+        let weights = Exalt.weights(item, &candidate_tiers, omens);
+
+        let choice = *util::rand::choice(rng, &candidate_tiers, &weights);
+
+        outcome.add_mod(item, choice, rng);
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers =
+            self.filter_addable(item, candidate_tiers, &mut CraftOutcome::default());
+
+        let weights = Exalt.weights(item, &candidate_tiers, omens);
+
+        Some(ItemDistribution::weighted(
+            &candidate_tiers,
+            &weights,
+            |&tier_id| {
+                let mut item = item.clone();
+                item.mods.push(tier_id);
+                item
+            },
+        ))
+    }
+}
+
+impl Augmentation {
+    /// Check that a mod which fits on the item can be rolled
+    fn require_addable(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        let mut outcome = CraftOutcome::default();
+        let candidate_tiers = self.filter_addable(item, candidate_tiers, &mut outcome);
+        require_candidates(item, &candidate_tiers, omens, outcome.filters)
+    }
+
+    /// Tiers which fit in the free affix slot
+    fn filter_addable(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
             Box::new(candidate_tiers.iter().copied());
 
-        if item.num_prefixes() == 1 {
+        // Room is checked as magic, since Transmutation adds its mod to a normal item
+        let max_affixes = item.rules().max_affixes(Rarity::Magic);
+        if item.num_prefixes() >= max_affixes {
             // Filter out prefixes
-            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix))
+            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
-        if item.num_suffixes() == 1 {
+        if item.num_suffixes() >= max_affixes {
             // Filter out suffixes
-            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix))
+            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
         }
 
-        let candidate_tiers = candidate_tiers.collect::<Vec<_>>();
-
-        // Roll a mod
-        let weights = candidate_tiers
-            .iter()
-            .map(|&tier_id| TIERS[tier_id].weight)
-            .collect::<Vec<_>>();
-
-        let choice = *util::rand::choice(&candidate_tiers, &weights);
-
-        item.mods.push(choice);
+        candidate_tiers.collect()
     }
 }
 
@@ -169,7 +548,7 @@ impl Currency for GreaterAugmentation {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Augmentation.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -178,9 +557,22 @@ impl Currency for GreaterAugmentation {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 55);
+        let mut outcome = Augmentation.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(55));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 55);
-        Augmentation.craft(item, &candidate_tiers, omens);
+        Augmentation.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -195,7 +587,7 @@ impl Currency for PerfectAugmentation {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Augmentation.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -204,9 +596,22 @@ impl Currency for PerfectAugmentation {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 70);
+        let mut outcome = Augmentation.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(70));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 70);
-        Augmentation.craft(item, &candidate_tiers, omens);
+        Augmentation.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -221,13 +626,14 @@ impl Currency for Regal {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Magic && {
-            // TODO: see if we can do this check without copying
-            let mut item = item.clone();
-            item.rarity = Rarity::Rare;
-            Exalt.can_be_used(&item, candidate_tiers, omens)
-        }
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Magic)?;
+        require_allowed_rarity(item, Rarity::Rare)?;
+
+        // TODO: see if we can do this check without copying
+        let mut item = item.clone();
+        item.rarity = Rarity::Rare;
+        Exalt.can_be_used(&item, candidate_tiers, omens)
     }
 
     fn craft(
@@ -235,9 +641,23 @@ impl Currency for Regal {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+        outcome.set_rarity(item, Rarity::Rare);
+        outcome.merge(Exalt.craft(item, candidate_tiers, omens, rng));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let mut item = item.clone();
         item.rarity = Rarity::Rare;
-        Exalt.craft(item, candidate_tiers, omens);
+        Exalt.outcomes(&item, candidate_tiers, omens)
     }
 }
 
@@ -252,7 +672,7 @@ impl Currency for GreaterRegal {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Regal.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -261,9 +681,22 @@ impl Currency for GreaterRegal {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
         let candidate_tiers = filter_better_currency(candidate_tiers, 35);
-        Regal.craft(item, &candidate_tiers, omens);
+        let mut outcome = Regal.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(35));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 35);
+        Regal.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -278,7 +711,7 @@ impl Currency for PerfectRegal {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Regal.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -287,9 +720,22 @@ impl Currency for PerfectRegal {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
         let candidate_tiers = filter_better_currency(candidate_tiers, 50);
-        Regal.craft(item, &candidate_tiers, omens);
+        let mut outcome = Regal.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(50));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 50);
+        Regal.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -304,60 +750,60 @@ impl Currency for Exalt {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Rare && item.mods.len() < 6 && {
-            // Omens
-            let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
-                Box::new(candidate_tiers.iter().copied());
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Rare)?;
+        let max = item.max_mods();
+        if item.mods.len() >= max {
+            return Err(CraftError::TooManyMods { max });
+        }
 
-            if omens.contains(&Omen::Dextral) {
-                // filter suffixes
-                candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
-            }
-            if omens.contains(&Omen::Sinistral) {
-                // filter prefixes
-                candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
-            }
-            if omens.contains(&Omen::Homogenous) {
-                // filter tags
-                let existing_tags = item.mod_tags();
-                // If there are no tags, homogenizing has no effect
-                if !existing_tags.is_empty() {
-                    candidate_tiers = Box::new(filter_tags(candidate_tiers, existing_tags));
-                }
+        // Omens forcing an affix which is already full
+        for affix in [Affix::Prefix, Affix::Suffix] {
+            if omens_restrict_to(omens, affix) && !item.has_room(affix) {
+                return Err(CraftError::NotEnoughRoom {
+                    affix: Some(affix),
+                    needed: 1,
+                });
             }
+        }
 
-            // Filter out based on current item state
-            let candidate_tiers = self.filter_slammable(item, &candidate_tiers.collect::<Vec<_>>());
-            if candidate_tiers.is_empty() {
-                return false;
-            }
+        // Omens
+        let mut outcome = CraftOutcome::default();
+        let candidate_tiers = self.filter_omens(item, candidate_tiers, omens, &mut outcome);
 
-            // Which affixes can be slammed
-            if omens.contains(&Omen::Greater) {
-                let unique_affixes = candidate_tiers
-                    .iter()
-                    .map(|&tier_id| {
-                        let tier = &TIERS[tier_id];
-
-                        tier.affix
-                    })
-                    .collect::<HashSet<_>>();
-
-                if unique_affixes.len() == 1 {
-                    if unique_affixes.contains(&Affix::Suffix) {
-                        item.num_suffixes() <= 1
-                    } else {
-                        item.num_prefixes() <= 1
-                    }
+        // Filter out based on current item state
+        let candidate_tiers = self.filter_slammable(item, &candidate_tiers, &mut outcome);
+        require_candidates(item, &candidate_tiers, omens, outcome.filters)?;
+
+        // Which affixes can be slammed
+        if omens.contains(&Omen::GreaterExaltation) {
+            let unique_affixes = candidate_tiers
+                .iter()
+                .map(|&tier_id| {
+                    let tier = &TIERS[tier_id];
+
+                    tier.affix
+                })
+                .collect::<HashSet<_>>();
+
+            let max_affixes = item.rules().max_affixes(item.rarity);
+            let (affix, has_room) = if unique_affixes.len() == 1 {
+                if unique_affixes.contains(&Affix::Suffix) {
+                    (Some(Affix::Suffix), item.num_suffixes() + 2 <= max_affixes)
                 } else {
-                    item.mods.len() <= 4
+                    (Some(Affix::Prefix), item.num_prefixes() + 2 <= max_affixes)
                 }
             } else {
-                // By now we've checked if anything is slammable for 1 mod
-                true
+                (None, item.mods.len() + 2 <= item.max_mods())
+            };
+
+            if !has_room {
+                return Err(CraftError::NotEnoughRoom { affix, needed: 2 });
             }
         }
+
+        // By now we've checked if anything is slammable
+        Ok(())
     }
 
     fn craft(
@@ -365,68 +811,162 @@ impl Currency for Exalt {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome {
+            omens: omens.clone(),
+            ..Default::default()
+        };
+
+        // Apply omens
+        let candidate_tiers = self.filter_omens(item, candidate_tiers, omens, &mut outcome);
+
+        let num_slams = if omens.contains(&Omen::GreaterExaltation) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..num_slams {
+            let candidate_tiers = self.filter_slammable(item, &candidate_tiers, &mut outcome);
+            let weights = self.weights(item, &candidate_tiers, omens);
+            if weights.iter().all(|&w| w == 0) {
+                // Nothing left to slam, so the item is left as-is
+                break;
+            }
+
+            let choice = *util::rand::choice(rng, &candidate_tiers, &weights);
+            outcome.add_mod(item, choice, rng);
+        }
+        if omens.contains(&Omen::CatalysingExaltation) {
+            Self::consume_quality(item);
+        }
+
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers =
+            self.filter_omens(item, candidate_tiers, omens, &mut CraftOutcome::default());
+
+        let num_slams = if omens.contains(&Omen::GreaterExaltation) {
+            2
+        } else {
+            1
+        };
+        let outcomes = (0..num_slams).fold(ItemDistribution::certain(item.clone()), |dist, _| {
+            dist.flat_map(|item| {
+                let candidate_tiers =
+                    self.filter_slammable(item, &candidate_tiers, &mut CraftOutcome::default());
+                let weights = self.weights(item, &candidate_tiers, omens);
+                if weights.iter().all(|&w| w == 0) {
+                    // Nothing left to slam, so the item is left as-is
+                    return ItemDistribution::certain(item.clone());
+                }
+
+                ItemDistribution::weighted(&candidate_tiers, &weights, |&tier_id| {
+                    let mut item = item.clone();
+                    item.mods.push(tier_id);
+                    item
+                })
+            })
+        });
+
+        if omens.contains(&Omen::CatalysingExaltation) {
+            Some(outcomes.map(Self::consume_quality))
+        } else {
+            Some(outcomes)
+        }
+    }
+}
+
+impl Exalt {
+    /// Restrict the pool of tiers according to the omens used
+    fn filter_omens(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
             Box::new(candidate_tiers.iter().copied());
 
-        // Apply omens
-        if omens.contains(&Omen::Dextral) {
+        if omens_restrict_to(omens, Affix::Suffix) {
             // filter suffixes
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
-        if omens.contains(&Omen::Sinistral) {
+        if omens_restrict_to(omens, Affix::Prefix) {
             // filter prefixes
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
         }
-        if omens.contains(&Omen::Homogenous) {
+        if omens.iter().any(Omen::is_homogenising) {
             // filter tags
             let existing_tags = item.mod_tags();
             // If there are no tags, homogenizing has no effect
             if !existing_tags.is_empty() {
                 candidate_tiers = Box::new(filter_tags(candidate_tiers, existing_tags));
+                outcome.restrict(FilterStage::Tags);
             }
         }
 
-        let candidate_tiers = candidate_tiers.collect::<Vec<_>>();
-
-        // TODO: Check validity of 2nd slam
-        let num_slams = if omens.contains(&Omen::Greater) { 2 } else { 1 };
-        for _ in 0..num_slams {
-            let candidate_tiers = self.filter_slammable(item, &candidate_tiers);
-
-            let mut weights = candidate_tiers
-                .iter()
-                .map(|&tier_id| TIERS[tier_id].weight)
-                .collect::<Vec<_>>();
+        candidate_tiers.collect()
+    }
 
-            // Edge case: When all weights are 0, randomly choose one
-            if weights.iter().all(|&w| w == 0) {
-                weights = vec![1; weights.len()];
-            }
+    /// Sample weights for each of the candidate tiers.
+    /// Tiers which can't roll on the item have a weight of 0, so are never chosen
+    fn weights(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Vec<u32> {
+        let bias = if omens.contains(&Omen::CatalysingExaltation) {
+            ItemState::CATALYSING_BIAS
+        } else {
+            1
+        };
+        candidate_tiers
+            .iter()
+            .map(|&tier_id| item.biased_weight(tier_id, bias))
+            .collect()
+    }
 
-            let choice = *util::rand::choice(&candidate_tiers, &weights);
-            item.mods.push(choice);
-        }
+    /// Omen of Catalysing Exaltation uses up the quality once the mods are added
+    fn consume_quality(item: &mut ItemState) {
+        item.quality = 0;
+        item.quality_type = None;
     }
-}
 
-impl Exalt {
     fn filter_slammable(
         &self,
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
+        outcome: &mut CraftOutcome,
     ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
             Box::new(candidate_tiers.iter().copied());
 
-        candidate_tiers = Box::new(filter_out_families(candidate_tiers, item.mod_familities()));
+        let families = item.mod_familities();
+        if !families.is_empty() {
+            candidate_tiers = Box::new(filter_out_families(candidate_tiers, families));
+            outcome.restrict(FilterStage::Families);
+        }
 
-        if item.num_prefixes() == 3 {
+        if !item.has_room(Affix::Prefix) {
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
 
-        if item.num_suffixes() == 3 {
+        if !item.has_room(Affix::Suffix) {
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
         }
 
         candidate_tiers.collect()
@@ -444,7 +984,7 @@ impl Currency for GreaterExalt {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Exalt.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -453,9 +993,22 @@ impl Currency for GreaterExalt {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
         let candidate_tiers = filter_better_currency(candidate_tiers, 35);
-        Exalt.craft(item, &candidate_tiers, omens);
+        let mut outcome = Exalt.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(35));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 35);
+        Exalt.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -470,7 +1023,7 @@ impl Currency for PerfectExalt {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Exalt.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -479,9 +1032,22 @@ impl Currency for PerfectExalt {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
         let candidate_tiers = filter_better_currency(candidate_tiers, 50);
-        Exalt.craft(item, &candidate_tiers, omens);
+        let mut outcome = Exalt.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(50));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 50);
+        Exalt.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -496,30 +1062,28 @@ impl Currency for Annulment {
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
-        !item.mods.is_empty() && {
-            // Omens
-            let mut candidate_removes: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
-                Box::new(item.mods.iter().copied());
+    ) -> Result<(), CraftError> {
+        if item.mods.is_empty() {
+            return Err(CraftError::NoMods);
+        }
 
-            // Apply omens
-            if omens.contains(&Omen::Dextral) {
-                // filter suffixes
-                candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Suffix));
-            }
-            if omens.contains(&Omen::Sinistral) {
-                // filter prefixes
-                candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Prefix));
-            }
-            // For chaos orb
-            if omens.contains(&Omen::Whittling) {
-                // filter lowest tier
-                candidate_removes = Box::new(filter_lowest_tier(candidate_removes));
-            }
-            let num_removes = if omens.contains(&Omen::Greater) { 2 } else { 1 };
+        // Omens
+        let mut outcome = CraftOutcome::default();
+        let candidate_removes = self.filter_removable(item, omens, &mut outcome);
+        let num_removes = if omens.contains(&Omen::GreaterAnnulment) {
+            2
+        } else {
+            1
+        };
 
-            candidate_removes.count() >= num_removes
+        if candidate_removes.len() < num_removes {
+            return Err(CraftError::NotEnoughCandidates {
+                needed: num_removes,
+                filters: outcome.filters,
+            });
         }
+
+        Ok(())
     }
 
     fn craft(
@@ -527,41 +1091,124 @@ impl Currency for Annulment {
         item: &mut ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome {
+            omens: omens.clone(),
+            ..Default::default()
+        };
+
         // Omens
+        let mut candidate_removes = self.filter_removable(item, omens, &mut outcome);
+
+        let num_removes = if omens.contains(&Omen::GreaterAnnulment) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..num_removes {
+            if candidate_removes.is_empty() {
+                // Nothing left to remove, so the item is left as-is
+                break;
+            }
+            let weights = vec![1; candidate_removes.len()];
+            let to_remove = *util::rand::choice(rng, &candidate_removes, &weights);
+
+            outcome.remove_mod(item, to_remove);
+            candidate_removes.retain(|tier_id| *tier_id != to_remove);
+        }
+
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_removes = self.filter_removable(item, omens, &mut CraftOutcome::default());
+
+        let num_removes = if omens.contains(&Omen::GreaterAnnulment) {
+            2
+        } else {
+            1
+        };
+        let outcomes = (0..num_removes).fold(ItemDistribution::certain(item.clone()), |dist, _| {
+            dist.flat_map(|item| {
+                // Candidates which haven't been removed already
+                let candidate_removes = candidate_removes
+                    .iter()
+                    .copied()
+                    .filter(|tier_id| item.mods.contains(tier_id))
+                    .collect::<Vec<_>>();
+                if candidate_removes.is_empty() {
+                    // Nothing left to remove, so the item is left as-is
+                    return ItemDistribution::certain(item.clone());
+                }
+
+                ItemDistribution::uniform(&candidate_removes, |&to_remove| {
+                    let mut item = item.clone();
+                    item.remove_mod(to_remove);
+                    item
+                })
+            })
+        });
+
+        Some(outcomes)
+    }
+}
+
+impl Annulment {
+    /// Mods which can be removed with the omens used
+    fn filter_removable(
+        &self,
+        item: &ItemState,
+        omens: &HashSet<Omen>,
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_removes: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
             Box::new(item.mods.iter().copied());
 
+        // Fractured mods are locked
+        if !item.fractured.is_empty() {
+            candidate_removes = Box::new(filter_out_fractured(candidate_removes, &item.fractured));
+            outcome.restrict(FilterStage::Fractured);
+        }
+
         // Apply omens
-        if omens.contains(&Omen::Dextral) {
+        if omens_restrict_to(omens, Affix::Suffix) {
             // filter suffixes
             candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
-        if omens.contains(&Omen::Sinistral) {
+        if omens_restrict_to(omens, Affix::Prefix) {
             // filter prefixes
             candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
+        }
+        if omens.contains(&Omen::Light) {
+            // filter desecrated
+            candidate_removes = Box::new(filter_desecrated(candidate_removes, &item.desecrated));
+            outcome.restrict(FilterStage::Desecrated);
         }
         // For chaos orb
         if omens.contains(&Omen::Whittling) {
             // filter lowest tier
             candidate_removes = Box::new(filter_lowest_tier(candidate_removes));
+            outcome.restrict(FilterStage::LowestTier);
         }
 
-        let mut candidate_removes = candidate_removes.collect::<Vec<_>>();
-
-        // TODO: Check validity of 2nd remove
-        let num_removes = if omens.contains(&Omen::Greater) { 2 } else { 1 };
-        for _ in 0..num_removes {
-            let weights = vec![1; candidate_removes.len()];
-            let to_remove = *util::rand::choice(&candidate_removes, &weights);
-
-            item.mods.retain(|tier_id| *tier_id != to_remove);
-            candidate_removes.retain(|tier_id| *tier_id != to_remove);
-        }
+        candidate_removes.collect()
     }
 }
 
 pub struct Alchemy;
+impl Alchemy {
+    /// Mods added, unless the class can't have that many on a rare
+    const NUM_MODS: usize = 4;
+}
+
 impl Currency for Alchemy {
     fn name(&self) -> &str {
         "Alchemy"
@@ -572,8 +1219,9 @@ impl Currency for Alchemy {
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Normal
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Normal)?;
+        require_allowed_rarity(item, Rarity::Rare)
     }
 
     fn craft(
@@ -581,22 +1229,26 @@ impl Currency for Alchemy {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
-        item.rarity = Rarity::Rare;
-
-        let omens = if omens.contains(&Omen::Dextral) {
-            HashSet::from_iter(std::iter::once(Omen::Dextral))
-        } else if omens.contains(&Omen::Sinistral) {
-            HashSet::from_iter(std::iter::once(Omen::Sinistral))
-        } else {
-            HashSet::new()
-        };
-
-        for _ in 0..3 {
-            Exalt.craft(item, candidate_tiers, &omens);
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+        outcome.set_rarity(item, Rarity::Rare);
+
+        // Sinistral/Dextral omens fill up their affix with the first mods, the rest are random
+        let max_affixes = item.rules().max_affixes(Rarity::Rare);
+        let num_mods = Self::NUM_MODS.min(max_affixes * 2);
+        let omen_slams = max_affixes.min(num_mods);
+        for _ in 0..omen_slams {
+            outcome.merge(Exalt.craft(item, candidate_tiers, omens, rng));
+        }
+        for _ in omen_slams..num_mods {
+            outcome.merge(Exalt.craft(item, candidate_tiers, &HashSet::new(), rng));
         }
-        Exalt.craft(item, candidate_tiers, &HashSet::new());
+
+        outcome
     }
+
+    // No exact outcomes: 4 slams from the whole pool is too many states to enumerate
 }
 
 pub struct Chaos;
@@ -610,8 +1262,9 @@ impl Currency for Chaos {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
-        item.rarity == Rarity::Rare && Annulment.can_be_used(item, candidate_tiers, omens)
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Rare)?;
+        Annulment.can_be_used(item, candidate_tiers, omens)
     }
 
     fn craft(
@@ -619,9 +1272,27 @@ impl Currency for Chaos {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
-        Annulment.craft(item, candidate_tiers, omens);
-        Exalt.craft(item, candidate_tiers, &HashSet::new());
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = Annulment.craft(item, candidate_tiers, omens, rng);
+        outcome.merge(Exalt.craft(item, candidate_tiers, &HashSet::new(), rng));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let removed = Annulment.outcomes(item, candidate_tiers, omens)?;
+        let outcomes = removed.flat_map(|item| {
+            Exalt
+                .outcomes(item, candidate_tiers, &HashSet::new())
+                .expect("Exalt always has exact outcomes")
+        });
+
+        Some(outcomes)
     }
 }
 
@@ -636,7 +1307,7 @@ impl Currency for GreaterChaos {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Chaos.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -645,9 +1316,22 @@ impl Currency for GreaterChaos {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 35);
+        let mut outcome = Chaos.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(35));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 35);
-        Chaos.craft(item, &candidate_tiers, omens);
+        Chaos.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -662,7 +1346,7 @@ impl Currency for PerfectChaos {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         Chaos.can_be_used(item, candidate_tiers, omens)
     }
 
@@ -671,9 +1355,22 @@ impl Currency for PerfectChaos {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let candidate_tiers = filter_better_currency(candidate_tiers, 50);
+        let mut outcome = Chaos.craft(item, &candidate_tiers, omens, rng);
+        outcome.restrict(FilterStage::MinModLevel(50));
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
         let candidate_tiers = filter_better_currency(candidate_tiers, 50);
-        Chaos.craft(item, &candidate_tiers, omens);
+        Chaos.outcomes(item, &candidate_tiers, omens)
     }
 }
 
@@ -693,40 +1390,58 @@ impl Currency for Essence {
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         // must be magic
-        if item.rarity != Rarity::Magic {
-            return false;
-        }
+        require_rarity(item, Rarity::Magic)?;
+        require_allowed_rarity(item, Rarity::Rare)?;
 
         // base type must match
-        let Some(new_tier_ids) = self.tiers.get(&item.base_type) else {
-            return false;
-        };
+        let new_tier_ids =
+            self.tiers
+                .get(&item.base_type)
+                .ok_or_else(|| CraftError::WrongBaseType {
+                    base_type: item.base_type.clone(),
+                })?;
         let new_tiers = new_tier_ids
             .iter()
             .map(|&tier_id| &TIERS[tier_id])
             .collect::<Vec<_>>();
 
-        // Must not have a mod of the same family already
-        // Assumption: all mods added have the same family
-        let new_mod_family = &MODS[new_tiers.first().unwrap().mod_id].family;
-        if item.mod_familities().contains(new_mod_family) {
-            return false;
+        // Must not share a family with a mod already on the item
+        // Assumption: all mods added have the same families
+        let item_families = item.mod_familities();
+        let new_mod = &MODS[new_tiers.first().unwrap().mod_id];
+        if let Some(family) = new_mod
+            .families
+            .iter()
+            .find(|family| item_families.contains(*family))
+        {
+            return Err(CraftError::FamilyAlreadyPresent {
+                family: family.clone(),
+            });
         }
 
-        // Must have space for the new mod
+        // Must have space for the new mod once the item is rare
+        let max_affixes = item.rules().max_affixes(Rarity::Rare);
         let new_mod_affixes = new_tiers.iter().map(|tier| tier.affix).collect::<Vec<_>>();
-        match (
+        let has_room = match (
             new_mod_affixes.contains(&Affix::Prefix),
             new_mod_affixes.contains(&Affix::Suffix),
         ) {
             // Can add either, so doesn't matter which is removed
-            (true, true) => true,
+            (true, true) => None,
             // Adds one affix, so space must be made if full
-            (true, false) => item.num_prefixes() < 3,
-            (false, true) => item.num_suffixes() < 3,
+            (true, false) => Some((Affix::Prefix, item.num_prefixes() < max_affixes)),
+            (false, true) => Some((Affix::Suffix, item.num_suffixes() < max_affixes)),
             (false, false) => unreachable!(),
+        };
+
+        match has_room {
+            Some((affix, false)) => Err(CraftError::NotEnoughRoom {
+                affix: Some(affix),
+                needed: 1,
+            }),
+            _ => Ok(()),
         }
     }
 
@@ -735,12 +1450,50 @@ impl Currency for Essence {
         item: &mut ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+        outcome.set_rarity(item, Rarity::Rare);
+        add_essence_mod(item, &self.tiers[&item.base_type], &mut outcome, rng);
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let mut item = item.clone();
         item.rarity = Rarity::Rare;
-        Exalt.craft(item, &self.tiers[&item.base_type], &HashSet::new());
+        Some(essence_mod_outcomes(&item, &self.tiers[&item.base_type]))
     }
 }
 
+/// Add one of an essence's mods. These may not be able to roll randomly so have no spawn weight,
+/// instead each tier which fits on the item is equally likely
+fn add_essence_mod(
+    item: &mut ItemState,
+    new_tier_ids: &[OpaqueIndex<Tier>],
+    outcome: &mut CraftOutcome,
+    rng: &mut CraftRng,
+) {
+    let candidate_tiers = Exalt.filter_slammable(item, new_tier_ids, outcome);
+    let weights = vec![1; candidate_tiers.len()];
+    let choice = *util::rand::choice(rng, &candidate_tiers, &weights);
+    outcome.add_mod(item, choice, rng);
+}
+
+/// Exact outcomes of add_essence_mod
+fn essence_mod_outcomes(item: &ItemState, new_tier_ids: &[OpaqueIndex<Tier>]) -> ItemDistribution {
+    let candidate_tiers = Exalt.filter_slammable(item, new_tier_ids, &mut CraftOutcome::default());
+    ItemDistribution::uniform(&candidate_tiers, |&tier_id| {
+        let mut item = item.clone();
+        item.mods.push(tier_id);
+        item
+    })
+}
+
 impl PartialEq for Essence {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -762,62 +1515,49 @@ impl Currency for PerfectEssence {
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
         // must be rare
-        if item.rarity != Rarity::Rare {
-            return false;
-        }
+        require_rarity(item, Rarity::Rare)?;
 
         // base type must match
-        let Some(new_tier_ids) = self.tiers.get(&item.base_type) else {
-            return false;
-        };
+        let new_tier_ids =
+            self.tiers
+                .get(&item.base_type)
+                .ok_or_else(|| CraftError::WrongBaseType {
+                    base_type: item.base_type.clone(),
+                })?;
         let new_tiers = new_tier_ids
             .iter()
             .map(|&tier_id| &TIERS[tier_id])
             .collect::<Vec<_>>();
 
-        // Must not have a mod of the same family already
-        // Assumption: all mods added have the same family
-        let new_mod_family = &MODS[new_tiers.first().unwrap().mod_id].family;
-        if item.mod_familities().contains(new_mod_family) {
-            return false;
+        // Must not share a family with a mod already on the item
+        // Assumption: all mods added have the same families
+        let item_families = item.mod_familities();
+        let new_mod = &MODS[new_tiers.first().unwrap().mod_id];
+        if let Some(family) = new_mod
+            .families
+            .iter()
+            .find(|family| item_families.contains(*family))
+        {
+            return Err(CraftError::FamilyAlreadyPresent {
+                family: family.clone(),
+            });
         }
 
         // Must have room for it
-        // If there's not enough space for the mod, remove a mod with the same affix
-        // Otherwise, remove a random mod
-        let mut candidate_removes: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
-            Box::new(item.mods.iter().copied());
-
-        let new_mod_affixes = new_tiers.iter().map(|tier| tier.affix).collect::<Vec<_>>();
-        let need_remove_affix = match (
-            new_mod_affixes.contains(&Affix::Prefix),
-            new_mod_affixes.contains(&Affix::Suffix),
-        ) {
-            // Can add either, so doesn't matter which is removed
-            (true, true) => None,
-            // Adds one affix, so space must be made if full
-            (true, false) => (item.num_prefixes() == 3).then_some(Affix::Prefix),
-            (false, true) => (item.num_suffixes() == 3).then_some(Affix::Suffix),
-            (false, false) => unreachable!(),
-        };
-        if let Some(affix) = need_remove_affix {
-            // filter same affix as essence adds
-            candidate_removes = Box::new(filter_affix(candidate_removes, affix));
-        }
-
-        // Apply omens
-        if omens.contains(&Omen::Dextral) {
-            // filter suffixes
-            candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Suffix));
-        }
-        if omens.contains(&Omen::Sinistral) {
-            // filter prefixes
-            candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Prefix));
+        let mut outcome = CraftOutcome::default();
+        if self
+            .filter_removable(item, new_tier_ids, omens, &mut outcome)
+            .is_empty()
+        {
+            return Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: outcome.filters,
+            });
         }
 
-        candidate_removes.count() > 0
+        Ok(())
     }
 
     fn craft(
@@ -825,20 +1565,72 @@ impl Currency for PerfectEssence {
         item: &mut ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome {
+            omens: omens.clone(),
+            ..Default::default()
+        };
+
         let new_tier_ids = &self.tiers[&item.base_type];
-        let new_tiers = new_tier_ids
-            .iter()
-            .map(|&tier_id| &TIERS[tier_id])
-            .collect::<Vec<_>>();
+        let candidate_removes = self.filter_removable(item, new_tier_ids, omens, &mut outcome);
 
-        // Must have room for it
-        // If there's not enough space for the mod, remove a mod with the same affix
-        // Otherwise, remove a random mod
+        // Remove a mod
+        let weights = vec![1; candidate_removes.len()];
+        let to_remove = *util::rand::choice(rng, &candidate_removes, &weights);
+
+        outcome.remove_mod(item, to_remove);
+
+        // Add on a new mod
+        add_essence_mod(item, new_tier_ids, &mut outcome, rng);
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let new_tier_ids = &self.tiers[&item.base_type];
+        let candidate_removes =
+            self.filter_removable(item, new_tier_ids, omens, &mut CraftOutcome::default());
+
+        let removed = ItemDistribution::uniform(&candidate_removes, |&to_remove| {
+            let mut item = item.clone();
+            item.remove_mod(to_remove);
+            item
+        });
+        let outcomes = removed.flat_map(|item| essence_mod_outcomes(item, new_tier_ids));
+
+        Some(outcomes)
+    }
+}
+
+impl PerfectEssence {
+    /// Mods which could be removed to make room for the essence mod
+    /// If there's not enough space for the mod, remove a mod with the same affix
+    /// Otherwise, remove a random mod
+    fn filter_removable(
+        &self,
+        item: &ItemState,
+        new_tier_ids: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_removes: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
             Box::new(item.mods.iter().copied());
 
-        let new_mod_affixes = new_tiers.iter().map(|tier| tier.affix).collect::<Vec<_>>();
+        // Fractured mods are locked
+        if !item.fractured.is_empty() {
+            candidate_removes = Box::new(filter_out_fractured(candidate_removes, &item.fractured));
+            outcome.restrict(FilterStage::Fractured);
+        }
+
+        let new_mod_affixes = new_tier_ids
+            .iter()
+            .map(|&tier_id| TIERS[tier_id].affix)
+            .collect::<Vec<_>>();
         let need_remove_affix = match (
             new_mod_affixes.contains(&Affix::Prefix),
             new_mod_affixes.contains(&Affix::Suffix),
@@ -846,35 +1638,29 @@ impl Currency for PerfectEssence {
             // Can add either, so doesn't matter which is removed
             (true, true) => None,
             // Adds one affix, so space must be made if full
-            (true, false) => (item.num_prefixes() == 3).then_some(Affix::Prefix),
-            (false, true) => (item.num_suffixes() == 3).then_some(Affix::Suffix),
+            (true, false) => (!item.has_room(Affix::Prefix)).then_some(Affix::Prefix),
+            (false, true) => (!item.has_room(Affix::Suffix)).then_some(Affix::Suffix),
             (false, false) => unreachable!(),
         };
         if let Some(affix) = need_remove_affix {
             // filter same affix as essence adds
             candidate_removes = Box::new(filter_affix(candidate_removes, affix));
+            outcome.restrict(FilterStage::Affix(affix));
         }
 
         // Apply omens
-        if omens.contains(&Omen::Dextral) {
+        if omens_restrict_to(omens, Affix::Suffix) {
             // filter suffixes
             candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
-        if omens.contains(&Omen::Sinistral) {
+        if omens_restrict_to(omens, Affix::Prefix) {
             // filter prefixes
             candidate_removes = Box::new(filter_affix(candidate_removes, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
         }
 
-        let candidate_removes = candidate_removes.collect::<Vec<_>>();
-
-        // Remove a mod
-        let weights = vec![1; candidate_removes.len()];
-        let to_remove = *util::rand::choice(&candidate_removes, &weights);
-
-        item.mods.retain(|tier_id| *tier_id != to_remove);
-
-        // Add on a new mod
-        Exalt.craft(item, new_tier_ids, &HashSet::new());
+        candidate_removes.collect()
     }
 }
 
@@ -884,20 +1670,84 @@ impl PartialEq for PerfectEssence {
     }
 }
 
-pub struct Desecrate;
+/// Which bone a desecration currency is, deciding the item classes it can be used on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bone {
+    Jawbone,
+    Rib,
+    Collarbone,
+}
+
+impl Bone {
+    /// Item classes this bone can desecrate
+    fn item_classes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Jawbone => WEAPON_CLASSES,
+            Self::Rib => ARMOUR_CLASSES,
+            Self::Collarbone => &["Amulet", "Ring", "Belt"],
+        }
+    }
+}
+
+/// Grade of a desecration bone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoneGrade {
+    /// Only for items of level 64 or lower
+    Gnawed,
+    Preserved,
+    /// The unveiled mod has a minimum modifier level of 40
+    Ancient,
+}
+
+impl BoneGrade {
+    const GNAWED_MAX_ILVL: u32 = 64;
+    const ANCIENT_MIN_MOD_LEVEL: u32 = 40;
+}
+
+/// Adds a veiled mod, to be revealed later with an Unveil
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desecrate {
+    name: &'static str,
+    bone: Bone,
+    grade: BoneGrade,
+}
+
 impl Currency for Desecrate {
     fn name(&self) -> &str {
-        "Descrate Item"
+        self.name
     }
 
     fn can_be_used(
         &self,
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
-        _omens: &HashSet<Omen>,
-    ) -> bool {
-        // TOOD: Check for existing veiled/desecrated mod
-        item.rarity == Rarity::Rare
+        omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Rare)?;
+
+        let item_class = ITEM_CLASSES.get(&item.base_type);
+        if !item_class.is_some_and(|class| self.bone.item_classes().contains(&class.as_str())) {
+            return Err(CraftError::WrongBaseType {
+                base_type: item.base_type.clone(),
+            });
+        }
+
+        if self.grade == BoneGrade::Gnawed && item.item_level > BoneGrade::GNAWED_MAX_ILVL {
+            return Err(CraftError::ItemLevelTooHigh {
+                max: BoneGrade::GNAWED_MAX_ILVL,
+            });
+        }
+
+        if !item.desecrated.is_empty() {
+            return Err(CraftError::AlreadyDesecrated);
+        }
+
+        // A full item needs a mod it can remove to make room
+        if let Some(annul_omens) = self.make_room_omens(item, omens) {
+            Annulment.can_be_used(item, &[], &annul_omens)?;
+        }
+
+        Ok(())
     }
 
     fn craft(
@@ -905,58 +1755,103 @@ impl Currency for Desecrate {
         item: &mut ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
-        // TODO: Clean up this monstrosity
-        let mut remove = 0; // 0 == neither, 1 == prefix, 2 == suffix, 3 == either
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome {
+            omens: omens.clone(),
+            ..Default::default()
+        };
 
-        if omens.contains(&Omen::Sinistral) && !item.has_room(Affix::Prefix) {
-            remove = 1;
-        } else if omens.contains(&Omen::Dextral) && !item.has_room(Affix::Suffix) {
-            remove = 2;
-        } else if item.mods.len() == 6 {
-            remove = 3;
+        if let Some(annul_omens) = self.make_room_omens(item, omens) {
+            outcome.merge(Annulment.craft(item, &[], &annul_omens, rng));
         }
 
-        let mut annul_hm = HashSet::new();
-        match remove {
-            1 => {
-                annul_hm.insert(Omen::Sinistral);
-            }
-            2 => {
-                annul_hm.insert(Omen::Dextral);
-            }
-            _ => (),
-        }
-        if remove != 0 {
-            Annulment.craft(item, &[], &annul_hm);
+        let candidate_tiers = self.filter_veiled(item, omens, &mut outcome);
+
+        let weights = vec![1; candidate_tiers.len()];
+        let choice = *util::rand::choice(rng, &candidate_tiers, &weights);
+
+        outcome.add_mod(item, choice, rng);
+        self.veil(item, choice);
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let removed = match self.make_room_omens(item, omens) {
+            Some(annul_omens) => Annulment.outcomes(item, &[], &annul_omens)?,
+            None => ItemDistribution::certain(item.clone()),
+        };
+
+        let outcomes = removed.flat_map(|item| {
+            let candidate_tiers = self.filter_veiled(item, omens, &mut CraftOutcome::default());
+            ItemDistribution::uniform(&candidate_tiers, |&tier_id| {
+                let mut item = item.clone();
+                item.mods.push(tier_id);
+                self.veil(&mut item, tier_id);
+                item
+            })
+        });
+
+        Some(outcomes)
+    }
+}
+
+impl Desecrate {
+    /// If the item is full, the omens for the Annulment used to make room for the veiled mod
+    fn make_room_omens(&self, item: &ItemState, omens: &HashSet<Omen>) -> Option<HashSet<Omen>> {
+        if omens.contains(&Omen::SinistralNecromancy) && !item.has_room(Affix::Prefix) {
+            Some(HashSet::from([Omen::SinistralNecromancy]))
+        } else if omens.contains(&Omen::DextralNecromancy) && !item.has_room(Affix::Suffix) {
+            Some(HashSet::from([Omen::DextralNecromancy]))
+        } else if item.mods.len() >= item.max_mods() {
+            Some(HashSet::new())
+        } else {
+            None
         }
+    }
 
+    /// Veiled mods which can be added
+    fn filter_veiled(
+        &self,
+        item: &ItemState,
+        omens: &HashSet<Omen>,
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
         let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
-            Box::new([TIERS.opaque("VeiledPrefix"), TIERS.opaque("VeiledSuffix")].into_iter());
+            Box::new(veiled_tiers().into_iter());
 
         // Apply omens
-        if omens.contains(&Omen::Dextral) || !item.has_room(Affix::Prefix) {
+        if omens_restrict_to(omens, Affix::Suffix) || !item.has_room(Affix::Prefix) {
             // filter suffixes
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
         }
-        if omens.contains(&Omen::Sinistral) || !item.has_room(Affix::Suffix) {
+        if omens_restrict_to(omens, Affix::Prefix) || !item.has_room(Affix::Suffix) {
             // filter prefixes
             candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
         }
 
-        let candidate_tiers = candidate_tiers.collect::<Vec<_>>();
-
-        let weights = vec![1; candidate_tiers.len()];
-        let choice = *util::rand::choice(&candidate_tiers, &weights);
+        candidate_tiers.collect()
+    }
 
-        item.mods.push(choice);
+    /// Mark a newly added veiled mod as desecrated by this bone
+    fn veil(&self, item: &mut ItemState, tier_id: OpaqueIndex<Tier>) {
+        item.desecrated.push(tier_id);
+        item.veiled_min_level =
+            (self.grade == BoneGrade::Ancient).then_some(BoneGrade::ANCIENT_MIN_MOD_LEVEL);
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct Unveil {
-    /// List of mods that the user would pick if they saw them
-    priority_list: Vec<OpaqueIndex<Tier>>,
+    /// List of mods that the user would pick if they saw them, best first
+    pub priority_list: Vec<OpaqueIndex<Tier>>,
 }
 
 impl PartialEq for Unveil {
@@ -975,58 +1870,591 @@ impl Currency for Unveil {
         item: &ItemState,
         _candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) -> bool {
-        let veiled_tiers = [TIERS.opaque("VeiledPrefix"), TIERS.opaque("VeiledSuffix")];
-        item.mods
+    ) -> Result<(), CraftError> {
+        if !item
+            .mods
+            .iter()
+            .any(|tier_id| veiled_tiers().contains(tier_id))
+        {
+            return Err(CraftError::NoVeiledMod);
+        }
+
+        // Mods which can't roll on the base have no weight, so are never revealed
+        let mut outcome = CraftOutcome::default();
+        let candidate_tiers = self.filter_unveiled(item, &mut outcome);
+        if !candidate_tiers
             .iter()
-            .any(|tier_id| veiled_tiers.contains(tier_id))
+            .any(|&tier_id| item.weight(tier_id) > 0)
+        {
+            return Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: outcome.filters,
+            });
+        }
+
+        Ok(())
     }
 
     fn craft(
         &self,
         item: &mut ItemState,
-        candidate_tiers: &[OpaqueIndex<Tier>],
+        _candidate_tiers: &[OpaqueIndex<Tier>],
         _omens: &HashSet<Omen>,
-    ) {
-        let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
-            Box::new(candidate_tiers.iter().copied());
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+        let candidate_tiers = self.filter_unveiled(item, &mut outcome);
 
-        let tier_id_prefix = TIERS.opaque("VeiledPrefix");
-        let tier_id_suffix = TIERS.opaque("VeiledSuffix");
-        if item.mods.contains(&tier_id_prefix) {
-            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
-        } else if item.mods.contains(&tier_id_suffix) {
-            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
-        }
-
-        let candidate_tiers = candidate_tiers.collect::<Vec<_>>();
-
-        // Choose 3 random mods - TODO: include desecrated mods
+        // Reveal up to 3 random mods
         let mut weights = candidate_tiers
             .iter()
-            .map(|&tier_id| TIERS[tier_id].weight)
+            .map(|&tier_id| item.weight(tier_id))
             .collect::<Vec<_>>();
-        let mut choices = vec![];
-        for _ in 0..3 {
-            let index =
-                *util::rand::choice(&(0..candidate_tiers.len()).collect::<Vec<_>>(), &weights);
+        let mut revealed = vec![];
+        while revealed.len() < Self::NUM_REVEALED && weights.iter().any(|&w| w > 0) {
+            let index = *util::rand::choice(
+                rng,
+                &(0..candidate_tiers.len()).collect::<Vec<_>>(),
+                &weights,
+            );
 
             // Zero out weight so we don't pick it twice
-            choices.push(candidate_tiers[index]);
+            revealed.push(candidate_tiers[index]);
             weights[index] = 0;
         }
 
-        // Take the highest by priority
-        let choice = choices
-            .into_iter()
-            .min_by_key(|tier_id| self.priority_list.iter().position(|p| p == tier_id))
-            .expect("Tier not in priority list");
+        // Nothing could be revealed, so the item is left as-is
+        let Some(choice) = self.best(&revealed) else {
+            return outcome;
+        };
+
+        // Replace veiled mod
+        for tier_id in veiled_tiers() {
+            if item.mods.contains(&tier_id) {
+                outcome.remove_mod(item, tier_id);
+            }
+        }
+
+        outcome.add_mod(item, choice, rng);
+        item.desecrated.push(choice);
+        outcome
+    }
 
-        // Remove veiled mod
-        item.mods
-            .retain(|tier_id| ![tier_id_prefix, tier_id_suffix].contains(tier_id));
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let candidate_tiers = self.filter_unveiled(item, &mut CraftOutcome::default());
+        let mut weights = candidate_tiers
+            .iter()
+            .map(|&tier_id| item.weight(tier_id))
+            .collect::<Vec<_>>();
+        let mut chances = HashMap::new();
+        self.choice_chances(
+            &candidate_tiers,
+            &mut weights,
+            &mut vec![],
+            1.,
+            &mut chances,
+        );
+
+        let outcomes = chances.into_iter().fold(
+            ItemDistribution::default(),
+            |mut dist, (choice, probability)| {
+                let mut item = item.clone();
+                for tier_id in veiled_tiers() {
+                    item.remove_mod(tier_id);
+                }
+                item.mods.push(choice);
+                item.desecrated.push(choice);
+                dist.add(item, probability);
+                dist
+            },
+        );
 
-        item.mods.push(choice);
+        Some(outcomes)
+    }
+}
+
+impl Unveil {
+    /// Number of mods revealed to choose from
+    const NUM_REVEALED: usize = 3;
+
+    /// The revealed mod highest by priority, anything not on the list comes last
+    fn best(&self, revealed: &[OpaqueIndex<Tier>]) -> Option<OpaqueIndex<Tier>> {
+        revealed.iter().copied().min_by_key(|tier_id| {
+            self.priority_list
+                .iter()
+                .position(|p| p == tier_id)
+                .unwrap_or(usize::MAX)
+        })
+    }
+
+    /// Add up the chance of each mod being chosen, by going through every order
+    /// the mods could be revealed in after those already revealed
+    fn choice_chances(
+        &self,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        weights: &mut [u32],
+        revealed: &mut Vec<OpaqueIndex<Tier>>,
+        probability: f64,
+        chances: &mut HashMap<OpaqueIndex<Tier>, f64>,
+    ) {
+        let total = weights.iter().map(|&w| w as f64).sum::<f64>();
+        if revealed.len() == Self::NUM_REVEALED || total == 0. {
+            if let Some(choice) = self.best(revealed) {
+                *chances.entry(choice).or_default() += probability;
+            }
+            return;
+        }
+
+        for index in 0..candidate_tiers.len() {
+            let weight = weights[index];
+            if weight == 0 {
+                continue;
+            }
+
+            weights[index] = 0;
+            revealed.push(candidate_tiers[index]);
+            self.choice_chances(
+                candidate_tiers,
+                weights,
+                revealed,
+                probability * weight as f64 / total,
+                chances,
+            );
+            revealed.pop();
+            weights[index] = weight;
+        }
+    }
+
+    /// Desecrated mods which could be revealed in place of the veiled mod
+    fn filter_unveiled(
+        &self,
+        item: &ItemState,
+        outcome: &mut CraftOutcome,
+    ) -> Vec<OpaqueIndex<Tier>> {
+        let [tier_id_prefix, tier_id_suffix] = veiled_tiers();
+        let mut pool = get_desecrated_mods_for_item(item);
+        if let Some(min_level) = item.veiled_min_level {
+            pool = filter_better_currency(&pool, min_level);
+            outcome.restrict(FilterStage::MinModLevel(min_level));
+        }
+
+        let mut candidate_tiers: Box<dyn Iterator<Item = OpaqueIndex<Tier>>> =
+            Box::new(pool.into_iter());
+
+        if item.mods.contains(&tier_id_prefix) {
+            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Prefix));
+            outcome.restrict(FilterStage::Affix(Affix::Prefix));
+        } else if item.mods.contains(&tier_id_suffix) {
+            candidate_tiers = Box::new(filter_affix(candidate_tiers, Affix::Suffix));
+            outcome.restrict(FilterStage::Affix(Affix::Suffix));
+        }
+
+        let families = item.mod_familities();
+        if !families.is_empty() {
+            candidate_tiers = Box::new(filter_out_families(candidate_tiers, families));
+            outcome.restrict(FilterStage::Families);
+        }
+
+        candidate_tiers.collect()
+    }
+}
+
+pub struct Fracturing;
+impl Currency for Fracturing {
+    fn name(&self) -> &str {
+        "Fracturing"
+    }
+
+    fn can_be_used(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        require_rarity(item, Rarity::Rare)?;
+        if item.mods.len() < 4 {
+            return Err(CraftError::TooFewMods { min: 4 });
+        }
+        if !item.fractured.is_empty() {
+            return Err(CraftError::AlreadyFractured);
+        }
+
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let weights = vec![1; item.mods.len()];
+        let choice = *util::rand::choice(rng, &item.mods, &weights);
+
+        item.fractured.push(choice);
+        CraftOutcome {
+            fractured: Some(choice),
+            ..Default::default()
+        }
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        Some(ItemDistribution::uniform(&item.mods, |&tier_id| {
+            let mut item = item.clone();
+            item.fractured.push(tier_id);
+            item
+        }))
+    }
+}
+
+/// What can happen when an item is corrupted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaalOutcome {
+    /// The item is corrupted with no other changes
+    NoChange,
+    /// Adds a random corrupted implicit
+    Implicit,
+    /// Rerolls the values of the item's mods
+    /// TODO: Values can go outside the tier's range in game, for now this is the same as a Divine
+    RerollValues,
+}
+
+/// Relative weights of each outcome. The real odds aren't published, so they're assumed equal
+/// and results using them are only approximate.
+pub const VAAL_OUTCOMES: [(VaalOutcome, u32); 3] = [
+    (VaalOutcome::NoChange, 1),
+    (VaalOutcome::Implicit, 1),
+    (VaalOutcome::RerollValues, 1),
+];
+
+pub struct Vaal;
+impl Currency for Vaal {
+    fn name(&self) -> &str {
+        "Vaal"
+    }
+
+    fn can_be_used(
+        &self,
+        _item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        // Anything that isn't already corrupted, which is checked for every currency
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome {
+            corrupted: true,
+            omens: omens.clone(),
+            ..Default::default()
+        };
+        item.corrupted = true;
+
+        let (outcomes, weights): (Vec<_>, Vec<_>) =
+            self.possible_outcomes(omens).into_iter().unzip();
+        match util::rand::choice(rng, &outcomes, &weights) {
+            VaalOutcome::NoChange => (),
+            VaalOutcome::RerollValues => {
+                outcome.merge(Divine.craft(item, &[], &HashSet::new(), rng));
+            }
+            VaalOutcome::Implicit => {
+                // Implicit weights aren't in the data, so choose uniformly
+                let implicits = get_corrupted_implicits_for_item(item);
+                if !implicits.is_empty() {
+                    let weights = vec![1; implicits.len()];
+                    let choice = *util::rand::choice(rng, &implicits, &weights);
+
+                    item.implicits.push(choice);
+                    outcome.added.push(choice);
+                }
+            }
+        }
+
+        outcome
+    }
+
+    // No exact outcomes: VAAL_OUTCOMES are a guess until the real odds are known, so corrupting
+    // is only ever estimated by sampling
+}
+
+impl Vaal {
+    /// Outcomes which can happen with the given omens, with their weights
+    fn possible_outcomes(&self, omens: &HashSet<Omen>) -> Vec<(VaalOutcome, u32)> {
+        VAAL_OUTCOMES
+            .into_iter()
+            // Omen of Corruption guarantees that the item changes
+            .filter(|&(outcome, _)| {
+                !(omens.contains(&Omen::Corruption) && outcome == VaalOutcome::NoChange)
+            })
+            .collect()
+    }
+}
+
+pub struct Divine;
+impl Currency for Divine {
+    fn name(&self) -> &str {
+        "Divine"
+    }
+
+    fn can_be_used(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        if item.mods.is_empty() {
+            return Err(CraftError::NoMods);
+        }
+
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        let mut outcome = CraftOutcome::default();
+
+        // Fractured mods keep their values
+        let tier_ids = item
+            .implicits
+            .iter()
+            .chain(&item.mods)
+            .filter(|tier_id| !item.fractured.contains(tier_id))
+            .copied()
+            .collect::<Vec<_>>();
+        for tier_id in tier_ids {
+            outcome.reroll(item, tier_id, rng);
+        }
+
+        outcome
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        // Only the rolled values change, which exact outcomes don't track
+        Some(ItemDistribution::certain(item.clone()))
+    }
+}
+
+/// Adds catalyst quality to jewellery, replacing quality of a different type
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catalyst {
+    quality_type: QualityType,
+}
+
+impl Catalyst {
+    /// Quality added per use, which goes down with rarity
+    fn quality_per_use(item: &ItemState) -> u32 {
+        match item.rarity {
+            Rarity::Normal => 5,
+            Rarity::Magic => 2,
+            Rarity::Rare => 1,
+        }
+    }
+
+    /// Apply the catalyst, which always has the same result
+    fn apply(&self, item: &mut ItemState) {
+        if item.quality_type != Some(self.quality_type) {
+            item.quality_type = Some(self.quality_type);
+            item.quality = 0;
+        }
+        item.quality = (item.quality + Self::quality_per_use(item)).min(ItemState::MAX_QUALITY);
+    }
+}
+
+impl Currency for Catalyst {
+    fn name(&self) -> &str {
+        self.quality_type.catalyst()
+    }
+
+    fn can_be_used(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        let item_class = ITEM_CLASSES.get(&item.base_type);
+        if !item_class.is_some_and(|class| ["Amulet", "Ring"].contains(&class.as_str())) {
+            return Err(CraftError::WrongBaseType {
+                base_type: item.base_type.clone(),
+            });
+        }
+
+        if item.quality_type == Some(self.quality_type) && item.quality >= ItemState::MAX_QUALITY {
+            return Err(CraftError::MaxQuality {
+                max: ItemState::MAX_QUALITY,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+        _rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        self.apply(item);
+        CraftOutcome::default()
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let mut item = item.clone();
+        self.apply(&mut item);
+        Some(ItemDistribution::certain(item))
+    }
+}
+
+pub struct Artificer;
+impl Currency for Artificer {
+    fn name(&self) -> &str {
+        "Artificer's Orb"
+    }
+
+    fn can_be_used(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        let max = item.max_sockets();
+        if max == 0 {
+            return Err(CraftError::WrongBaseType {
+                base_type: item.base_type.clone(),
+            });
+        }
+        if item.sockets.len() >= max {
+            return Err(CraftError::MaxSockets { max });
+        }
+
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+        _rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        item.sockets.push(None);
+        CraftOutcome::default()
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let mut item = item.clone();
+        item.sockets.push(None);
+        Some(ItemDistribution::certain(item))
+    }
+}
+
+/// Socketing a rune, soul core or talisman into the first empty socket
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rune {
+    pub name: SocketableId,
+}
+
+impl Rune {
+    fn socket(&self, item: &mut ItemState) {
+        if let Some(socket) = item.sockets.iter_mut().find(|socket| socket.is_none()) {
+            *socket = Some(self.name.clone());
+        }
+    }
+}
+
+impl Currency for Rune {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn can_be_used(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Result<(), CraftError> {
+        // Only usable if it grants stats for this class of item
+        let socketable = &SOCKETABLES[&self.name];
+        let stats = ITEM_CLASSES.get(&item.base_type).and_then(|class| {
+            if WEAPON_CLASSES.contains(&class.as_str()) {
+                Some(&socketable.weapon_stats)
+            } else if ARMOUR_CLASSES.contains(&class.as_str()) {
+                Some(&socketable.armour_stats)
+            } else {
+                None
+            }
+        });
+        if stats.is_none_or(|stats| stats.is_empty()) {
+            return Err(CraftError::WrongBaseType {
+                base_type: item.base_type.clone(),
+            });
+        }
+
+        if !item.sockets.contains(&None) {
+            return Err(CraftError::NoEmptySocket);
+        }
+
+        Ok(())
+    }
+
+    fn craft(
+        &self,
+        item: &mut ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+        _rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        self.socket(item);
+        CraftOutcome::default()
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        _candidate_tiers: &[OpaqueIndex<Tier>],
+        _omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let mut item = item.clone();
+        self.socket(&mut item);
+        Some(ItemDistribution::certain(item))
     }
 }
 
@@ -1051,8 +2479,14 @@ pub enum CurrencyType {
     PerfectChaos,
     Essence(Essence),
     PerfectEssence(PerfectEssence),
-    Desecrate,
+    Desecrate(Desecrate),
     Unveil(Unveil),
+    Fracturing,
+    Vaal,
+    Divine,
+    Catalyst(Catalyst),
+    Artificer,
+    Rune(Rune),
 }
 
 impl Currency for CurrencyType {
@@ -1077,8 +2511,14 @@ impl Currency for CurrencyType {
             Self::PerfectChaos => PerfectChaos.name(),
             Self::Essence(essence) => essence.name(),
             Self::PerfectEssence(essence) => essence.name(),
-            Self::Desecrate => Desecrate.name(),
+            Self::Desecrate(desecrate) => desecrate.name(),
             Self::Unveil(unveil) => unveil.name(),
+            Self::Fracturing => Fracturing.name(),
+            Self::Vaal => Vaal.name(),
+            Self::Divine => Divine.name(),
+            Self::Catalyst(catalyst) => catalyst.name(),
+            Self::Artificer => Artificer.name(),
+            Self::Rune(rune) => rune.name(),
         }
     }
 
@@ -1087,7 +2527,39 @@ impl Currency for CurrencyType {
         item: &ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) -> bool {
+    ) -> Result<(), CraftError> {
+        // Nothing can be used on a corrupted item, though empty sockets can still be filled
+        if item.corrupted && !matches!(self, Self::Rune(_)) {
+            return Err(CraftError::Corrupted);
+        }
+
+        let possible_omens = self.possible_omens();
+        if let Some(&omen) = omens.iter().find(|omen| !possible_omens.contains(omen)) {
+            return Err(CraftError::WrongOmen { omen });
+        }
+        if let Some((&first, &second)) = omens
+            .iter()
+            .sorted()
+            .tuple_combinations()
+            .find(|(first, second)| first.conflicts_with(second))
+        {
+            return Err(CraftError::ConflictingOmens { first, second });
+        }
+
+        // A foreseen outcome is applied by the currency that was previewed. Any other currency
+        // discards it and is used as normal
+        if item
+            .foreseen
+            .as_ref()
+            .is_some_and(|foreseen| foreseen.currency == self.name())
+        {
+            return Ok(());
+        }
+
+        if omens.contains(&Omen::HinekorasLock) {
+            return self.can_be_used(item, candidate_tiers, &without_lock(omens));
+        }
+
         match self {
             Self::Transmute => Transmute.can_be_used(item, candidate_tiers, omens),
             Self::GreaterTransmute => GreaterTransmute.can_be_used(item, candidate_tiers, omens),
@@ -1112,8 +2584,14 @@ impl Currency for CurrencyType {
             Self::PerfectChaos => PerfectChaos.can_be_used(item, candidate_tiers, omens),
             Self::Essence(essence) => essence.can_be_used(item, candidate_tiers, omens),
             Self::PerfectEssence(essence) => essence.can_be_used(item, candidate_tiers, omens),
-            Self::Desecrate => Desecrate.can_be_used(item, candidate_tiers, omens),
+            Self::Desecrate(desecrate) => desecrate.can_be_used(item, candidate_tiers, omens),
             Self::Unveil(unveil) => unveil.can_be_used(item, candidate_tiers, omens),
+            Self::Fracturing => Fracturing.can_be_used(item, candidate_tiers, omens),
+            Self::Vaal => Vaal.can_be_used(item, candidate_tiers, omens),
+            Self::Divine => Divine.can_be_used(item, candidate_tiers, omens),
+            Self::Catalyst(catalyst) => catalyst.can_be_used(item, candidate_tiers, omens),
+            Self::Artificer => Artificer.can_be_used(item, candidate_tiers, omens),
+            Self::Rune(rune) => rune.can_be_used(item, candidate_tiers, omens),
         }
     }
 
@@ -1122,29 +2600,133 @@ impl Currency for CurrencyType {
         item: &mut ItemState,
         candidate_tiers: &[OpaqueIndex<Tier>],
         omens: &HashSet<Omen>,
-    ) {
+        rng: &mut CraftRng,
+    ) -> CraftOutcome {
+        // Taken either way, as other currencies discard the foreseen outcome
+        if let Some(foreseen) = item.foreseen.take()
+            && foreseen.currency == self.name()
+        {
+            let before = item.clone();
+            *item = *foreseen.item;
+            return CraftOutcome::between(&before, item);
+        }
+
+        if omens.contains(&Omen::HinekorasLock) {
+            let mut preview = item.clone();
+            self.craft(&mut preview, candidate_tiers, &without_lock(omens), rng);
+            item.foreseen = Some(Foreseen {
+                currency: self.name().to_string(),
+                item: Box::new(preview),
+            });
+
+            return CraftOutcome {
+                omens: HashSet::from([Omen::HinekorasLock]),
+                ..Default::default()
+            };
+        }
+
+        match self {
+            Self::Transmute => Transmute.craft(item, candidate_tiers, omens, rng),
+            Self::GreaterTransmute => GreaterTransmute.craft(item, candidate_tiers, omens, rng),
+            Self::PerfectTransmute => PerfectTransmute.craft(item, candidate_tiers, omens, rng),
+            Self::Augmentation => Augmentation.craft(item, candidate_tiers, omens, rng),
+            Self::GreaterAugmentation => {
+                GreaterAugmentation.craft(item, candidate_tiers, omens, rng)
+            }
+            Self::PerfectAugmentation => {
+                PerfectAugmentation.craft(item, candidate_tiers, omens, rng)
+            }
+            Self::Regal => Regal.craft(item, candidate_tiers, omens, rng),
+            Self::GreaterRegal => GreaterRegal.craft(item, candidate_tiers, omens, rng),
+            Self::PerfectRegal => PerfectRegal.craft(item, candidate_tiers, omens, rng),
+            Self::Exalt => Exalt.craft(item, candidate_tiers, omens, rng),
+            Self::GreaterExalt => GreaterExalt.craft(item, candidate_tiers, omens, rng),
+            Self::PerfectExalt => PerfectExalt.craft(item, candidate_tiers, omens, rng),
+            Self::Annulment => Annulment.craft(item, candidate_tiers, omens, rng),
+            Self::Alchemy => Alchemy.craft(item, candidate_tiers, omens, rng),
+            Self::Chaos => Chaos.craft(item, candidate_tiers, omens, rng),
+            Self::GreaterChaos => GreaterChaos.craft(item, candidate_tiers, omens, rng),
+            Self::PerfectChaos => PerfectChaos.craft(item, candidate_tiers, omens, rng),
+            Self::Essence(essence) => essence.craft(item, candidate_tiers, omens, rng),
+            Self::PerfectEssence(essence) => essence.craft(item, candidate_tiers, omens, rng),
+            Self::Desecrate(desecrate) => desecrate.craft(item, candidate_tiers, omens, rng),
+            Self::Unveil(unveil) => unveil.craft(item, candidate_tiers, omens, rng),
+            Self::Fracturing => Fracturing.craft(item, candidate_tiers, omens, rng),
+            Self::Vaal => Vaal.craft(item, candidate_tiers, omens, rng),
+            Self::Divine => Divine.craft(item, candidate_tiers, omens, rng),
+            Self::Catalyst(catalyst) => catalyst.craft(item, candidate_tiers, omens, rng),
+            Self::Artificer => Artificer.craft(item, candidate_tiers, omens, rng),
+            Self::Rune(rune) => rune.craft(item, candidate_tiers, omens, rng),
+        }
+    }
+
+    fn outcomes(
+        &self,
+        item: &ItemState,
+        candidate_tiers: &[OpaqueIndex<Tier>],
+        omens: &HashSet<Omen>,
+    ) -> Option<ItemDistribution> {
+        let discarded;
+        let item = match &item.foreseen {
+            Some(foreseen) if foreseen.currency == self.name() => {
+                return Some(ItemDistribution::certain((*foreseen.item).clone()));
+            }
+            Some(_) => {
+                discarded = ItemState {
+                    foreseen: None,
+                    ..item.clone()
+                };
+                &discarded
+            }
+            None => item,
+        };
+
+        if omens.contains(&Omen::HinekorasLock) {
+            let previews = self.outcomes(item, candidate_tiers, &without_lock(omens))?;
+            let outcomes =
+                previews
+                    .iter()
+                    .fold(ItemDistribution::default(), |mut dist, (preview, p)| {
+                        let mut item = item.clone();
+                        item.foreseen = Some(Foreseen {
+                            currency: self.name().to_string(),
+                            item: Box::new(preview.clone()),
+                        });
+                        dist.add(item, p);
+                        dist
+                    });
+
+            return Some(outcomes);
+        }
+
         match self {
-            Self::Transmute => Transmute.craft(item, candidate_tiers, omens),
-            Self::GreaterTransmute => GreaterTransmute.craft(item, candidate_tiers, omens),
-            Self::PerfectTransmute => PerfectTransmute.craft(item, candidate_tiers, omens),
-            Self::Augmentation => Augmentation.craft(item, candidate_tiers, omens),
-            Self::GreaterAugmentation => GreaterAugmentation.craft(item, candidate_tiers, omens),
-            Self::PerfectAugmentation => PerfectAugmentation.craft(item, candidate_tiers, omens),
-            Self::Regal => Regal.craft(item, candidate_tiers, omens),
-            Self::GreaterRegal => GreaterRegal.craft(item, candidate_tiers, omens),
-            Self::PerfectRegal => PerfectRegal.craft(item, candidate_tiers, omens),
-            Self::Exalt => Exalt.craft(item, candidate_tiers, omens),
-            Self::GreaterExalt => GreaterExalt.craft(item, candidate_tiers, omens),
-            Self::PerfectExalt => PerfectExalt.craft(item, candidate_tiers, omens),
-            Self::Annulment => Annulment.craft(item, candidate_tiers, omens),
-            Self::Alchemy => Alchemy.craft(item, candidate_tiers, omens),
-            Self::Chaos => Chaos.craft(item, candidate_tiers, omens),
-            Self::GreaterChaos => GreaterChaos.craft(item, candidate_tiers, omens),
-            Self::PerfectChaos => PerfectChaos.craft(item, candidate_tiers, omens),
-            Self::Essence(essence) => essence.craft(item, candidate_tiers, omens),
-            Self::PerfectEssence(essence) => essence.craft(item, candidate_tiers, omens),
-            Self::Desecrate => Desecrate.craft(item, candidate_tiers, omens),
-            Self::Unveil(unveil) => unveil.craft(item, candidate_tiers, omens),
+            Self::Transmute => Transmute.outcomes(item, candidate_tiers, omens),
+            Self::GreaterTransmute => GreaterTransmute.outcomes(item, candidate_tiers, omens),
+            Self::PerfectTransmute => PerfectTransmute.outcomes(item, candidate_tiers, omens),
+            Self::Augmentation => Augmentation.outcomes(item, candidate_tiers, omens),
+            Self::GreaterAugmentation => GreaterAugmentation.outcomes(item, candidate_tiers, omens),
+            Self::PerfectAugmentation => PerfectAugmentation.outcomes(item, candidate_tiers, omens),
+            Self::Regal => Regal.outcomes(item, candidate_tiers, omens),
+            Self::GreaterRegal => GreaterRegal.outcomes(item, candidate_tiers, omens),
+            Self::PerfectRegal => PerfectRegal.outcomes(item, candidate_tiers, omens),
+            Self::Exalt => Exalt.outcomes(item, candidate_tiers, omens),
+            Self::GreaterExalt => GreaterExalt.outcomes(item, candidate_tiers, omens),
+            Self::PerfectExalt => PerfectExalt.outcomes(item, candidate_tiers, omens),
+            Self::Annulment => Annulment.outcomes(item, candidate_tiers, omens),
+            Self::Alchemy => Alchemy.outcomes(item, candidate_tiers, omens),
+            Self::Chaos => Chaos.outcomes(item, candidate_tiers, omens),
+            Self::GreaterChaos => GreaterChaos.outcomes(item, candidate_tiers, omens),
+            Self::PerfectChaos => PerfectChaos.outcomes(item, candidate_tiers, omens),
+            Self::Essence(essence) => essence.outcomes(item, candidate_tiers, omens),
+            Self::PerfectEssence(essence) => essence.outcomes(item, candidate_tiers, omens),
+            Self::Desecrate(desecrate) => desecrate.outcomes(item, candidate_tiers, omens),
+            Self::Unveil(unveil) => unveil.outcomes(item, candidate_tiers, omens),
+            Self::Fracturing => Fracturing.outcomes(item, candidate_tiers, omens),
+            Self::Vaal => Vaal.outcomes(item, candidate_tiers, omens),
+            Self::Divine => Divine.outcomes(item, candidate_tiers, omens),
+            Self::Catalyst(catalyst) => catalyst.outcomes(item, candidate_tiers, omens),
+            Self::Artificer => Artificer.outcomes(item, candidate_tiers, omens),
+            Self::Rune(rune) => rune.outcomes(item, candidate_tiers, omens),
         }
     }
 }
@@ -1155,39 +2737,841 @@ impl CurrencyType {
         use CurrencyType::*;
         use Omen::*;
         let omens = match self {
-            Regal | GreaterRegal | PerfectRegal => vec![Homogenous],
-            Annulment => vec![Sinistral, Dextral, Greater],
-            Alchemy | PerfectEssence(_) | Desecrate => vec![Sinistral, Dextral],
-            Chaos | GreaterChaos | PerfectChaos => vec![Sinistral, Dextral, Whittling],
-            Exalt | GreaterExalt | PerfectExalt => {
-                vec![Sinistral, Dextral, Homogenous, Greater]
+            Regal | GreaterRegal | PerfectRegal => {
+                vec![
+                    SinistralCoronation,
+                    DextralCoronation,
+                    HomogenisingCoronation,
+                ]
             }
+            Annulment => vec![
+                SinistralAnnulment,
+                DextralAnnulment,
+                GreaterAnnulment,
+                Light,
+            ],
+            Alchemy => vec![SinistralAlchemy, DextralAlchemy],
+            PerfectEssence(_) => vec![SinistralCrystallisation, DextralCrystallisation],
+            Desecrate(_) => vec![SinistralNecromancy, DextralNecromancy],
+            Chaos | GreaterChaos | PerfectChaos => {
+                vec![SinistralErasure, DextralErasure, Whittling]
+            }
+            Exalt | GreaterExalt | PerfectExalt => vec![
+                SinistralExaltation,
+                DextralExaltation,
+                HomogenisingExaltation,
+                GreaterExaltation,
+                CatalysingExaltation,
+            ],
+            Vaal => vec![Corruption],
             _ => vec![],
         };
 
-        HashSet::from_iter(omens)
+        // Hinekora's Lock can preview any currency
+        HashSet::from_iter(omens.into_iter().chain([HinekorasLock]))
     }
 
     pub const fn all() -> &'static [Self] {
-        &[
-            Self::Transmute,
-            Self::GreaterTransmute,
-            Self::PerfectTransmute,
-            Self::Augmentation,
-            Self::GreaterAugmentation,
-            Self::PerfectAugmentation,
-            Self::Regal,
-            Self::GreaterRegal,
-            Self::PerfectRegal,
-            Self::Exalt,
-            Self::GreaterExalt,
-            Self::PerfectExalt,
-            Self::Annulment,
-            Self::Alchemy,
-            Self::Chaos,
-            Self::GreaterChaos,
-            Self::PerfectChaos,
-            Self::Desecrate,
-        ]
+        // Declared as a constant so that Unveil's empty priority list doesn't need dropping
+        const ALL: &[CurrencyType] = &[
+            CurrencyType::Transmute,
+            CurrencyType::GreaterTransmute,
+            CurrencyType::PerfectTransmute,
+            CurrencyType::Augmentation,
+            CurrencyType::GreaterAugmentation,
+            CurrencyType::PerfectAugmentation,
+            CurrencyType::Regal,
+            CurrencyType::GreaterRegal,
+            CurrencyType::PerfectRegal,
+            CurrencyType::Exalt,
+            CurrencyType::GreaterExalt,
+            CurrencyType::PerfectExalt,
+            CurrencyType::Annulment,
+            CurrencyType::Alchemy,
+            CurrencyType::Chaos,
+            CurrencyType::GreaterChaos,
+            CurrencyType::PerfectChaos,
+            CurrencyType::Desecrate(Desecrate {
+                name: "Gnawed Jawbone",
+                bone: Bone::Jawbone,
+                grade: BoneGrade::Gnawed,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Gnawed Rib",
+                bone: Bone::Rib,
+                grade: BoneGrade::Gnawed,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Gnawed Collarbone",
+                bone: Bone::Collarbone,
+                grade: BoneGrade::Gnawed,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Preserved Jawbone",
+                bone: Bone::Jawbone,
+                grade: BoneGrade::Preserved,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Preserved Rib",
+                bone: Bone::Rib,
+                grade: BoneGrade::Preserved,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Preserved Collarbone",
+                bone: Bone::Collarbone,
+                grade: BoneGrade::Preserved,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Ancient Jawbone",
+                bone: Bone::Jawbone,
+                grade: BoneGrade::Ancient,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Ancient Rib",
+                bone: Bone::Rib,
+                grade: BoneGrade::Ancient,
+            }),
+            CurrencyType::Desecrate(Desecrate {
+                name: "Ancient Collarbone",
+                bone: Bone::Collarbone,
+                grade: BoneGrade::Ancient,
+            }),
+            CurrencyType::Unveil(Unveil {
+                priority_list: Vec::new(),
+            }),
+            CurrencyType::Fracturing,
+            CurrencyType::Vaal,
+            CurrencyType::Divine,
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Life,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Mana,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Defences,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Physical,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Fire,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Cold,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Lightning,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Chaos,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Attack,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Caster,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Speed,
+            }),
+            CurrencyType::Catalyst(Catalyst {
+                quality_type: QualityType::Attribute,
+            }),
+            CurrencyType::Artificer,
+        ];
+
+        ALL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use rand::SeedableRng;
+
+    use crate::{
+        CURRENCIES, TIERS,
+        crafting::FilterStage,
+        currency::{
+            Bone, BoneGrade, Catalyst, CraftError, Currency, CurrencyType, Desecrate, Essence,
+            Exalt, PerfectEssence, Rune, Unveil, Vaal, VaalOutcome,
+        },
+        distribution::ItemDistribution,
+        hashvec::OpaqueIndex,
+        internal::test_data::{self, item, ring},
+        item_state::{ItemState, Rarity, get_valid_mods_for_item},
+        types::{Affix, Omen, QualityType, Tier},
+        util::rand::CraftRng,
+    };
+
+    fn tier(id: &str) -> OpaqueIndex<Tier> {
+        TIERS.opaque(id)
+    }
+
+    /// Check that sampling the currency gives the same distribution as its exact outcomes
+    fn assert_outcomes_match(currency: &CurrencyType, item: &ItemState, omens: &[Omen]) {
+        const SAMPLES: usize = 4000;
+        let omens = HashSet::from_iter(omens.iter().copied());
+        let candidate_tiers = get_valid_mods_for_item(item);
+        currency
+            .can_be_used(item, &candidate_tiers, &omens)
+            .unwrap();
+
+        let exact = currency.outcomes(item, &candidate_tiers, &omens).unwrap();
+        assert!((exact.total() - 1.).abs() < 1e-9);
+
+        let mut rng = CraftRng::seed_from_u64(0);
+        let sampled = (0..SAMPLES).fold(ItemDistribution::default(), |mut dist, _| {
+            let mut item = item.clone();
+            currency.craft(&mut item, &candidate_tiers, &omens, &mut rng);
+            dist.add(item, 1. / SAMPLES as f64);
+            dist
+        });
+
+        let exact = exact.iter().collect::<HashMap<_, _>>();
+        for (result, p) in sampled.iter() {
+            let expected = exact.get(result).copied().unwrap_or_default();
+            assert!(
+                (p - expected).abs() < 0.03,
+                "Sampled {p} but expected {expected} for\n{result}"
+            );
+        }
+        assert_eq!(sampled.len(), exact.len());
+    }
+
+    #[test]
+    fn test_outcomes_match_craft() {
+        test_data::init();
+
+        // The first slam takes the only suffix family, so there's nothing left for the second
+        let item = ring(Rarity::Rare, &["IncreasedLife1"]);
+        assert_outcomes_match(
+            &CurrencyType::Exalt,
+            &item,
+            &[Omen::DextralExaltation, Omen::GreaterExaltation],
+        );
+        assert_outcomes_match(&CurrencyType::Exalt, &item, &[Omen::GreaterExaltation]);
+
+        // Remove then add
+        let item = ring(Rarity::Rare, &["IncreasedLife1", "FireResist1"]);
+        assert_outcomes_match(&CurrencyType::Chaos, &item, &[]);
+        assert_outcomes_match(&CurrencyType::Chaos, &item, &[Omen::Whittling]);
+        assert_outcomes_match(&CurrencyType::Annulment, &item, &[Omen::SinistralAnnulment]);
+    }
+
+    /// Every outcome of the currency keeps the fractured mods, whether exact or sampled
+    fn assert_keeps_fractured(currency: &CurrencyType, item: &ItemState, omens: &[Omen]) {
+        let omens = HashSet::from_iter(omens.iter().copied());
+        let candidate_tiers = get_valid_mods_for_item(item);
+        currency
+            .can_be_used(item, &candidate_tiers, &omens)
+            .unwrap();
+
+        let outcomes = currency.outcomes(item, &candidate_tiers, &omens).unwrap();
+        assert!(
+            outcomes
+                .removed_tiers(item)
+                .keys()
+                .all(|tier_id| !item.fractured.contains(tier_id))
+        );
+
+        let mut rng = CraftRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let mut crafted = item.clone();
+            let outcome = currency.craft(&mut crafted, &candidate_tiers, &omens, &mut rng);
+            assert!(
+                outcome
+                    .removed
+                    .iter()
+                    .all(|tier_id| !item.fractured.contains(tier_id))
+            );
+            assert!(
+                item.fractured
+                    .iter()
+                    .all(|tier_id| crafted.mods.contains(tier_id))
+            );
+            assert_eq!(crafted.fractured, item.fractured);
+        }
+    }
+
+    #[test]
+    fn test_fractured() {
+        test_data::init();
+        let mut item = ring(
+            Rarity::Rare,
+            &["IncreasedLife1", "LifeAndMana1", "FireResist1"],
+        );
+
+        // Needs at least 4 mods
+        assert_eq!(
+            CurrencyType::Fracturing.can_be_used(&item, &[], &HashSet::new()),
+            Err(CraftError::TooFewMods { min: 4 })
+        );
+        let mut full = item.clone();
+        full.mods.push(tier("EssenceMana1"));
+        CurrencyType::Fracturing
+            .can_be_used(&full, &[], &HashSet::new())
+            .unwrap();
+        CurrencyType::Fracturing.craft(
+            &mut full,
+            &[],
+            &HashSet::new(),
+            &mut CraftRng::seed_from_u64(0),
+        );
+        assert_eq!(full.fractured.len(), 1);
+        assert_eq!(
+            CurrencyType::Fracturing.can_be_used(&full, &[], &HashSet::new()),
+            Err(CraftError::AlreadyFractured)
+        );
+
+        // Fractured mods are never removed
+        item.fractured = vec![tier("IncreasedLife1")];
+        assert_keeps_fractured(&CurrencyType::Annulment, &item, &[]);
+        assert_keeps_fractured(&CurrencyType::Annulment, &item, &[Omen::SinistralAnnulment]);
+        assert_keeps_fractured(&CurrencyType::Chaos, &item, &[]);
+        assert_keeps_fractured(&CurrencyType::Chaos, &item, &[Omen::Whittling]);
+        let essence = CurrencyType::PerfectEssence(PerfectEssence {
+            name: "Perfect Essence of the Mind".to_string(),
+            tiers: HashMap::from([(item.base_type.clone(), vec![tier("EssenceMana1")])]),
+        });
+        assert_keeps_fractured(&essence, &item, &[]);
+        assert_keeps_fractured(&essence, &item, &[Omen::SinistralCrystallisation]);
+
+        // Nothing left to remove
+        let only_prefix = ItemState {
+            fractured: vec![tier("IncreasedLife1")],
+            ..ring(Rarity::Rare, &["IncreasedLife1", "FireResist1"])
+        };
+        assert_eq!(
+            CurrencyType::Annulment.can_be_used(
+                &only_prefix,
+                &[],
+                &HashSet::from([Omen::SinistralAnnulment])
+            ),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![FilterStage::Fractured, FilterStage::Affix(Affix::Prefix)],
+            })
+        );
+    }
+
+    #[test]
+    fn test_desecrate_full() {
+        test_data::init();
+        let desecrate = CurrencyType::Desecrate(Desecrate {
+            name: "Preserved Collarbone",
+            bone: Bone::Collarbone,
+            grade: BoneGrade::Preserved,
+        });
+        let mut item = ring(
+            Rarity::Rare,
+            &[
+                "IncreasedLife1",
+                "LifeAndMana1",
+                "EssenceMana1",
+                "FireResist1",
+            ],
+        );
+        let omens = HashSet::from([Omen::SinistralNecromancy]);
+
+        // Room is made by removing a prefix
+        desecrate.can_be_used(&item, &[], &omens).unwrap();
+        let mut crafted = item.clone();
+        desecrate.craft(&mut crafted, &[], &omens, &mut CraftRng::seed_from_u64(0));
+        assert_eq!(crafted.num_prefixes(), 3);
+        assert_eq!(crafted.desecrated.len(), 1);
+
+        // Unless none of them can be removed
+        item.fractured = item.mods[..3].to_vec();
+        assert_eq!(
+            desecrate.can_be_used(&item, &[], &omens),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![FilterStage::Fractured, FilterStage::Affix(Affix::Prefix)],
+            })
+        );
+    }
+
+    #[test]
+    fn test_corrupted() {
+        test_data::init();
+        let item = ring(Rarity::Rare, &["IncreasedLife1", "FireResist1"]);
+        let candidate_tiers = get_valid_mods_for_item(&item);
+        let mut corrupted = item.clone();
+        CurrencyType::Vaal.craft(
+            &mut corrupted,
+            &candidate_tiers,
+            &HashSet::new(),
+            &mut CraftRng::seed_from_u64(0),
+        );
+        assert!(corrupted.corrupted);
+
+        // The odds aren't known, so there's no exact distribution
+        assert!(
+            CurrencyType::Vaal
+                .outcomes(&item, &candidate_tiers, &HashSet::new())
+                .is_none()
+        );
+
+        // Nothing else can be used afterwards
+        for currency in CURRENCIES.iter() {
+            let result = currency.can_be_used(&corrupted, &candidate_tiers, &HashSet::new());
+            match currency {
+                // Sockets can still be filled, though rings don't have any
+                CurrencyType::Rune(_) => assert!(
+                    matches!(result, Err(CraftError::WrongBaseType { .. })),
+                    "{result:?}"
+                ),
+                _ => assert_eq!(result, Err(CraftError::Corrupted), "{}", currency.name()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_can_be_used_errors() {
+        test_data::init();
+        let no_omens = HashSet::new();
+
+        // Wrong rarity
+        let magic = ring(Rarity::Magic, &["IncreasedLife1", "FireResist1"]);
+        let candidate_tiers = get_valid_mods_for_item(&magic);
+        assert_eq!(
+            CurrencyType::Exalt.can_be_used(&magic, &candidate_tiers, &no_omens),
+            Err(CraftError::WrongRarity {
+                expected: Rarity::Rare,
+                actual: Rarity::Magic,
+            })
+        );
+        assert_eq!(
+            CurrencyType::Transmute.can_be_used(&magic, &candidate_tiers, &no_omens),
+            Err(CraftError::WrongRarity {
+                expected: Rarity::Normal,
+                actual: Rarity::Magic,
+            })
+        );
+
+        // No room
+        assert_eq!(
+            CurrencyType::Augmentation.can_be_used(&magic, &candidate_tiers, &no_omens),
+            Err(CraftError::TooManyMods { max: 2 })
+        );
+        let prefixes = ring(
+            Rarity::Rare,
+            &["IncreasedLife1", "LifeAndMana1", "EssenceMana1"],
+        );
+        let candidate_tiers = get_valid_mods_for_item(&prefixes);
+        assert_eq!(
+            CurrencyType::Exalt.can_be_used(
+                &prefixes,
+                &candidate_tiers,
+                &HashSet::from([Omen::SinistralExaltation])
+            ),
+            Err(CraftError::NotEnoughRoom {
+                affix: Some(Affix::Prefix),
+                needed: 1,
+            })
+        );
+
+        // Corrupted
+        let corrupted = ItemState {
+            corrupted: true,
+            ..prefixes.clone()
+        };
+        assert_eq!(
+            CurrencyType::Exalt.can_be_used(&corrupted, &candidate_tiers, &no_omens),
+            Err(CraftError::Corrupted)
+        );
+
+        // Conflicting omens
+        assert_eq!(
+            CurrencyType::Exalt.can_be_used(
+                &prefixes,
+                &candidate_tiers,
+                &HashSet::from([Omen::DextralExaltation, Omen::SinistralExaltation])
+            ),
+            Err(CraftError::ConflictingOmens {
+                first: Omen::SinistralExaltation,
+                second: Omen::DextralExaltation,
+            })
+        );
+    }
+
+    #[test]
+    fn test_zero_weight_pool() {
+        test_data::init();
+        let item = ring(Rarity::Rare, &[]);
+
+        // Essence-only tiers have no weight on the base, so can't be rolled at random
+        assert_eq!(
+            Exalt.can_be_used(&item, &[tier("EssenceMana1")], &HashSet::new()),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_essence_mod() {
+        test_data::init();
+        let essence = Essence {
+            name: "Essence of the Mind".to_string(),
+            tiers: HashMap::from([(
+                ring(Rarity::Magic, &[]).base_type,
+                vec![tier("EssenceMana1")],
+            )]),
+        };
+        let item = ring(Rarity::Magic, &["FireResist1"]);
+        let candidate_tiers = get_valid_mods_for_item(&item);
+        assert!(
+            !candidate_tiers.contains(&tier("EssenceMana1")),
+            "Essence mods aren't in the random pool"
+        );
+
+        // Added even though it has no spawn weight
+        essence
+            .can_be_used(&item, &candidate_tiers, &HashSet::new())
+            .unwrap();
+        let outcomes = essence
+            .outcomes(&item, &candidate_tiers, &HashSet::new())
+            .unwrap();
+        assert_eq!(outcomes.len(), 1);
+        let mut crafted = item.clone();
+        essence.craft(
+            &mut crafted,
+            &candidate_tiers,
+            &HashSet::new(),
+            &mut CraftRng::seed_from_u64(0),
+        );
+        assert_eq!(crafted.rarity, Rarity::Rare);
+        assert!(crafted.mods.contains(&tier("EssenceMana1")));
+        assert!(crafted.is_valid());
+    }
+
+    #[test]
+    fn test_catalyst_bias() {
+        test_data::init();
+        let xoph = CurrencyType::Catalyst(Catalyst {
+            quality_type: QualityType::Fire,
+        });
+        let mut item = ring(Rarity::Normal, &[]);
+        let mut rng = CraftRng::seed_from_u64(0);
+        while xoph.can_be_used(&item, &[], &HashSet::new()).is_ok() {
+            xoph.craft(&mut item, &[], &HashSet::new(), &mut rng);
+        }
+        assert_eq!(item.quality_type, Some(QualityType::Fire));
+        assert_eq!(item.quality, ItemState::MAX_QUALITY);
+        assert_eq!(QualityType::Fire.tag(), "Fire");
+
+        // FireResistance is tagged Fire, IncreasedLife isn't
+        let fire = tier("FireResist1");
+        let life = tier("IncreasedLife1");
+        assert_eq!(item.weight(fire), 1200);
+        assert_eq!(item.weight(life), 1000);
+        assert_eq!(item.biased_weight(fire, ItemState::CATALYSING_BIAS), 2000);
+        assert_eq!(item.biased_weight(life, ItemState::CATALYSING_BIAS), 1000);
+
+        // A different catalyst replaces the quality rather than adding to it
+        let flesh = CurrencyType::Catalyst(Catalyst {
+            quality_type: QualityType::Life,
+        });
+        flesh.craft(&mut item, &[], &HashSet::new(), &mut rng);
+        assert_eq!(item.quality_type, Some(QualityType::Life));
+        assert_eq!(item.quality, 5);
+        assert_eq!(item.weight(fire), 1000);
+        assert_eq!(item.weight(life), 1050);
+    }
+
+    #[test]
+    fn test_unveil() {
+        test_data::init();
+        let item = ItemState {
+            desecrated: vec![tier("VeiledPrefix")],
+            ..ring(Rarity::Rare, &["IncreasedLife1", "VeiledPrefix"])
+        };
+
+        // With nothing on the list, the first mod revealed is taken
+        let unveil = CurrencyType::Unveil(Unveil {
+            priority_list: vec![],
+        });
+        assert_outcomes_match(&unveil, &item, &[]);
+        let added = unveil
+            .outcomes(&item, &[], &HashSet::new())
+            .unwrap()
+            .added_tiers(&item);
+        assert!((added[&tier("DesecratedSpirit1")] - 0.5).abs() < 1e-9);
+
+        // A listed mod is taken whenever it's one of the 3 revealed
+        let unveil = CurrencyType::Unveil(Unveil {
+            priority_list: vec![tier("DesecratedEvasion1")],
+        });
+        assert_outcomes_match(&unveil, &item, &[]);
+        let added = unveil
+            .outcomes(&item, &[], &HashSet::new())
+            .unwrap()
+            .added_tiers(&item);
+        assert!((added[&tier("DesecratedEvasion1")] - 0.5988095238095239).abs() < 1e-9);
+
+        // Only Strength is left, which can't roll on rings
+        let stuck = ItemState {
+            desecrated: vec![tier("VeiledSuffix")],
+            ..ring(
+                Rarity::Rare,
+                &[
+                    "DesecratedRarity1",
+                    "DesecratedLightningResist1",
+                    "VeiledSuffix",
+                ],
+            )
+        };
+        assert_eq!(
+            unveil.can_be_used(&stuck, &[], &HashSet::new()),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![FilterStage::Affix(Affix::Suffix), FilterStage::Families],
+            })
+        );
+        let mut crafted = stuck.clone();
+        unveil.craft(
+            &mut crafted,
+            &[],
+            &HashSet::new(),
+            &mut CraftRng::seed_from_u64(0),
+        );
+        assert_eq!(crafted, stuck);
+    }
+
+    #[test]
+    fn test_omens() {
+        test_data::init();
+
+        // Omen of Light only removes desecrated mods
+        let item = ItemState {
+            desecrated: vec![tier("DesecratedRarity1")],
+            ..ring(Rarity::Rare, &["IncreasedLife1", "DesecratedRarity1"])
+        };
+        assert_outcomes_match(&CurrencyType::Annulment, &item, &[Omen::Light]);
+        let removed = CurrencyType::Annulment
+            .outcomes(&item, &[], &HashSet::from([Omen::Light]))
+            .unwrap()
+            .removed_tiers(&item);
+        assert_eq!(removed, HashMap::from([(tier("DesecratedRarity1"), 1.)]));
+        assert_eq!(
+            CurrencyType::Annulment.can_be_used(
+                &ring(Rarity::Rare, &["IncreasedLife1"]),
+                &[],
+                &HashSet::from([Omen::Light])
+            ),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![FilterStage::Desecrated],
+            })
+        );
+
+        // Omen of Corruption always changes the item
+        let corruption = HashSet::from([Omen::Corruption]);
+        assert!(
+            Vaal.possible_outcomes(&HashSet::new())
+                .iter()
+                .any(|&(outcome, _)| outcome == VaalOutcome::NoChange)
+        );
+        assert!(
+            Vaal.possible_outcomes(&corruption)
+                .iter()
+                .all(|&(outcome, _)| outcome != VaalOutcome::NoChange)
+        );
+        let mut item = ring(Rarity::Rare, &["IncreasedLife1"]);
+        let outcome =
+            CurrencyType::Vaal.craft(&mut item, &[], &corruption, &mut CraftRng::seed_from_u64(0));
+        assert!(item.corrupted);
+        assert!(!outcome.added.is_empty() || !outcome.rerolled.is_empty());
+
+        // Omen of Catalysing Exaltation makes the catalyst's tag more likely, using up the quality
+        let item = ItemState {
+            quality: ItemState::MAX_QUALITY,
+            quality_type: Some(QualityType::Fire),
+            ..ring(Rarity::Rare, &["IncreasedLife1"])
+        };
+        let fire_chance = |omens: &[Omen]| {
+            let omens = HashSet::from_iter(omens.iter().copied());
+            let candidate_tiers = get_valid_mods_for_item(&item);
+            CurrencyType::Exalt
+                .can_be_used(&item, &candidate_tiers, &omens)
+                .unwrap();
+            let outcomes = CurrencyType::Exalt
+                .outcomes(&item, &candidate_tiers, &omens)
+                .unwrap();
+            let catalysing = omens.contains(&Omen::CatalysingExaltation);
+            assert!(outcomes.iter().all(|(result, _)| {
+                (result.quality_type.is_none() && result.quality == 0) == catalysing
+            }));
+            outcomes
+                .added_tiers(&item)
+                .into_iter()
+                .filter(|&(tier_id, _)| TIERS[tier_id].id.starts_with("FireResist"))
+                .map(|(_, p)| p)
+                .sum::<f64>()
+        };
+        // Both fire tiers weigh 1200, or 2000 with the omen, against 1000 for life and mana
+        assert!((fire_chance(&[]) - 2400. / 3400.).abs() < 1e-9);
+        assert!((fire_chance(&[Omen::CatalysingExaltation]) - 4000. / 5000.).abs() < 1e-9);
+        assert_outcomes_match(&CurrencyType::Exalt, &item, &[Omen::CatalysingExaltation]);
+    }
+
+    #[test]
+    fn test_hinekoras_lock() {
+        test_data::init();
+        let item = ring(Rarity::Rare, &["IncreasedLife1"]);
+        let candidate_tiers = get_valid_mods_for_item(&item);
+        let lock = HashSet::from([Omen::HinekorasLock]);
+        let mut rng = CraftRng::seed_from_u64(0);
+
+        // Previewing leaves the item as it was, with the result foreseen
+        let mut previewed = item.clone();
+        CurrencyType::Exalt.craft(&mut previewed, &candidate_tiers, &lock, &mut rng);
+        let foreseen = previewed.foreseen.clone().unwrap();
+        assert_eq!(foreseen.currency, CurrencyType::Exalt.name());
+        assert_eq!(foreseen.item.mods.len(), 2);
+        assert_eq!(
+            ItemState {
+                foreseen: None,
+                ..previewed.clone()
+            },
+            item
+        );
+        let previews = CurrencyType::Exalt
+            .outcomes(&item, &candidate_tiers, &lock)
+            .unwrap();
+        assert!(
+            previews
+                .iter()
+                .all(|(preview, _)| preview.foreseen.is_some())
+        );
+        assert_eq!(
+            previews.len(),
+            CurrencyType::Exalt
+                .outcomes(&item, &candidate_tiers, &HashSet::new())
+                .unwrap()
+                .len()
+        );
+
+        // Omens are still checked before the foreseen outcome is applied
+        assert_eq!(
+            CurrencyType::Exalt.can_be_used(
+                &previewed,
+                &candidate_tiers,
+                &HashSet::from([Omen::Light])
+            ),
+            Err(CraftError::WrongOmen { omen: Omen::Light })
+        );
+        assert!(matches!(
+            CurrencyType::Exalt.can_be_used(
+                &previewed,
+                &candidate_tiers,
+                &HashSet::from([Omen::SinistralExaltation, Omen::DextralExaltation])
+            ),
+            Err(CraftError::ConflictingOmens { .. })
+        ));
+
+        // Using the same currency applies the foreseen outcome
+        CurrencyType::Exalt
+            .can_be_used(&previewed, &candidate_tiers, &HashSet::new())
+            .unwrap();
+        let exact = CurrencyType::Exalt
+            .outcomes(&previewed, &candidate_tiers, &HashSet::new())
+            .unwrap();
+        assert_eq!(exact.len(), 1);
+        let mut applied = previewed.clone();
+        CurrencyType::Exalt.craft(&mut applied, &candidate_tiers, &HashSet::new(), &mut rng);
+        assert_eq!(applied, *foreseen.item);
+
+        // Anything else discards it
+        let mut skipped = previewed.clone();
+        CurrencyType::Divine.craft(&mut skipped, &candidate_tiers, &HashSet::new(), &mut rng);
+        assert!(skipped.foreseen.is_none());
+        assert_eq!(skipped.mods, item.mods);
+    }
+
+    #[test]
+    fn test_sockets() {
+        test_data::init();
+        let rune = CurrencyType::Rune(Rune {
+            name: test_data::RUNE.to_string(),
+        });
+        let no_omens = HashSet::new();
+        let mut rng = CraftRng::seed_from_u64(0);
+
+        // Rings can't be socketed
+        let ring = ring(Rarity::Rare, &[]);
+        let wrong_base = Err(CraftError::WrongBaseType {
+            base_type: ring.base_type.clone(),
+        });
+        assert_eq!(ring.max_sockets(), 0);
+        assert_eq!(
+            CurrencyType::Artificer.can_be_used(&ring, &[], &no_omens),
+            wrong_base
+        );
+        assert_eq!(rune.can_be_used(&ring, &[], &no_omens), wrong_base);
+
+        // Body armour and bows have 2 sockets, gloves 1
+        assert_eq!(item("Leather Gloves", Rarity::Normal, &[]).max_sockets(), 1);
+        assert_eq!(item("Crude Bow", Rarity::Normal, &[]).max_sockets(), 2);
+        let mut vest = item("Leather Vest", Rarity::Normal, &[]);
+        assert_eq!(vest.max_sockets(), 2);
+        assert_eq!(
+            rune.can_be_used(&vest, &[], &no_omens),
+            Err(CraftError::NoEmptySocket)
+        );
+        for _ in 0..2 {
+            CurrencyType::Artificer
+                .can_be_used(&vest, &[], &no_omens)
+                .unwrap();
+            CurrencyType::Artificer.craft(&mut vest, &[], &no_omens, &mut rng);
+        }
+        assert_eq!(vest.sockets, vec![None, None]);
+        assert_eq!(
+            CurrencyType::Artificer.can_be_used(&vest, &[], &no_omens),
+            Err(CraftError::MaxSockets { max: 2 })
+        );
+
+        // Armour takes the rune's armour stats
+        rune.can_be_used(&vest, &[], &no_omens).unwrap();
+        rune.craft(&mut vest, &[], &no_omens, &mut rng);
+        assert_eq!(vest.sockets, vec![Some(test_data::RUNE.to_string()), None]);
+        assert_eq!(
+            vest.socketed_stats(),
+            vec![("base_fire_damage_resistance_%".to_string(), 12)]
+        );
+
+        // Empty sockets can still be filled once corrupted
+        vest.corrupted = true;
+        assert_eq!(
+            CurrencyType::Artificer.can_be_used(&vest, &[], &no_omens),
+            Err(CraftError::Corrupted)
+        );
+        rune.can_be_used(&vest, &[], &no_omens).unwrap();
+        let outcomes = rune.outcomes(&vest, &[], &no_omens).unwrap();
+        rune.craft(&mut vest, &[], &no_omens, &mut rng);
+        assert_eq!(outcomes.iter().next().unwrap().0, &vest);
+        assert_eq!(
+            vest.socketed_stats(),
+            vec![("base_fire_damage_resistance_%".to_string(), 24)]
+        );
+        assert_eq!(
+            rune.can_be_used(&vest, &[], &no_omens),
+            Err(CraftError::NoEmptySocket)
+        );
+
+        // Weapons take its weapon stats
+        let bow = ItemState {
+            sockets: vec![Some(test_data::RUNE.to_string())],
+            ..item("Crude Bow", Rarity::Normal, &[])
+        };
+        assert_eq!(
+            bow.socketed_stats(),
+            vec![("local_minimum_added_fire_damage".to_string(), 7)]
+        );
     }
 }
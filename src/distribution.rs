@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{hashvec::OpaqueIndex, item_state::ItemState, types::Tier};
+
+/// Exact probability distribution over the item states resulting from a craft.
+/// Identical states are merged, ignoring the order of the mods on the item.
+#[derive(Debug, Clone, Default)]
+pub struct ItemDistribution(HashMap<ItemState, f64>);
+
+/// Normalise an item so that equivalent states compare equal
+fn normalise(item: &mut ItemState) {
+    // Mod order doesn't matter, so normalise it to merge equivalent states
+    item.mods.sort_unstable();
+    item.fractured.sort_unstable();
+    item.desecrated.sort_unstable();
+    item.sockets.sort_unstable();
+    // Rolled values aren't enumerated, so drop them to merge states that only differ by them
+    item.rolls.clear();
+    if let Some(foreseen) = &mut item.foreseen {
+        normalise(&mut foreseen.item);
+    }
+}
+
+impl ItemDistribution {
+    /// A distribution with only one outcome
+    pub fn certain(item: ItemState) -> Self {
+        let mut dist = Self::default();
+        dist.add(item, 1.);
+        dist
+    }
+
+    /// Weighted choice between outcomes, mirroring util::rand::choice
+    pub fn weighted<T, F>(choices: &[T], weights: &[u32], mut outcome: F) -> Self
+    where
+        F: FnMut(&T) -> ItemState,
+    {
+        assert_eq!(choices.len(), weights.len());
+
+        let total = weights.iter().map(|&w| w as f64).sum::<f64>();
+        choices.iter().zip(weights).filter(|&(_, &w)| w > 0).fold(
+            Self::default(),
+            |mut dist, (choice, &w)| {
+                dist.add(outcome(choice), w as f64 / total);
+                dist
+            },
+        )
+    }
+
+    /// Uniform choice between outcomes
+    pub fn uniform<T, F>(choices: &[T], outcome: F) -> Self
+    where
+        F: FnMut(&T) -> ItemState,
+    {
+        Self::weighted(choices, &vec![1; choices.len()], outcome)
+    }
+
+    /// Add some probability mass to an outcome
+    pub fn add(&mut self, mut item: ItemState, probability: f64) {
+        normalise(&mut item);
+        *self.0.entry(item).or_default() += probability;
+    }
+
+    /// Apply a change that happens to every outcome
+    pub fn map<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(&mut ItemState),
+    {
+        self.0
+            .into_iter()
+            .fold(Self::default(), |mut dist, (mut item, p)| {
+                f(&mut item);
+                dist.add(item, p);
+                dist
+            })
+    }
+
+    /// Chain another random step onto every outcome
+    pub fn flat_map<F>(&self, mut f: F) -> Self
+    where
+        F: FnMut(&ItemState) -> Self,
+    {
+        self.0.iter().fold(Self::default(), |mut dist, (item, &p)| {
+            f(item)
+                .0
+                .into_iter()
+                .for_each(|(next, q)| dist.add(next, p * q));
+            dist
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ItemState, f64)> {
+        self.0.iter().map(|(item, &p)| (item, p))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Total probability of all outcomes
+    pub fn total(&self) -> f64 {
+        self.0.values().sum()
+    }
+
+    /// Probability of each tier being present on the item afterwards when it wasn't before
+    pub fn added_tiers(&self, before: &ItemState) -> HashMap<OpaqueIndex<Tier>, f64> {
+        self.iter().fold(HashMap::new(), |mut added, (item, p)| {
+            item.mods
+                .iter()
+                .chain(&item.implicits)
+                .filter(|tier_id| {
+                    !before.mods.contains(tier_id) && !before.implicits.contains(tier_id)
+                })
+                .for_each(|&tier_id| *added.entry(tier_id).or_default() += p);
+            added
+        })
+    }
+
+    /// Probability of each tier being gone from the item afterwards when it was there before
+    pub fn removed_tiers(&self, before: &ItemState) -> HashMap<OpaqueIndex<Tier>, f64> {
+        self.iter().fold(HashMap::new(), |mut removed, (item, p)| {
+            before
+                .mods
+                .iter()
+                .chain(&before.implicits)
+                .filter(|tier_id| !item.mods.contains(tier_id) && !item.implicits.contains(tier_id))
+                .for_each(|&tier_id| *removed.entry(tier_id).or_default() += p);
+            removed
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        distribution::ItemDistribution,
+        hashvec::OpaqueIndex,
+        item_state::{ItemState, Rarity},
+    };
+
+    #[test]
+    fn test_merge_outcomes() {
+        let base = ItemState {
+            base_type: "Amulet".to_string(),
+            item_level: 100,
+            rarity: Rarity::Rare,
+            mods: vec![],
+            corrupted: false,
+            implicits: vec![],
+            fractured: vec![],
+            desecrated: vec![],
+            veiled_min_level: None,
+            foreseen: None,
+            quality: 0,
+            quality_type: None,
+            sockets: vec![],
+            rolls: BTreeMap::new(),
+        };
+        let tiers = [0, 1, 2].map(OpaqueIndex::new);
+
+        let add = |item: &ItemState, tier_id| {
+            let mut item = item.clone();
+            item.mods.push(tier_id);
+            item
+        };
+
+        // Two slams without replacement, so {0, 1} can be reached both ways
+        let dist = ItemDistribution::weighted(&tiers, &[1, 1, 2], |&t| add(&base, t));
+        let dist = dist.flat_map(|item| {
+            let remaining = tiers
+                .iter()
+                .copied()
+                .filter(|t| !item.mods.contains(t))
+                .collect::<Vec<_>>();
+            ItemDistribution::uniform(&remaining, |&t| add(item, t))
+        });
+
+        assert_eq!(dist.len(), 3);
+        assert!((dist.total() - 1.).abs() < 1e-9);
+
+        let added = dist.added_tiers(&base);
+        assert!((added[&tiers[0]] - 0.625).abs() < 1e-9);
+        assert!((added[&tiers[2]] - 0.75).abs() < 1e-9);
+
+        // Nothing to remove from an empty item
+        assert!(dist.removed_tiers(&base).is_empty());
+    }
+}
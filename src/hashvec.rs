@@ -4,7 +4,7 @@ use std::{
     collections::HashMap,
     hash::{Hash, Hasher},
     marker::PhantomData,
-    ops::{Deref, Index},
+    ops::{Deref, Index, IndexMut},
 };
 
 #[derive(Debug)]
@@ -83,6 +83,15 @@ where
     }
 }
 
+impl<K, V> IndexMut<OpaqueIndex<V>> for HashVec<K, V>
+where
+    K: Eq + Hash,
+{
+    fn index_mut(&mut self, index: OpaqueIndex<V>) -> &mut Self::Output {
+        &mut self.vec[*index]
+    }
+}
+
 impl<K, V> HashVec<K, V>
 where
     K: Eq + Hash,
@@ -133,9 +142,21 @@ where
         self.hm.contains_key(key)
     }
 
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.vec.iter()
+    }
+
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
         self.vec.iter_mut()
     }
+
+    /// Iterate over the values along with their opaque keys
+    pub fn iter_opaque(&self) -> impl Iterator<Item = (OpaqueIndex<V>, &V)> {
+        self.vec
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (OpaqueIndex::new(i), v))
+    }
 }
 
 impl<K, V> Default for HashVec<K, V> {
@@ -1,21 +1,28 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     path::Path,
-    sync::{LazyLock, OnceLock},
+    sync::{LazyLock, OnceLock, RwLock},
 };
 
 use itertools::Itertools;
 
 use crate::{
-    currency::{Currency, CurrencyType},
+    currency::{Currency, CurrencyType, Rune},
     hashvec::HashVec,
+    item_state::{ARMOUR_CLASSES, ItemClassRules, WEAPON_CLASSES, class_max_sockets},
     parsers::{
-        dat::{Dats, load_essences, load_mod_tiers},
+        dat::{Dats, load_equipment_rules, load_essences, load_mod_tiers, load_socketables},
         poe2db, stat_desc,
     },
-    types::{BaseItemId, ModGroup, Modifier, StatFormatter, StatFormatters, Tier, TierId},
+    types::{
+        Affix, BaseItemId, DEFAULT_LANGUAGE, Language, ModGroup, ModType, Modifier, Socketable,
+        SocketableId, StatFormatter, StatFormatters, StatID, Tier, TierId,
+    },
 };
 
+#[cfg(test)]
+pub(crate) mod test_data;
+
 /// 2-stage initialisation of global data
 /// 1) init() loads and sets the data in the OnceLock as it needs the data path passed to it
 /// 2) LazyLock pulls the initialised data on request so we can just use it like a normal reference
@@ -28,9 +35,10 @@ static MODS_INTERNAL: OnceLock<HashVec<ModGroup, Modifier>> = OnceLock::new();
 pub static MODS: LazyLock<&HashVec<ModGroup, Modifier>> =
     LazyLock::new(|| MODS_INTERNAL.get().expect("init() has not been called."));
 
-static ESSENCES_INTERNAL: OnceLock<Vec<CurrencyType>> = OnceLock::new();
+/// Currencies loaded from the data files: essences and socketables
+static LOADED_CURRENCIES_INTERNAL: OnceLock<Vec<CurrencyType>> = OnceLock::new();
 pub static CURRENCIES: LazyLock<Vec<&CurrencyType>> = LazyLock::new(|| {
-    ESSENCES_INTERNAL
+    LOADED_CURRENCIES_INTERNAL
         .get()
         .expect("init() has not been called.")
         .iter()
@@ -56,70 +64,437 @@ pub static ITEM_TIERS: LazyLock<&HashMap<BaseItemId, Vec<TierId>>> = LazyLock::n
         .expect("init() has not been called.")
 });
 
-static FORMATTERS_INTERNAL: OnceLock<HashMap<String, Vec<StatFormatter>>> = OnceLock::new();
-pub static FORMATTERS: LazyLock<&HashMap<String, Vec<StatFormatter>>> = LazyLock::new(|| {
+static DESECRATED_TIERS_INTERNAL: OnceLock<HashMap<BaseItemId, Vec<TierId>>> = OnceLock::new();
+pub static DESECRATED_TIERS: LazyLock<&HashMap<BaseItemId, Vec<TierId>>> = LazyLock::new(|| {
+    DESECRATED_TIERS_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+/// Spawn weight of each tier on each base, tiers which can't roll on the base are missing
+static ITEM_WEIGHTS_INTERNAL: OnceLock<HashMap<BaseItemId, HashMap<TierId, u32>>> = OnceLock::new();
+pub static ITEM_WEIGHTS: LazyLock<&HashMap<BaseItemId, HashMap<TierId, u32>>> =
+    LazyLock::new(|| {
+        ITEM_WEIGHTS_INTERNAL
+            .get()
+            .expect("init() has not been called.")
+    });
+
+/// Eg. Silk Gloves -> {gloves, int_armour, armour, default}
+static ITEM_TAGS_INTERNAL: OnceLock<HashMap<BaseItemId, HashSet<String>>> = OnceLock::new();
+pub static ITEM_TAGS: LazyLock<&HashMap<BaseItemId, HashSet<String>>> = LazyLock::new(|| {
+    ITEM_TAGS_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+static ITEM_CLASSES_INTERNAL: OnceLock<HashMap<BaseItemId, String>> = OnceLock::new();
+pub static ITEM_CLASSES: LazyLock<&HashMap<BaseItemId, String>> = LazyLock::new(|| {
+    ITEM_CLASSES_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+static SOCKETABLES_INTERNAL: OnceLock<HashMap<SocketableId, Socketable>> = OnceLock::new();
+pub static SOCKETABLES: LazyLock<&HashMap<SocketableId, Socketable>> = LazyLock::new(|| {
+    SOCKETABLES_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+static ITEM_MOD_DOMAINS_INTERNAL: OnceLock<HashMap<BaseItemId, usize>> = OnceLock::new();
+pub static ITEM_MOD_DOMAINS: LazyLock<&HashMap<BaseItemId, usize>> = LazyLock::new(|| {
+    ITEM_MOD_DOMAINS_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+/// Eg. Jewel -> 1 prefix and 1 suffix when magic, 2 of each when rare
+static ITEM_CLASS_RULES_INTERNAL: OnceLock<HashMap<String, ItemClassRules>> = OnceLock::new();
+pub static ITEM_CLASS_RULES: LazyLock<&HashMap<String, ItemClassRules>> = LazyLock::new(|| {
+    ITEM_CLASS_RULES_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+/// Rules for the class of every base
+fn class_rules(
+    item_classes: &HashMap<BaseItemId, String>,
+    equipment: ItemClassRules,
+) -> HashMap<String, ItemClassRules> {
+    item_classes
+        .values()
+        .map(|item_class| {
+            let rules = ItemClassRules::for_class(item_class, equipment);
+            (item_class.clone(), rules)
+        })
+        .collect()
+}
+
+/// Most rune sockets each base can have, bases which can't be socketed are missing
+static ITEM_SOCKETS_INTERNAL: OnceLock<HashMap<BaseItemId, usize>> = OnceLock::new();
+pub static ITEM_SOCKETS: LazyLock<&HashMap<BaseItemId, usize>> = LazyLock::new(|| {
+    ITEM_SOCKETS_INTERNAL
+        .get()
+        .expect("init() has not been called.")
+});
+
+/// Socket counts aren't in the tables, so each base takes the count for its class
+fn base_max_sockets(item_classes: &HashMap<BaseItemId, String>) -> HashMap<BaseItemId, usize> {
+    item_classes
+        .iter()
+        .map(|(base_id, item_class)| (base_id.clone(), class_max_sockets(item_class)))
+        .filter(|&(_, max_sockets)| max_sockets > 0)
+        .collect()
+}
+
+/// Item classes which can roll affixes, other than weapons and armour
+const OTHER_CRAFTABLE_CLASSES: &[&str] = &[
+    "Amulet",
+    "Ring",
+    "Belt",
+    "Quiver",
+    "Jewel",
+    "LifeFlask",
+    "ManaFlask",
+    "UtilityFlask",
+    "Map",
+    "TowerAugmentation",
+    "Relic",
+];
+
+/// Whether bases of this item class should be loaded for crafting
+fn is_craftable_class(item_class: &str) -> bool {
+    WEAPON_CLASSES.contains(&item_class)
+        || ARMOUR_CLASSES.contains(&item_class)
+        || OTHER_CRAFTABLE_CLASSES.contains(&item_class)
+}
+
+const ATTRIBUTES: [&str; 3] = ["str", "dex", "int"];
+
+/// Attributes in the name of a poe2db base, Eg. Gloves_StrDex -> {str, dex}.
+/// Empty if it's named after the whole class
+fn poe2db_attributes(name: &str) -> BTreeSet<String> {
+    let Some((_, suffix)) = name.rsplit_once('_') else {
+        return BTreeSet::new();
+    };
+
+    let mut attributes = BTreeSet::new();
+    let mut rest = suffix.to_lowercase();
+    while let Some(attribute) = ATTRIBUTES.iter().find(|&&attr| rest.starts_with(attr)) {
+        attributes.insert(attribute.to_string());
+        rest.drain(..attribute.len());
+    }
+
+    if rest.is_empty() {
+        attributes
+    } else {
+        BTreeSet::new()
+    }
+}
+
+/// Attributes of a base from the tables, by its attribute tag. Eg. str_dex_armour -> {str, dex}
+fn base_attributes(tags: &HashSet<&String>) -> BTreeSet<String> {
+    tags.iter()
+        .filter_map(|tag| {
+            let words = tag.split('_').collect::<Vec<_>>();
+            let (_, attributes) = words.split_last()?;
+            (!attributes.is_empty() && attributes.iter().all(|word| ATTRIBUTES.contains(word)))
+                .then(|| attributes.iter().map(|word| word.to_string()).collect())
+        })
+        .max_by_key(|attributes: &BTreeSet<_>| attributes.len())
+        .unwrap_or_default()
+}
+
+/// The poe2db base of the same class which covers a base from the tables: the one with the same
+/// attributes, or the only one if poe2db doesn't split the class by attribute
+fn poe2db_base<'a>(names: &[&'a str], tags: &HashSet<&String>) -> Option<&'a str> {
+    if let [name] = names {
+        return Some(name);
+    }
+
+    let attributes = base_attributes(tags);
+    names
+        .iter()
+        .copied()
+        .find(|name| poe2db_attributes(name) == attributes)
+}
+
+/// Add the pools and weights poe2db lists for a base
+fn insert_poe2db_pool(
+    base: &BaseItemId,
+    item_root: &poe2db::ItemRoot,
+    base_tiers: &mut HashMap<BaseItemId, Vec<TierId>>,
+    base_desecrated_tiers: &mut HashMap<BaseItemId, Vec<TierId>>,
+    base_weights: &mut HashMap<BaseItemId, HashMap<TierId, u32>>,
+) {
+    let codes = |mods: &[poe2db::Modifier]| mods.iter().map(|m| m.Code.clone()).collect();
+    base_tiers.insert(base.clone(), codes(&item_root.normal));
+    base_desecrated_tiers.insert(base.clone(), codes(&item_root.desecrated));
+    base_weights.insert(
+        base.clone(),
+        item_root
+            .normal
+            .iter()
+            .chain(&item_root.desecrated)
+            .map(|m| (m.Code.clone(), m.DropChance))
+            .collect(),
+    );
+}
+
+static FORMATTERS_INTERNAL: OnceLock<HashMap<Language, StatFormatters>> = OnceLock::new();
+pub static FORMATTERS: LazyLock<&HashMap<Language, StatFormatters>> = LazyLock::new(|| {
     FORMATTERS_INTERNAL
         .get()
         .expect("init() has not been called.")
 });
 
+/// Language stat text is displayed and parsed in
+static LANGUAGE: LazyLock<RwLock<Language>> =
+    LazyLock::new(|| RwLock::new(DEFAULT_LANGUAGE.to_string()));
+
+pub fn language() -> Language {
+    LANGUAGE.read().unwrap().clone()
+}
+
+pub fn set_language(language: &str) {
+    *LANGUAGE.write().unwrap() = language.to_string();
+}
+
+/// Formatters for a stat in the selected language, falling back to the default language for stats
+/// which haven't been translated. Key is "|" delimited StatID's
+pub fn get_formatters(key: &str) -> Option<&'static [StatFormatter]> {
+    let formatters: &'static HashMap<Language, StatFormatters> = *FORMATTERS;
+    formatters
+        .get(&language())
+        .and_then(|lut| lut.get(key))
+        .or_else(|| formatters.get(DEFAULT_LANGUAGE)?.get(key))
+        .map(Vec::as_slice)
+}
+
+/// Stats on the loaded mods and socketables without a formatter, which are displayed by their raw
+/// id instead. Useful for spotting stat descriptions that are out of date after a patch.
+pub fn missing_formatters() -> BTreeSet<StatID> {
+    let mod_stats = MODS
+        .values()
+        .filter(|modifier| get_formatters(&modifier.stats.join("|")).is_none())
+        .flat_map(|modifier| &modifier.stats);
+    let socketable_stats = SOCKETABLES
+        .values()
+        .flat_map(|socketable| {
+            socketable
+                .weapon_stats
+                .iter()
+                .chain(&socketable.armour_stats)
+        })
+        .map(|(stat_id, _)| stat_id);
+
+    mod_stats
+        .chain(socketable_stats)
+        .filter(|stat_id| get_formatters(stat_id).is_none())
+        .cloned()
+        .collect()
+}
+
 /// Load all of the data
 /// PATHS
 /// data_root/
 /// -> tables/  - Extracted with poe_data_tools
-/// -> coe/     - From Prohibited Library discord
-/// -> stat_descriptions.json      - https://repoe-fork.github.io/poe2/stat_translations/stat_descriptions.json
+/// -> coe/     - From Prohibited Library discord. Optional, overrides the pools and weights
+///               derived from the tables for the bases it covers
+/// -> stat_descriptions.json      - Baked from the game's .csd files by bake_assets, every language
 pub fn init(data_root: &Path) {
-    // Load weight data
+    // Load weight overrides
     #[cfg(not(feature = "embed_data"))]
-    let poe2db_root = poe2db::load(&data_root.join("coe/poe2db_data_altered_weights.json"));
+    let poe2db_root = {
+        let path = data_root.join("coe/poe2db_data_altered_weights.json");
+        if path.exists() {
+            poe2db::load(&path)
+        } else {
+            poe2db::Root::default()
+        }
+    };
     #[cfg(feature = "embed_data")]
     let poe2db_root = poe2db::load_embedded();
 
-    // Create TierId -> weight LUT
-    let mut tier_weights = HashMap::new();
-    // Create BaseItemId -> [TierId] LUT
-    let mut base_tiers = HashMap::new();
-    // Gloves -> {Gloves_StrDex, Gloves_DexInt, ...}
-    let mut specific_bases = HashMap::<String, HashSet<BaseItemId>>::new();
-    for (item_name, item_root) in poe2db_root {
-        specific_bases
-            .entry(item_root.opt.ItemClassesCode)
+    // Load mod groups from dat files
+    #[cfg(not(feature = "embed_data"))]
+    let dat_tables = Dats::load_tables(&data_root.join("tables"));
+    #[cfg(feature = "embed_data")]
+    let dat_tables = Dats::load_tables_embedded();
+
+    // Load ModGroup -> [Tier] LUT from dat files
+    // Load ModGroup -> [Stat] LUT
+    let (tiers, mut mod_stats) = load_mod_tiers(&dat_tables);
+
+    // Gloves -> [Gloves_DexInt, Gloves_StrDex, ...]
+    let mut poe2db_classes = HashMap::<&str, Vec<&str>>::new();
+    for (item_name, item_root) in &poe2db_root {
+        poe2db_classes
+            .entry(&item_root.opt.ItemClassesCode)
             .or_default()
-            .insert(item_name.clone());
+            .push(item_name);
+    }
+    poe2db_classes
+        .values_mut()
+        .for_each(|names| names.sort_unstable());
 
-        for tier in &item_root.normal {
-            tier_weights.insert(tier.Code.clone(), tier.DropChance);
+    // Create BaseItemId -> TierId -> weight LUT
+    let mut base_weights = HashMap::new();
+    // Create BaseItemId -> [TierId] LUT
+    let mut base_tiers = HashMap::new();
+    // Create BaseItemId -> [TierId] LUT for mods revealed by unveiling
+    let mut base_desecrated_tiers = HashMap::new();
+    // Silk Gloves -> Gloves
+    let mut item_classes = HashMap::new();
+    // Silk Gloves -> ModDomain
+    let mut base_domains = HashMap::new();
+    // Silk Gloves -> {gloves, int_armour, armour, default}
+    let mut base_tags = HashMap::new();
+    // poe2db entries used by a base from the tables
+    let mut matched_poe2db = HashSet::new();
+    for base in &dat_tables.base_item_types {
+        let item_class = &dat_tables.item_classes[base.ItemClass].Id;
+        if !is_craftable_class(item_class) {
+            continue;
         }
 
-        base_tiers.insert(
-            item_name.clone(),
-            item_root
-                .normal
+        let tags = base
+            .Tags
+            .iter()
+            .map(|&tag| &dat_tables.tags[tag].Id)
+            .collect::<HashSet<_>>();
+
+        // poe2db overrides the pool of the base it lists with the same attributes
+        let poe2db_name = poe2db_classes
+            .get(item_class.as_str())
+            .and_then(|names| poe2db_base(names, &tags));
+        if let Some(poe2db_name) = poe2db_name {
+            let item_root = &poe2db_root[poe2db_name];
+            matched_poe2db.insert(poe2db_name);
+            insert_poe2db_pool(
+                &base.Name,
+                item_root,
+                &mut base_tiers,
+                &mut base_desecrated_tiers,
+                &mut base_weights,
+            );
+            base_domains.insert(base.Name.clone(), item_root.opt.ModDomainsID as usize);
+        } else {
+            // Derive the pool from the spawn weights, like the game does
+            let pool = tiers
+                .values()
+                .filter(|tier| {
+                    tier.mod_domain == base.ModDomain
+                        && matches!(tier.affix, Affix::Prefix | Affix::Suffix)
+                })
+                .map(|tier| (&tier.id, tier.spawn_weight(&tags)))
+                .filter(|&(_, weight)| weight > 0)
+                .collect::<Vec<_>>();
+
+            // Split into the normal pool and the pool revealed by unveiling
+            let (desecrated, normal): (Vec<_>, Vec<_>) = pool
                 .iter()
-                .map(|m| m.Code.clone())
-                .collect::<Vec<_>>(),
+                .map(|&(tier_id, _)| tier_id.clone())
+                .partition(|tier_id| {
+                    mod_stats[tiers.by_key(tier_id).mod_id].mod_type == ModType::Desecrated
+                });
+            let weights = pool
+                .into_iter()
+                .map(|(tier_id, weight)| (tier_id.clone(), weight))
+                .collect::<HashMap<_, _>>();
+
+            base_domains.insert(base.Name.clone(), base.ModDomain);
+            base_tiers.insert(base.Name.clone(), normal);
+            base_desecrated_tiers.insert(base.Name.clone(), desecrated);
+            base_weights.insert(base.Name.clone(), weights);
+        }
+
+        item_classes.insert(base.Name.clone(), item_class.clone());
+        base_tags.insert(base.Name.clone(), tags.into_iter().cloned().collect());
+    }
+
+    // Anything poe2db lists without a matching base is kept under its own name. poe2db doesn't
+    // list tags, so use the ones shared by every base of the same class
+    for (item_name, item_root) in &poe2db_root {
+        if matched_poe2db.contains(item_name.as_str()) {
+            continue;
+        }
+
+        let item_class = &item_root.opt.ItemClassesCode;
+        insert_poe2db_pool(
+            item_name,
+            item_root,
+            &mut base_tiers,
+            &mut base_desecrated_tiers,
+            &mut base_weights,
         );
+        item_classes.insert(item_name.clone(), item_class.clone());
+        base_domains.insert(item_name.clone(), item_root.opt.ModDomainsID as usize);
+
+        let tags = dat_tables
+            .base_item_types
+            .iter()
+            .filter(|base| &dat_tables.item_classes[base.ItemClass].Id == item_class)
+            .map(|base| {
+                base.Tags
+                    .iter()
+                    .map(|&tag| dat_tables.tags[tag].Id.clone())
+                    .collect::<HashSet<_>>()
+            })
+            .reduce(|a, b| &a & &b)
+            .unwrap_or_default();
+        base_tags.insert(item_name.clone(), tags);
     }
     ITEM_TIERS_INTERNAL.set(base_tiers).unwrap();
+    DESECRATED_TIERS_INTERNAL
+        .set(base_desecrated_tiers)
+        .unwrap();
+    ITEM_SOCKETS_INTERNAL
+        .set(base_max_sockets(&item_classes))
+        .unwrap();
+    let equipment = load_equipment_rules(&dat_tables).unwrap_or(ItemClassRules::EQUIPMENT);
+    ITEM_CLASS_RULES_INTERNAL
+        .set(class_rules(&item_classes, equipment))
+        .unwrap();
+    ITEM_CLASSES_INTERNAL.set(item_classes).unwrap();
+    ITEM_MOD_DOMAINS_INTERNAL.set(base_domains).unwrap();
+    ITEM_WEIGHTS_INTERNAL.set(base_weights).unwrap();
+    ITEM_TAGS_INTERNAL.set(base_tags).unwrap();
 
-    // Load mod groups from dat files
-    #[cfg(not(feature = "embed_data"))]
-    let dat_tables = Dats::load_tables(&data_root.join("tables"));
-    #[cfg(feature = "embed_data")]
-    let dat_tables = Dats::load_tables_embedded();
-
-    // Load ModGroup -> [Tier] LUT from dat files
-    // Load ModGroup -> [Stat] LUT
-    let (mut tiers, mod_stats) = load_mod_tiers(&dat_tables);
+    // Mods that can only be revealed by unveiling, never rolled normally
+    let mod_groups = |tier_ids: Vec<&TierId>| {
+        tier_ids
+            .into_iter()
+            .flat_map(|tier_id| tiers.get_opaque(tier_id))
+            .map(|tier_id| mod_stats[tiers[tier_id].mod_id].group.clone())
+            .collect::<HashSet<_>>()
+    };
+    let normal_groups = mod_groups(ITEM_TIERS.values().flatten().collect());
+    let desecrated_groups = mod_groups(DESECRATED_TIERS.values().flatten().collect());
+    mod_stats
+        .values_mut()
+        .filter(|modifier| {
+            modifier.mod_type == ModType::Normal
+                && desecrated_groups.contains(&modifier.group)
+                && !normal_groups.contains(&modifier.group)
+        })
+        .for_each(|modifier| modifier.mod_type = ModType::Desecrated);
     MODS_INTERNAL.set(mod_stats).unwrap();
-
-    // Apply mod weights
-    tiers.values_mut().for_each(|tier| {
-        tier.weight = *tier_weights.get(&tier.id).unwrap_or(&0);
-    });
     TIERS_INTERNAL.set(tiers).unwrap();
-    ESSENCES_INTERNAL.set(load_essences(&dat_tables)).unwrap();
+
+    // Socketing a rune is modelled as using it like a currency
+    let socketables = load_socketables(&dat_tables);
+    let loaded_currencies = load_essences(&dat_tables)
+        .into_iter()
+        .chain(
+            socketables
+                .keys()
+                .map(|name| CurrencyType::Rune(Rune { name: name.clone() })),
+        )
+        .collect();
+    LOADED_CURRENCIES_INTERNAL.set(loaded_currencies).unwrap();
+    SOCKETABLES_INTERNAL.set(socketables).unwrap();
 
     // Load stat descriptions
     #[cfg(not(feature = "embed_data"))]
@@ -127,17 +502,91 @@ pub fn init(data_root: &Path) {
     #[cfg(feature = "embed_data")]
     let stat_desc_root = stat_desc::load_embedded();
 
-    // Create StatID -> [Formatter] LUT
-    let mut stat_formatters = StatFormatters::new();
-    for m in &stat_desc_root {
-        // Add a multi-stat formatter
-        let key = m.ids.join("|");
-        stat_formatters.insert(key, m.English.clone());
+    FORMATTERS_INTERNAL
+        .set(load_formatters(&stat_desc_root))
+        .unwrap();
+}
+
+/// Create Language -> StatID -> [Formatter] LUT
+fn load_formatters(stat_desc_root: &stat_desc::Root) -> HashMap<Language, StatFormatters> {
+    let mut stat_formatters = HashMap::<_, StatFormatters>::new();
+    for m in stat_desc_root {
+        for (language, formatters) in &m.languages {
+            let lut = stat_formatters.entry(language.clone()).or_default();
+
+            // Add a multi-stat formatter
+            let key = m.ids.join("|");
+            lut.insert(key, formatters.clone());
 
-        // Also add as per-stat formatters
-        for key in &m.ids {
-            stat_formatters.insert(key.clone(), m.English.clone());
+            // Also add as per-stat formatters
+            for key in &m.ids {
+                lut.insert(key.clone(), formatters.clone());
+            }
         }
     }
-    FORMATTERS_INTERNAL.set(stat_formatters).unwrap();
+
+    stat_formatters
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use crate::{
+        internal::{
+            base_attributes, get_formatters, missing_formatters, poe2db_attributes, poe2db_base,
+            set_language, test_data,
+        },
+        types::DEFAULT_LANGUAGE,
+    };
+
+    fn attributes(attributes: &[&str]) -> BTreeSet<String> {
+        attributes.iter().map(|attr| attr.to_string()).collect()
+    }
+
+    #[test]
+    fn test_poe2db_bases() {
+        assert_eq!(
+            poe2db_attributes("Gloves_StrDex"),
+            attributes(&["str", "dex"])
+        );
+        assert_eq!(poe2db_attributes("Body_Armour_Int"), attributes(&["int"]));
+        assert_eq!(poe2db_attributes("Ring"), attributes(&[]));
+        assert_eq!(poe2db_attributes("One_Hand_Sword"), attributes(&[]));
+
+        let tags = ["gloves", "str_dex_armour", "armour", "default"].map(String::from);
+        let tags = tags.iter().collect::<HashSet<_>>();
+        assert_eq!(base_attributes(&tags), attributes(&["str", "dex"]));
+
+        // The base with the same attributes, not just the same class
+        let gloves = ["Gloves_Dex", "Gloves_DexInt", "Gloves_Str", "Gloves_StrDex"];
+        assert_eq!(poe2db_base(&gloves, &tags), Some("Gloves_StrDex"));
+        assert_eq!(poe2db_base(&gloves[..3], &tags), None);
+        // Classes which poe2db doesn't split cover every base
+        assert_eq!(poe2db_base(&["Gloves"], &tags), Some("Gloves"));
+    }
+
+    #[test]
+    fn test_formatters() {
+        test_data::init();
+        let display =
+            |key: &str| get_formatters(key).map(|formatters| formatters[0].format_values(&[5]));
+        assert_eq!(display("additional_strength").unwrap(), "+5 to Strength");
+        assert_eq!(display("base_maximum_life").unwrap(), "+5 to maximum Life");
+        assert_eq!(display("base_maximum_life|base_maximum_mana"), None);
+
+        // Stats which aren't translated fall back to English, as does a language which isn't loaded
+        set_language("French");
+        assert_eq!(display("additional_strength").unwrap(), "+5 à la Force");
+        assert_eq!(display("base_maximum_life").unwrap(), "+5 to maximum Life");
+        set_language("German");
+        assert_eq!(display("additional_strength").unwrap(), "+5 to Strength");
+        set_language(DEFAULT_LANGUAGE);
+
+        // The rune's weapon stat has no description
+        assert_eq!(
+            missing_formatters(),
+            BTreeSet::from(["local_minimum_added_fire_damage".to_string()])
+        );
+    }
 }
@@ -0,0 +1,411 @@
+/**
+A small hand-written data set for tests which need the global data, in place of init().
+Two ring bases share one pool of mods. The other bases can't roll any, and are there for
+sockets and the affix limits of their classes. The mods:
+    IncreasedLife       prefix, tiers with overlapping ranges
+    LifeAndMana         hybrid prefix, displayed over two lines
+    FireResistance      suffix, tagged Fire for catalysts
+    EssenceMana         prefix only added by essences
+    ItemFoundRarity     desecrated suffix
+    Desecrated*         more desecrated mods, of which Strength can't roll on rings and is the
+                        only stat translated to French
+    CriticalStrikeChance corrupted implicit, only on rings
+along with the placeholder tiers added by desecration, and a rune to socket.
+*/
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Once,
+};
+
+use crate::{
+    TIERS,
+    currency::{CurrencyType, Rune},
+    hashvec::HashVec,
+    internal::{
+        DESECRATED_TIERS_INTERNAL, FORMATTERS_INTERNAL, ITEM_CLASS_RULES_INTERNAL,
+        ITEM_CLASSES_INTERNAL, ITEM_MOD_DOMAINS_INTERNAL, ITEM_SOCKETS_INTERNAL,
+        ITEM_TAGS_INTERNAL, ITEM_TIERS_INTERNAL, ITEM_WEIGHTS_INTERNAL, LOADED_CURRENCIES_INTERNAL,
+        MODS_INTERNAL, SOCKETABLES_INTERNAL, TIERS_INTERNAL, base_max_sockets, class_rules,
+        load_formatters,
+    },
+    item_state::{ItemClassRules, ItemState, Rarity},
+    parsers::csd,
+    types::{Affix, ModType, Modifier, Socketable, Tier},
+};
+
+/// (name, item class, tags)
+type BaseRow = (&'static str, &'static str, &'static [&'static str]);
+
+pub const BASES: &[BaseRow] = &[
+    ("Sapphire Ring", "Ring", &["ring", "default"]),
+    ("Ruby Ring", "Ring", &["ring", "default"]),
+    (
+        "Leather Vest",
+        "Body Armour",
+        &["body_armour", "armour", "default"],
+    ),
+    ("Leather Gloves", "Gloves", &["gloves", "armour", "default"]),
+    ("Crude Bow", "Bow", &["bow", "weapon", "default"]),
+    ("Ruby", "Jewel", &["jewel", "default"]),
+    ("Lesser Life Flask", "LifeFlask", &["flask", "default"]),
+];
+
+/// (tier id, name, ilvl, value ranges)
+type TierRow = (&'static str, &'static str, u32, &'static [[i32; 2]]);
+
+/// (group, stats, tags, affix, mod type, spawn weights, tiers)
+/// Every other tag, including default, has a weight of 0
+type ModRow = (
+    &'static str,
+    &'static [&'static str],
+    &'static [&'static str],
+    Affix,
+    ModType,
+    &'static [(&'static str, u32)],
+    &'static [TierRow],
+);
+
+const MOD_ROWS: &[ModRow] = &[
+    // Placeholders added by desecration, which never roll
+    (
+        "VeiledPrefix",
+        &[],
+        &[],
+        Affix::Prefix,
+        ModType::Normal,
+        &[],
+        &[("VeiledPrefix", "", 1, &[])],
+    ),
+    (
+        "VeiledSuffix",
+        &[],
+        &[],
+        Affix::Suffix,
+        ModType::Normal,
+        &[],
+        &[("VeiledSuffix", "", 1, &[])],
+    ),
+    (
+        "IncreasedLife",
+        &["base_maximum_life"],
+        &["Life"],
+        Affix::Prefix,
+        ModType::Normal,
+        &[("ring", 1000)],
+        &[
+            ("IncreasedLife1", "Plump", 1, &[[10, 19]]),
+            ("IncreasedLife2", "Hale", 40, &[[50, 59]]),
+            ("IncreasedLife3", "Healthy", 60, &[[55, 69]]),
+        ],
+    ),
+    (
+        "LifeAndMana",
+        &["base_maximum_life", "base_maximum_mana"],
+        &["Life", "Mana"],
+        Affix::Prefix,
+        ModType::Normal,
+        &[("ring", 1000)],
+        &[("LifeAndMana1", "Vigorous", 1, &[[8, 12], [8, 12]])],
+    ),
+    (
+        "FireResistance",
+        &["base_fire_damage_resistance_%"],
+        &["Elemental", "Fire", "Resistance"],
+        Affix::Suffix,
+        ModType::Normal,
+        &[("ring", 1000)],
+        &[
+            ("FireResist1", "of the Whelpling", 1, &[[6, 10]]),
+            ("FireResist2", "of the Salamander", 50, &[[36, 40]]),
+        ],
+    ),
+    (
+        "EssenceMana",
+        &["base_maximum_mana"],
+        &["Mana"],
+        Affix::Prefix,
+        ModType::Essence,
+        &[],
+        &[("EssenceMana1", "Essences", 1, &[[20, 30]])],
+    ),
+    (
+        "ItemFoundRarity",
+        &["base_item_found_rarity_+%"],
+        &[],
+        Affix::Suffix,
+        ModType::Desecrated,
+        &[("ring", 1000)],
+        &[("DesecratedRarity1", "of the Abyss", 1, &[[10, 15]])],
+    ),
+    (
+        "DesecratedLightningResistance",
+        &["base_lightning_damage_resistance_%"],
+        &["Elemental", "Lightning", "Resistance"],
+        Affix::Suffix,
+        ModType::Desecrated,
+        &[("ring", 500)],
+        &[(
+            "DesecratedLightningResist1",
+            "of the Drowned",
+            1,
+            &[[20, 25]],
+        )],
+    ),
+    (
+        "DesecratedStrength",
+        &["additional_strength"],
+        &["Attribute"],
+        Affix::Suffix,
+        ModType::Desecrated,
+        &[],
+        &[("DesecratedStrength1", "of the Brute", 1, &[[10, 15]])],
+    ),
+    (
+        "DesecratedSpirit",
+        &["base_spirit"],
+        &[],
+        Affix::Prefix,
+        ModType::Desecrated,
+        &[("ring", 1000)],
+        &[("DesecratedSpirit1", "Abyssal", 1, &[[10, 15]])],
+    ),
+    (
+        "DesecratedArmour",
+        &["base_physical_damage_reduction_rating"],
+        &["Defences"],
+        Affix::Prefix,
+        ModType::Desecrated,
+        &[("ring", 500)],
+        &[("DesecratedArmour1", "Drowned", 1, &[[40, 60]])],
+    ),
+    (
+        "DesecratedEvasion",
+        &["base_evasion_rating"],
+        &["Defences"],
+        Affix::Prefix,
+        ModType::Desecrated,
+        &[("ring", 250)],
+        &[("DesecratedEvasion1", "Sunken", 1, &[[40, 60]])],
+    ),
+    (
+        "DesecratedEnergyShield",
+        &["base_maximum_energy_shield"],
+        &["Defences"],
+        Affix::Prefix,
+        ModType::Desecrated,
+        &[("ring", 250)],
+        &[("DesecratedEnergyShield1", "Hollow", 1, &[[20, 30]])],
+    ),
+    (
+        "CriticalStrikeChance",
+        &["critical_strike_chance_+%"],
+        &["Critical"],
+        Affix::Corrupted,
+        ModType::Corrupted,
+        &[("ring", 1000)],
+        &[("CorruptedCritChance1", "", 1, &[[10, 20]])],
+    ),
+];
+
+pub const RUNE: &str = "Desert Rune";
+
+const STAT_DESCRIPTIONS: &str = r#"
+description
+	1 base_maximum_life
+	1
+		# "{0:+d} to maximum Life"
+description
+	1 base_maximum_mana
+	1
+		# "{0:+d} to maximum Mana"
+description
+	1 base_fire_damage_resistance_%
+	1
+		# "{0:+d}% to Fire Resistance"
+description
+	1 base_item_found_rarity_+%
+	2
+		1|# "{0}% increased Rarity of Items found"
+		#|-1 "{0}% reduced Rarity of Items found" negate 1
+description
+	1 base_lightning_damage_resistance_%
+	1
+		# "{0:+d}% to Lightning Resistance"
+description
+	1 additional_strength
+	1
+		# "{0:+d} to Strength"
+	lang "French"
+	1
+		# "{0:+d} à la Force"
+description
+	1 base_spirit
+	1
+		# "{0:+d} to Spirit"
+description
+	1 base_physical_damage_reduction_rating
+	1
+		# "{0:+d} to Armour"
+description
+	1 base_evasion_rating
+	1
+		# "{0:+d} to Evasion Rating"
+description
+	1 base_maximum_energy_shield
+	1
+		# "{0:+d} to maximum Energy Shield"
+description
+	1 critical_strike_chance_+%
+	1
+		1|# "{0}% increased Critical Hit Chance"
+"#;
+
+/// A base with the given mods, by tier id
+pub fn item(base_type: &str, rarity: Rarity, mods: &[&str]) -> ItemState {
+    ItemState {
+        base_type: base_type.to_string(),
+        item_level: 100,
+        rarity,
+        mods: mods.iter().map(|&tier_id| TIERS.opaque(tier_id)).collect(),
+        corrupted: false,
+        implicits: vec![],
+        fractured: vec![],
+        desecrated: vec![],
+        veiled_min_level: None,
+        rolls: Default::default(),
+        foreseen: None,
+        quality: 0,
+        quality_type: None,
+        sockets: vec![],
+    }
+}
+
+/// A Sapphire Ring with the given mods, by tier id
+pub fn ring(rarity: Rarity, mods: &[&str]) -> ItemState {
+    item(BASES[0].0, rarity, mods)
+}
+
+static INIT: Once = Once::new();
+
+/// Load the test data into the globals. Safe to call from every test
+pub fn init() {
+    INIT.call_once(|| {
+        let mut tiers = HashVec::default();
+        let mut mods = HashVec::default();
+        for &(group, stats, tags, affix, mod_type, weights, rows) in MOD_ROWS {
+            let mod_id = mods.insert(
+                group.to_string(),
+                Modifier {
+                    group: group.to_string(),
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                    mod_type,
+                    stats: stats.iter().map(|stat| stat.to_string()).collect(),
+                    families: vec![group.to_string()],
+                },
+            );
+            for &(id, name, ilvl, value_ranges) in rows {
+                tiers.insert(
+                    id.to_string(),
+                    Tier {
+                        id: id.to_string(),
+                        name: name.to_string(),
+                        mod_id,
+                        affix,
+                        ilvl,
+                        value_ranges: value_ranges.to_vec(),
+                        spawn_weights: weights
+                            .iter()
+                            .map(|&(tag, weight)| (tag.to_string(), weight))
+                            .chain([("default".to_string(), 0)])
+                            .collect(),
+                        mod_domain: 1,
+                    },
+                );
+            }
+        }
+
+        // Pools from the spawn weights, as the loader does for bases poe2db doesn't cover
+        let mut base_tiers = HashMap::new();
+        let mut base_desecrated_tiers = HashMap::new();
+        let mut base_weights = HashMap::new();
+        let mut base_tags = HashMap::new();
+        for &(base, _, tags) in BASES {
+            let tags = tags
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect::<HashSet<_>>();
+            let tag_refs = tags.iter().collect::<HashSet<_>>();
+            // Like poe2db, desecrated mods are listed even if they can't roll on the base
+            let affixes = tiers
+                .values()
+                .filter(|tier| {
+                    matches!(tier.affix, Affix::Prefix | Affix::Suffix)
+                        && (tier.spawn_weight(&tag_refs) > 0
+                            || mods[tier.mod_id].mod_type == ModType::Desecrated)
+                })
+                .collect::<Vec<_>>();
+            let pool = |desecrated: bool| {
+                affixes
+                    .iter()
+                    .filter(|tier| {
+                        (mods[tier.mod_id].mod_type == ModType::Desecrated) == desecrated
+                    })
+                    .map(|tier| tier.id.clone())
+                    .collect::<Vec<_>>()
+            };
+
+            base_tiers.insert(base.to_string(), pool(false));
+            base_desecrated_tiers.insert(base.to_string(), pool(true));
+            base_weights.insert(
+                base.to_string(),
+                affixes
+                    .iter()
+                    .map(|tier| (tier.id.clone(), tier.spawn_weight(&tag_refs)))
+                    .collect::<HashMap<_, _>>(),
+            );
+            base_tags.insert(base.to_string(), tags);
+        }
+        let base_classes = BASES
+            .iter()
+            .map(|&(base, item_class, _)| (base.to_string(), item_class.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        ITEM_TIERS_INTERNAL.set(base_tiers).unwrap();
+        DESECRATED_TIERS_INTERNAL
+            .set(base_desecrated_tiers)
+            .unwrap();
+        ITEM_WEIGHTS_INTERNAL.set(base_weights).unwrap();
+        ITEM_TAGS_INTERNAL.set(base_tags).unwrap();
+        ITEM_SOCKETS_INTERNAL
+            .set(base_max_sockets(&base_classes))
+            .unwrap();
+        ITEM_CLASS_RULES_INTERNAL
+            .set(class_rules(&base_classes, ItemClassRules::EQUIPMENT))
+            .unwrap();
+        ITEM_MOD_DOMAINS_INTERNAL
+            .set(base_classes.keys().map(|base| (base.clone(), 1)).collect())
+            .unwrap();
+        ITEM_CLASSES_INTERNAL.set(base_classes).unwrap();
+        MODS_INTERNAL.set(mods).unwrap();
+        TIERS_INTERNAL.set(tiers).unwrap();
+
+        let rune = Socketable {
+            name: RUNE.to_string(),
+            weapon_stats: vec![("local_minimum_added_fire_damage".to_string(), 7)],
+            armour_stats: vec![("base_fire_damage_resistance_%".to_string(), 12)],
+        };
+        SOCKETABLES_INTERNAL
+            .set(HashMap::from([(RUNE.to_string(), rune)]))
+            .unwrap();
+        LOADED_CURRENCIES_INTERNAL
+            .set(vec![CurrencyType::Rune(Rune {
+                name: RUNE.to_string(),
+            })])
+            .unwrap();
+
+        let descriptions = csd::to_root(csd::parse(STAT_DESCRIPTIONS));
+        FORMATTERS_INTERNAL
+            .set(load_formatters(&descriptions))
+            .unwrap();
+    });
+}
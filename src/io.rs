@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     CURRENCIES, MODS, TIERS,
-    currency::{Currency, CurrencyType},
+    currency::{Currency, CurrencyType, Unveil},
     hashvec::OpaqueIndex,
     item_state::ItemState,
     strategy::Strategy,
@@ -55,12 +55,26 @@ impl<'de> Deserialize<'de> for OpaqueIndex<Tier> {
     }
 }
 
+/// How a currency is saved. Most are saved by name, but Unveil needs its priority list too.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SavedCurrency {
+    Name(String),
+    Unveil { unveil: Vec<OpaqueIndex<Tier>> },
+}
+
 impl Serialize for CurrencyType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(self.name())
+        match self {
+            CurrencyType::Unveil(unveil) => SavedCurrency::Unveil {
+                unveil: unveil.priority_list.clone(),
+            },
+            _ => SavedCurrency::Name(self.name().to_string()),
+        }
+        .serialize(serializer)
     }
 }
 
@@ -69,13 +83,19 @@ impl<'de> Deserialize<'de> for CurrencyType {
     where
         D: serde::Deserializer<'de>,
     {
-        let name = String::deserialize(deserializer)?;
-        let currency = *CURRENCIES
-            .iter()
-            .find(|c| c.name() == name)
-            .ok_or_else(|| serde::de::Error::custom(format!("Unknown currency: {name}")))?;
+        match SavedCurrency::deserialize(deserializer)? {
+            SavedCurrency::Name(name) => {
+                let currency = *CURRENCIES
+                    .iter()
+                    .find(|c| c.name() == name)
+                    .ok_or_else(|| serde::de::Error::custom(format!("Unknown currency: {name}")))?;
 
-        Ok(currency.clone())
+                Ok(currency.clone())
+            }
+            SavedCurrency::Unveil { unveil } => Ok(CurrencyType::Unveil(Unveil {
+                priority_list: unveil,
+            })),
+        }
     }
 }
 
@@ -104,3 +124,87 @@ impl SavedStrategy {
         Ok(strategy)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        MODS, TIERS,
+        currency::{Currency, CurrencyType, Unveil},
+        internal::test_data::{self, ring},
+        io::SavedStrategy,
+        item_state::{ItemState, Rarity},
+        strategy::{Condition, ConditionGroup, ModifierCondition, Strategy},
+    };
+
+    #[test]
+    fn test_fractured_round_trip() {
+        test_data::init();
+        let saved = SavedStrategy {
+            base_item: ItemState {
+                fractured: vec![TIERS.opaque("FireResist1")],
+                ..ring(Rarity::Rare, &["IncreasedLife1", "FireResist1"])
+            },
+            strategy: Strategy(vec![(
+                Condition {
+                    rarity: Rarity::Rare,
+                    corrupted: None,
+                    foreseen: None,
+                    groups: vec![ConditionGroup::Count {
+                        count: 1..=1,
+                        mods: vec![ModifierCondition {
+                            mod_group: MODS.opaque("FireResistance"),
+                            levels: vec![1],
+                            fractured: Some(true),
+                            min_roll: None,
+                        }],
+                    }],
+                },
+                Some((HashSet::new(), CurrencyType::Exalt)),
+            )]),
+        };
+
+        let json = serde_json::to_string(&saved).unwrap();
+        let loaded = serde_json::from_str::<SavedStrategy>(&json).unwrap();
+
+        assert_eq!(loaded.base_item, saved.base_item);
+        let ConditionGroup::Count { mods, .. } = &loaded.strategy.0[0].0.groups[0] else {
+            panic!("Expected a count group");
+        };
+        assert_eq!(mods[0].fractured, Some(true));
+        assert!(loaded.strategy.0[0].0.check(&loaded.base_item));
+    }
+
+    #[test]
+    fn test_saved_currency() {
+        test_data::init();
+
+        // Older files saved every currency by name, including Unveil
+        let exalt = serde_json::from_str::<CurrencyType>(r#""Exalt""#).unwrap();
+        assert_eq!(exalt, CurrencyType::Exalt);
+        let CurrencyType::Unveil(unveil) =
+            serde_json::from_str::<CurrencyType>(r#""Unveil Desecrated Modifier""#).unwrap()
+        else {
+            panic!("Expected an unveil");
+        };
+        assert!(unveil.priority_list.is_empty());
+        assert!(serde_json::from_str::<CurrencyType>(r#""Not a currency""#).is_err());
+
+        // Names are still written for everything but Unveil
+        assert_eq!(serde_json::to_string(&exalt).unwrap(), r#""Exalt""#);
+        let unveil = CurrencyType::Unveil(Unveil {
+            priority_list: vec![TIERS.opaque("DesecratedRarity1")],
+        });
+        let json = serde_json::to_string(&unveil).unwrap();
+        let CurrencyType::Unveil(loaded) = serde_json::from_str::<CurrencyType>(&json).unwrap()
+        else {
+            panic!("Expected an unveil");
+        };
+        assert_eq!(
+            loaded.priority_list,
+            vec![TIERS.opaque("DesecratedRarity1")]
+        );
+        assert_eq!(unveil.name(), "Unveil Desecrated Modifier");
+    }
+}
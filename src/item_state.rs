@@ -1,29 +1,252 @@
-use std::{collections::HashSet, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+};
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    FORMATTERS, ITEM_TIERS, MODS, TIERS,
+    DESECRATED_TIERS, ITEM_CLASS_RULES, ITEM_CLASSES, ITEM_MOD_DOMAINS, ITEM_SOCKETS, ITEM_TAGS,
+    ITEM_TIERS, ITEM_WEIGHTS, MODS, SOCKETABLES, TIERS, get_formatters,
     hashvec::OpaqueIndex,
-    types::{Affix, BaseItemId, ModFamily, ModTag, Tier, get_matching_formatter},
+    types::{
+        Affix, BaseItemId, ModFamily, ModTag, QualityType, SocketableId, StatFormatter, StatID,
+        Tier, get_matching_formatter,
+    },
 };
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+/// Item classes which take weapon stats from runes
+pub const WEAPON_CLASSES: &[&str] = &[
+    "Claw",
+    "Dagger",
+    "Wand",
+    "One Hand Sword",
+    "One Hand Axe",
+    "One Hand Mace",
+    "Sceptre",
+    "Spear",
+    "Flail",
+    "Bow",
+    "Staff",
+    "Two Hand Sword",
+    "Two Hand Axe",
+    "Two Hand Mace",
+    "Warstaff",
+    "Crossbow",
+    "Talisman",
+];
+
+/// Item classes which take armour stats from runes
+pub const ARMOUR_CLASSES: &[&str] = &[
+    "Body Armour",
+    "Helmet",
+    "Gloves",
+    "Boots",
+    "Shield",
+    "Buckler",
+    "Focus",
+];
+
+/// Item classes which can have 2 sockets rather than 1
+const TWO_SOCKET_CLASSES: &[&str] = &[
+    "Body Armour",
+    "Bow",
+    "Staff",
+    "Two Hand Sword",
+    "Two Hand Axe",
+    "Two Hand Mace",
+    "Warstaff",
+    "Crossbow",
+    "Talisman",
+];
+
+/// Most sockets a base of this class can have, or 0 if it can't be socketed
+pub(crate) fn class_max_sockets(item_class: &str) -> usize {
+    if TWO_SOCKET_CLASSES.contains(&item_class) {
+        2
+    } else if WEAPON_CLASSES.contains(&item_class) || ARMOUR_CLASSES.contains(&item_class) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Affix limits and allowed rarities for a class of item.
+/// Equipment takes its limits from the game's Rarity table, see parsers::dat::load_equipment_rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemClassRules {
+    /// Max prefixes, and separately max suffixes, on a magic item
+    pub magic_affixes: usize,
+    /// Max prefixes, and separately max suffixes, on a rare item. None if it can't be rare.
+    pub rare_affixes: Option<usize>,
+}
+
+impl ItemClassRules {
+    /// Weapons, armour, jewellery and waystones. Only used if the Rarity table is missing
+    pub const EQUIPMENT: Self = Self {
+        magic_affixes: 1,
+        rare_affixes: Some(3),
+    };
+
+    /// Rules for a class, given the rules for equipment.
+    /// The tables don't say which classes have other limits, so those are listed by hand.
+    pub fn for_class(item_class: &str, equipment: Self) -> Self {
+        match item_class {
+            "Jewel" => Self {
+                magic_affixes: 1,
+                rare_affixes: Some(2),
+            },
+            "LifeFlask" | "ManaFlask" | "UtilityFlask" | "TowerAugmentation" | "Relic" => Self {
+                magic_affixes: 1,
+                rare_affixes: None,
+            },
+            _ => equipment,
+        }
+    }
+
+    /// Max prefixes, and separately max suffixes, at a given rarity
+    pub fn max_affixes(&self, rarity: Rarity) -> usize {
+        match rarity {
+            Rarity::Normal => 0,
+            Rarity::Magic => self.magic_affixes,
+            Rarity::Rare => self.rare_affixes.unwrap_or(0),
+        }
+    }
+
+    pub fn allows_rarity(&self, rarity: Rarity) -> bool {
+        rarity != Rarity::Rare || self.rare_affixes.is_some()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Rarity {
     Normal,
     Magic,
     Rare,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ItemState {
     pub base_type: BaseItemId,
     pub item_level: u32,
     pub rarity: Rarity,
     pub mods: Vec<OpaqueIndex<Tier>>,
+    /// Corrupted items can't be modified by any more currency
+    #[serde(default)]
+    pub corrupted: bool,
+    /// Corrupted implicits, which don't take up affix slots
+    #[serde(default)]
+    pub implicits: Vec<OpaqueIndex<Tier>>,
+    /// Mods which are locked in place and can't be removed
+    #[serde(default)]
+    pub fractured: Vec<OpaqueIndex<Tier>>,
+    /// Mod added by desecration, either still veiled or unveiled. Items can only have one.
+    #[serde(default)]
+    pub desecrated: Vec<OpaqueIndex<Tier>>,
+    /// Minimum modifier level of the mod revealed by unveiling, set by Ancient bones
+    #[serde(default)]
+    pub veiled_min_level: Option<u32>,
+    /// Concrete stat values for each mod, one per value range of its tier.
+    /// Mods without an entry haven't been rolled yet, and display their full range.
+    #[serde(default)]
+    pub rolls: BTreeMap<OpaqueIndex<Tier>, Vec<i32>>,
+    /// Outcome previewed by Hinekora's Lock, which is applied by the next use of that currency.
+    /// Using any other currency discards it
+    #[serde(default)]
+    pub foreseen: Option<Foreseen>,
+    /// Quality percentage, only affects mod weights when it has a type
+    #[serde(default)]
+    pub quality: u32,
+    /// Catalyst quality type on jewellery
+    #[serde(default)]
+    pub quality_type: Option<QualityType>,
+    /// Sockets on the item, with the rune or soul core in each if it's filled
+    #[serde(default)]
+    pub sockets: Vec<Option<SocketableId>>,
+}
+
+/// The result of a currency, seen before it was used
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Foreseen {
+    /// Name of the previewed currency
+    pub currency: String,
+    pub item: Box<ItemState>,
 }
 
 impl ItemState {
+    pub const MAX_QUALITY: u32 = 20;
+
+    /// Omen of Catalysing Exaltation consumes the quality to make it this many times as effective
+    pub const CATALYSING_BIAS: u32 = 5;
+
+    /// Spawn weight of a tier on this item, scaled up by catalyst quality if the mod has its tag
+    /// 0 if the tier can't roll on this base
+    pub fn weight(&self, tier_id: OpaqueIndex<Tier>) -> u32 {
+        self.biased_weight(tier_id, 1)
+    }
+
+    /// Spawn weight with the effect of catalyst quality multiplied by `bias`
+    pub fn biased_weight(&self, tier_id: OpaqueIndex<Tier>, bias: u32) -> u32 {
+        let tier = &TIERS[tier_id];
+        let modifier = &MODS[tier.mod_id];
+        let weight = ITEM_WEIGHTS
+            .get(&self.base_type)
+            .and_then(|weights| weights.get(&tier.id))
+            .copied()
+            .unwrap_or(0);
+
+        match self.quality_type {
+            Some(quality_type) if modifier.tags.contains(quality_type.tag()) => {
+                weight * (100 + self.quality * bias) / 100
+            }
+            _ => weight,
+        }
+    }
+
+    /// Most sockets the item can have, or 0 if its base can't be socketed
+    pub fn max_sockets(&self) -> usize {
+        ITEM_SOCKETS.get(&self.base_type).copied().unwrap_or(0)
+    }
+
+    /// Stats granted by everything socketed into the item, with repeated stats added together
+    pub fn socketed_stats(&self) -> Vec<(StatID, i32)> {
+        let is_weapon = ITEM_CLASSES
+            .get(&self.base_type)
+            .is_some_and(|class| WEAPON_CLASSES.contains(&class.as_str()));
+
+        self.sockets
+            .iter()
+            .flatten()
+            .flat_map(|name| {
+                let socketable = &SOCKETABLES[name];
+                if is_weapon {
+                    &socketable.weapon_stats
+                } else {
+                    &socketable.armour_stats
+                }
+            })
+            .fold(
+                vec![],
+                |mut totals: Vec<(StatID, i32)>, (stat_id, value)| {
+                    match totals.iter_mut().find(|(id, _)| id == stat_id) {
+                        Some((_, total)) => *total += value,
+                        None => totals.push((stat_id.clone(), *value)),
+                    }
+                    totals
+                },
+            )
+    }
+
+    /// Remove a mod along with anything attached to it
+    pub fn remove_mod(&mut self, tier_id: OpaqueIndex<Tier>) {
+        self.mods.retain(|&t| t != tier_id);
+        self.rolls.remove(&tier_id);
+        self.desecrated.retain(|&t| t != tier_id);
+        if veiled_tiers().contains(&tier_id) {
+            self.veiled_min_level = None;
+        }
+    }
+
     pub fn num_prefixes(&self) -> usize {
         self.mods
             .iter()
@@ -38,17 +261,27 @@ impl ItemState {
             .count()
     }
 
+    /// Affix limits for the item's class
+    pub fn rules(&self) -> ItemClassRules {
+        ITEM_CLASSES
+            .get(&self.base_type)
+            .and_then(|class| ITEM_CLASS_RULES.get(class))
+            .copied()
+            .unwrap_or(ItemClassRules::EQUIPMENT)
+    }
+
+    /// Most mods the item can have at its current rarity
+    pub fn max_mods(&self) -> usize {
+        self.rules().max_affixes(self.rarity) * 2
+    }
+
     /// Whether the item has room for a mod of the given type
     pub fn has_room(&self, affix: Affix) -> bool {
-        let max_affixes = match self.rarity {
-            Rarity::Normal => 0,
-            Rarity::Magic => 1,
-            Rarity::Rare => 3,
-        };
+        let max_affixes = self.rules().max_affixes(self.rarity);
         match affix {
             Affix::Prefix => self.num_prefixes() < max_affixes,
             Affix::Suffix => self.num_suffixes() < max_affixes,
-            Affix::Corrupted => unreachable!(),
+            Affix::Corrupted | Affix::Other => unreachable!(),
         }
     }
 
@@ -70,10 +303,10 @@ impl ItemState {
     pub fn mod_familities(&self) -> HashSet<ModFamily> {
         self.mods
             .iter()
-            .map(|tier_id| {
+            .flat_map(|tier_id| {
                 let tier = &TIERS[*tier_id];
                 let modifier = &MODS[tier.mod_id];
-                &modifier.family
+                &modifier.families
             })
             .cloned()
             .collect()
@@ -85,17 +318,14 @@ impl ItemState {
 
     /// Checks whether the current state of the item is valid
     pub fn is_valid(&self) -> bool {
-        let num_mods_ok = match self.rarity {
-            Rarity::Normal => self.mods.is_empty(),
-            Rarity::Magic => self.mods.len() <= 2,
-            Rarity::Rare => self.mods.len() <= 6,
-        };
+        let rules = self.rules();
+        let rarity_ok = rules.allows_rarity(self.rarity);
 
-        let num_affixes_ok = match self.rarity {
-            Rarity::Normal => true,
-            Rarity::Magic => self.num_prefixes() <= 1 && self.num_suffixes() <= 1,
-            Rarity::Rare => self.num_prefixes() <= 3 && self.num_suffixes() <= 3,
-        };
+        let num_mods_ok = self.mods.len() <= self.max_mods();
+
+        let max_affixes = rules.max_affixes(self.rarity);
+        let num_affixes_ok =
+            self.num_prefixes() <= max_affixes && self.num_suffixes() <= max_affixes;
 
         let mod_ilvls_ok = self.mods.iter().all(|tier_id| {
             let tier = &TIERS[*tier_id];
@@ -103,78 +333,317 @@ impl ItemState {
             tier.ilvl <= self.item_level
         });
 
-        let mod_families_ok = self
+        // No family is shared between mods
+        let num_families = self
             .mods
             .iter()
             .map(|tier_id| {
                 let tier = &TIERS[*tier_id];
-                let modifier = &MODS[tier.mod_id];
-
-                &modifier.family
+                MODS[tier.mod_id].families.len()
             })
-            .collect::<HashSet<_>>()
-            .len()
-            == self.mods.len();
+            .sum::<usize>();
+        let mod_families_ok = self.mod_familities().len() == num_families;
+
+        let fractured_ok = self.fractured.len() <= 1
+            && self
+                .fractured
+                .iter()
+                .all(|tier_id| self.mods.contains(tier_id));
+
+        let desecrated_ok = self.desecrated.len() <= 1
+            && self
+                .desecrated
+                .iter()
+                .all(|tier_id| self.mods.contains(tier_id));
+
+        let implicits_ok = (self.corrupted || self.implicits.is_empty())
+            && self
+                .implicits
+                .iter()
+                .all(|&tier_id| TIERS[tier_id].affix == Affix::Corrupted);
 
-        num_mods_ok && num_affixes_ok && mod_ilvls_ok && mod_families_ok
+        let rolls_ok = self.rolls.iter().all(|(tier_id, values)| {
+            let value_ranges = &TIERS[*tier_id].value_ranges;
+            (self.mods.contains(tier_id) || self.implicits.contains(tier_id))
+                && values.len() == value_ranges.len()
+                && values
+                    .iter()
+                    .zip(value_ranges)
+                    .all(|(value, [a, b])| (*a.min(b)..=*a.max(b)).contains(value))
+        });
+
+        let quality_ok = self.quality <= Self::MAX_QUALITY;
+
+        let sockets_ok = self.sockets.len() <= self.max_sockets()
+            && self
+                .sockets
+                .iter()
+                .flatten()
+                .all(|name| SOCKETABLES.contains_key(name));
+
+        rarity_ok
+            && num_mods_ok
+            && num_affixes_ok
+            && mod_ilvls_ok
+            && mod_families_ok
+            && fractured_ok
+            && desecrated_ok
+            && implicits_ok
+            && rolls_ok
+            && quality_ok
+            && sockets_ok
     }
 }
 
+/// Format stats as they appear in game, one line per formatter. The formatter for all of the stats
+/// together is tried first, then one per stat. Stats without a usable formatter are shown by
+/// their raw id instead. Eg. "local_physical_damage_+%: (50-60)"
+fn format_stats(stats: &[StatID], value_ranges: &[[i32; 2]], rolls: Option<&[i32]>) -> Vec<String> {
+    // Pick formatters using the rolled values if there are any, otherwise the tier minimums
+    let match_values = rolls
+        .map(<[i32]>::to_vec)
+        .unwrap_or_else(|| value_ranges.iter().map(|[min, _]| *min).collect::<Vec<_>>());
+    let format =
+        |formatter: &StatFormatter, value_ranges: &[[i32; 2]], rolls: Option<&[i32]>| match rolls {
+            Some(values) => formatter.format_values(values),
+            None => formatter.format_value_range(value_ranges),
+        };
+
+    if let Some(formatter) = get_formatters(&stats.join("|"))
+        .and_then(|formatters| get_matching_formatter(formatters, &match_values))
+    {
+        return vec![format(formatter, value_ranges, rolls)];
+    }
+
+    stats
+        .iter()
+        .zip(value_ranges.chunks(1))
+        .enumerate()
+        .map(|(i, (stat_id, value_range))| {
+            let rolls = rolls.and_then(|values| values.get(i..=i));
+            let match_values = match_values.get(i..=i).unwrap_or_default();
+            match get_formatters(stat_id)
+                .and_then(|formatters| get_matching_formatter(formatters, match_values))
+            {
+                Some(formatter) => format(formatter, value_range, rolls),
+                None => match rolls {
+                    Some([value]) => format!("{stat_id}: {value}"),
+                    _ => format!("{stat_id}: ({}-{})", value_range[0][0], value_range[0][1]),
+                },
+            }
+        })
+        .collect()
+}
+
 impl Display for ItemState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "{}", self.base_type)?;
         writeln!(f, "ilvl: {}", self.item_level)?;
         writeln!(f, "{:?}", self.rarity)?;
+        if self.quality > 0 {
+            match self.quality_type {
+                Some(quality_type) => writeln!(f, "Quality: +{}% ({quality_type})", self.quality)?,
+                None => writeln!(f, "Quality: +{}%", self.quality)?,
+            }
+        }
         writeln!(f, "=====================")?;
-        for tier_id in &self.mods {
+        for tier_id in self.implicits.iter().chain(&self.mods) {
             let tier = &TIERS[*tier_id];
+            let fractured = if self.fractured.contains(tier_id) {
+                " (fractured)"
+            } else {
+                ""
+            };
+            let desecrated = if self.desecrated.contains(tier_id) {
+                " (desecrated)"
+            } else {
+                ""
+            };
             let modifier = &MODS[tier.mod_id];
 
-            let formatters_key = modifier.stats.join("|");
-            if let Some(formatters) = FORMATTERS.get(&formatters_key) {
-                let formatter = get_matching_formatter(
-                    formatters,
-                    &tier
-                        .value_ranges
-                        .iter()
-                        .map(|[min, _]| *min)
-                        .collect::<Vec<_>>(),
-                );
-                // Match on multi-stat formatter
-                let string = formatter.format_value_range(&tier.value_ranges);
-                writeln!(f, "{}", string)?;
-            } else {
-                // Per-stat formatters
-                for (stat_id, value_range) in modifier.stats.iter().zip(tier.value_ranges.chunks(1))
-                {
-                    let Some(formatters) = FORMATTERS.get(stat_id) else {
-                        panic!("No formatter for stat: {:?}", stat_id);
-                    };
-                    let formatter = get_matching_formatter(
-                        formatters,
-                        &tier
-                            .value_ranges
-                            .iter()
-                            .map(|[min, _]| *min)
-                            .collect::<Vec<_>>(),
-                    );
-                    // TODO: select formatter based on tier
-                    let string = formatter.format_value_range(value_range);
-
-                    writeln!(f, "{}", string)?;
+            let rolls = self.rolls.get(tier_id).map(Vec::as_slice);
+            for line in format_stats(&modifier.stats, &tier.value_ranges, rolls) {
+                writeln!(f, "{}{}{}", line, fractured, desecrated)?;
+            }
+        }
+        if !self.sockets.is_empty() {
+            writeln!(f, "=====================")?;
+            writeln!(
+                f,
+                "Sockets: {}/{}",
+                self.sockets.iter().flatten().count(),
+                self.sockets.len()
+            )?;
+            for (stat_id, value) in self.socketed_stats() {
+                for line in format_stats(&[stat_id], &[[value, value]], Some(&[value])) {
+                    writeln!(f, "{line} (socketed)")?;
                 }
             }
         }
+        if self.corrupted {
+            writeln!(f, "Corrupted")?;
+        }
+        if let Some(foreseen) = &self.foreseen {
+            writeln!(f, "=====================")?;
+            writeln!(f, "Foreseen {}:", foreseen.currency)?;
+            write!(f, "{}", foreseen.item)?;
+        }
 
         Ok(())
     }
 }
 
-/// Get the pool of mods that could ever roll on this item, regardless of its current state
-pub fn get_valid_mods_for_item(item: &ItemState) -> Vec<OpaqueIndex<Tier>> {
+/// Get the pool of corrupted implicits that could be added to this item by corrupting it
+pub fn get_corrupted_implicits_for_item(item: &ItemState) -> Vec<OpaqueIndex<Tier>> {
+    let domains = get_all_mods_for_item(item)
+        .into_iter()
+        .map(|tier_id| TIERS[tier_id].mod_domain)
+        .collect::<HashSet<_>>();
+    let base_tags = ITEM_TAGS
+        .get(&item.base_type)
+        .into_iter()
+        .flatten()
+        .collect::<HashSet<_>>();
+
+    TIERS
+        .iter_opaque()
+        .filter(|(_, tier)| {
+            tier.affix == Affix::Corrupted
+                && domains.contains(&tier.mod_domain)
+                && item.item_level >= tier.ilvl
+                && tier.spawn_weight(&base_tags) > 0
+        })
+        .map(|(tier_id, _)| tier_id)
+        .collect()
+}
+
+/// The placeholder tiers added by desecration, which are replaced when unveiled
+pub fn veiled_tiers() -> [OpaqueIndex<Tier>; 2] {
+    [TIERS.opaque("VeiledPrefix"), TIERS.opaque("VeiledSuffix")]
+}
+
+/// Get the pool of mods that can be revealed by unveiling a desecrated mod on this item
+pub fn get_desecrated_mods_for_item(item: &ItemState) -> Vec<OpaqueIndex<Tier>> {
+    DESECRATED_TIERS
+        .get(&item.base_type)
+        .into_iter()
+        .flatten()
+        .map(|tier_id| TIERS.opaque(tier_id))
+        .filter(|&tier_id| item.item_level >= TIERS[tier_id].ilvl)
+        .collect()
+}
+
+/// Get every mod that can be on this item, including ones which can't be rolled randomly
+pub fn get_all_mods_for_item(item: &ItemState) -> Vec<OpaqueIndex<Tier>> {
+    let domain = ITEM_MOD_DOMAINS.get(&item.base_type);
     ITEM_TIERS[&item.base_type]
         .iter()
         .map(|tier_id| TIERS.opaque(tier_id))
-        .filter(|&tier_id| item.item_level >= TIERS[tier_id].ilvl)
+        .filter(|&tier_id| {
+            let tier = &TIERS[tier_id];
+            item.item_level >= tier.ilvl && domain.is_none_or(|&domain| tier.mod_domain == domain)
+        })
+        .collect()
+}
+
+/// Get the pool of mods that could ever roll on this item, regardless of its current state.
+/// Tiers without a spawn weight can never be rolled, so they're left out rather than guessed at.
+pub fn get_valid_mods_for_item(item: &ItemState) -> Vec<OpaqueIndex<Tier>> {
+    get_all_mods_for_item(item)
+        .into_iter()
+        .filter(|&tier_id| {
+            item.weight(tier_id) > 0 && MODS[TIERS[tier_id].mod_id].mod_type.is_spawnable()
+        })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        TIERS,
+        crafting::FilterStage,
+        currency::{CraftError, Currency, CurrencyType},
+        internal::test_data::{self, item, ring},
+        item_state::{ItemClassRules, ItemState, Rarity},
+        types::Affix,
+    };
+
+    #[test]
+    fn test_equipment() {
+        test_data::init();
+        let item = ring(
+            Rarity::Rare,
+            &["IncreasedLife1", "LifeAndMana1", "EssenceMana1"],
+        );
+
+        assert_eq!(item.rules(), ItemClassRules::EQUIPMENT);
+        assert_eq!(item.max_mods(), 6);
+        assert!(!item.has_room(Affix::Prefix));
+        assert!(item.has_room(Affix::Suffix));
+        assert!(item.is_valid());
+    }
+
+    #[test]
+    fn test_jewel() {
+        test_data::init();
+
+        // Only 2 of each affix when rare
+        let jewel = item("Ruby", Rarity::Rare, &["IncreasedLife1", "LifeAndMana1"]);
+        assert_eq!(jewel.max_mods(), 4);
+        assert!(!jewel.has_room(Affix::Prefix));
+        assert!(jewel.has_room(Affix::Suffix));
+        assert!(jewel.is_valid());
+        let mut too_many = jewel.clone();
+        too_many.mods.push(TIERS.opaque("EssenceMana1"));
+        assert!(!too_many.is_valid());
+
+        // And 1 when magic, so Augmentation can only add a suffix
+        let magic = item("Ruby", Rarity::Magic, &["IncreasedLife1"]);
+        assert!(!magic.has_room(Affix::Prefix));
+        assert!(magic.is_valid());
+        assert_eq!(
+            CurrencyType::Augmentation.can_be_used(&magic, &[], &HashSet::new()),
+            Err(CraftError::NotEnoughCandidates {
+                needed: 1,
+                filters: vec![FilterStage::Affix(Affix::Suffix)],
+            })
+        );
+    }
+
+    #[test]
+    fn test_magic_only() {
+        test_data::init();
+        let flask = item("Lesser Life Flask", Rarity::Magic, &["IncreasedLife1"]);
+
+        assert!(!flask.rules().allows_rarity(Rarity::Rare));
+        assert!(!flask.has_room(Affix::Prefix));
+        assert!(flask.has_room(Affix::Suffix));
+        assert!(flask.is_valid());
+
+        // Can't be made rare
+        let rare = ItemState {
+            rarity: Rarity::Rare,
+            ..flask.clone()
+        };
+        assert_eq!(rare.max_mods(), 0);
+        assert!(!rare.is_valid());
+        assert_eq!(
+            CurrencyType::Regal.can_be_used(&flask, &[], &HashSet::new()),
+            Err(CraftError::RarityNotAllowed {
+                rarity: Rarity::Rare
+            })
+        );
+        assert_eq!(
+            CurrencyType::Alchemy.can_be_used(
+                &item("Lesser Life Flask", Rarity::Normal, &[]),
+                &[],
+                &HashSet::new()
+            ),
+            Err(CraftError::RarityNotAllowed {
+                rarity: Rarity::Rare
+            })
+        );
+    }
+}
@@ -1,13 +1,19 @@
 pub mod crafting;
 pub mod currency;
+pub mod distribution;
 pub mod hashvec;
 mod internal;
 pub mod io;
 pub mod item_state;
+pub mod markov;
 pub mod parsers;
 pub mod strategy;
 pub mod types;
 pub mod ui;
 pub mod util;
 
-pub use internal::{CURRENCIES, FORMATTERS, ITEM_TIERS, MODS, TIERS, init};
+pub use internal::{
+    CURRENCIES, DESECRATED_TIERS, FORMATTERS, ITEM_CLASS_RULES, ITEM_CLASSES, ITEM_MOD_DOMAINS,
+    ITEM_SOCKETS, ITEM_TAGS, ITEM_TIERS, ITEM_WEIGHTS, MODS, SOCKETABLES, TIERS, get_formatters,
+    init, language, missing_formatters, set_language,
+};
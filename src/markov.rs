@@ -0,0 +1,549 @@
+/**
+*   Exact evaluation of a Strategy as an absorbing Markov chain over item states
+*/
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    currency::{CraftError, Currency, CurrencyType},
+    distribution::ItemDistribution,
+    hashvec::OpaqueIndex,
+    item_state::ItemState,
+    strategy::Strategy,
+    types::{Omen, Tier},
+    util::rand::CraftRng,
+};
+
+/// Probability below this is treated as 0
+const TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug)]
+pub struct Evaluation {
+    /// Probability of the item ending up in each step. Only terminal steps can be non-zero.
+    pub end_probabilities: Vec<f64>,
+    /// Expected number of times each step's craft is used. Infinite for steps the item can get
+    /// stuck looping through.
+    pub expected_crafts: Vec<f64>,
+    /// Expected number of each currency used
+    pub expected_currencies: Vec<(CurrencyType, f64)>,
+    /// Expected number of each omen used
+    pub expected_omens: HashMap<Omen, f64>,
+    /// Probability of the item getting stuck in states which can never reach a terminal step
+    pub unresolved: f64,
+    /// Number of unique item states visited
+    pub num_states: usize,
+    /// Whether any of the transitions were estimated by sampling
+    pub approximate: bool,
+}
+
+impl Evaluation {
+    /// Whether the strategy always reaches a terminal step
+    pub fn finished(&self) -> bool {
+        self.unresolved < TOLERANCE
+    }
+}
+
+#[derive(Debug)]
+pub enum EvaluationError {
+    InvalidCraft {
+        item: ItemState,
+        currency: CurrencyType,
+        omens: HashSet<Omen>,
+        reason: CraftError,
+    },
+    NoMatchingState {
+        item: ItemState,
+    },
+    /// The strategy has conditions on rolled values, which aren't tracked exactly
+    UsesRolls,
+}
+
+/// What happens to an item in a given state
+enum Transition {
+    /// Strategy step with no action
+    End(usize),
+    /// Strategy step with a craft
+    Craft {
+        step: usize,
+        outcomes: ItemDistribution,
+    },
+}
+
+/// Enumerate every item state the strategy can reach, then solve the absorbing Markov chain for the
+/// probability of ending in each terminal step and the expected number of crafts.
+/// Currencies without exact outcomes are estimated by sampling them `num_samples` times.
+pub fn evaluate(
+    strategy: &Strategy,
+    base_item: &ItemState,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+    num_samples: usize,
+    rng: &mut CraftRng,
+) -> Result<Evaluation, Box<EvaluationError>> {
+    if strategy.uses_rolls() {
+        return Err(Box::new(EvaluationError::UsesRolls));
+    }
+
+    // Find every reachable state, in the order they were found
+    let mut approximate = false;
+    let mut states = vec![];
+    let mut indices = HashMap::<ItemState, usize>::new();
+    let mut transitions = vec![];
+    // Normalised the same way as every outcome
+    let start = ItemDistribution::certain(base_item.clone())
+        .iter()
+        .map(|(item, _)| item.clone())
+        .next()
+        .unwrap();
+    indices.insert(start.clone(), 0);
+    states.push(start);
+    while transitions.len() < states.len() {
+        let item = &states[transitions.len()];
+        let transition = get_transition(
+            strategy,
+            item,
+            candidate_tiers,
+            num_samples,
+            rng,
+            &mut approximate,
+        )?;
+        if let Transition::Craft { outcomes, .. } = &transition {
+            for (outcome, _) in outcomes.iter() {
+                if !indices.contains_key(outcome) {
+                    indices.insert(outcome.clone(), states.len());
+                    states.push(outcome.clone());
+                }
+            }
+        }
+        transitions.push(transition);
+    }
+
+    let solution = solve(&transitions, &indices, strategy.0.len());
+
+    // Coalesce steps into currency & omen totals
+    let mut expected_currencies = Vec::<(CurrencyType, f64)>::new();
+    let mut expected_omens = HashMap::new();
+    for ((_, action), &count) in strategy.0.iter().zip(&solution.expected_crafts) {
+        let Some((omens, currency)) = action else {
+            continue;
+        };
+
+        match expected_currencies.iter_mut().find(|(c, _)| c == currency) {
+            Some((_, total)) => *total += count,
+            None => expected_currencies.push((currency.clone(), count)),
+        }
+        for &omen in omens {
+            *expected_omens.entry(omen).or_default() += count;
+        }
+    }
+
+    Ok(Evaluation {
+        end_probabilities: solution.end_probabilities,
+        expected_crafts: solution.expected_crafts,
+        expected_currencies,
+        expected_omens,
+        unresolved: solution.unresolved,
+        num_states: states.len(),
+        approximate,
+    })
+}
+
+/// Results of solving the chain from the first state
+struct Solution {
+    end_probabilities: Vec<f64>,
+    expected_crafts: Vec<f64>,
+    unresolved: f64,
+}
+
+/// Solve for the absorption probabilities and expected visits from the first state, by eliminating
+/// the transient states one at a time.
+/// States which can never reach a terminal step are absorbing too, as the item is stuck in them.
+fn solve(
+    transitions: &[Transition],
+    indices: &HashMap<ItemState, usize>,
+    num_steps: usize,
+) -> Solution {
+    let num_states = transitions.len();
+    let successors = transitions
+        .iter()
+        .map(|transition| match transition {
+            Transition::End(_) => vec![],
+            Transition::Craft { outcomes, .. } => outcomes
+                .iter()
+                .filter(|&(_, p)| p > 0.)
+                .map(|(outcome, p)| (indices[outcome], p))
+                .collect::<Vec<_>>(),
+        })
+        .collect::<Vec<_>>();
+
+    // Walk backwards from the terminal steps to find the states which can finish
+    let mut incoming = vec![vec![]; num_states];
+    for (from, edges) in successors.iter().enumerate() {
+        for &(to, _) in edges {
+            incoming[to].push(from);
+        }
+    }
+    let mut can_finish = vec![false; num_states];
+    let mut stack = (0..num_states)
+        .filter(|&i| matches!(transitions[i], Transition::End(_)))
+        .collect::<Vec<_>>();
+    while let Some(i) = stack.pop() {
+        if !can_finish[i] {
+            can_finish[i] = true;
+            stack.extend(incoming[i].iter().copied());
+        }
+    }
+
+    // Each transient state's value is x = b + sum(P * x) over the transient states it leads to.
+    // b holds the probability of ending in each step, the number of crafts of each step, and the
+    // probability of getting stuck
+    let stuck = 2 * num_steps;
+    let mut b = vec![vec![0.; 2 * num_steps + 1]; num_states];
+    let mut rows = vec![HashMap::<usize, f64>::new(); num_states];
+    let mut expected_crafts = vec![0.; num_steps];
+    for (i, transition) in transitions.iter().enumerate() {
+        let Transition::Craft { step, .. } = transition else {
+            continue;
+        };
+        if !can_finish[i] {
+            // Every state is reachable, so looping here happens with some probability
+            expected_crafts[*step] = f64::INFINITY;
+            continue;
+        }
+
+        b[i][num_steps + step] = 1.;
+        for &(to, p) in &successors[i] {
+            match &transitions[to] {
+                Transition::End(end_step) => b[i][*end_step] += p,
+                Transition::Craft { .. } if !can_finish[to] => b[i][stuck] += p,
+                Transition::Craft { .. } => *rows[i].entry(to).or_default() += p,
+            }
+        }
+    }
+    let mut predecessors = vec![HashSet::new(); num_states];
+    for (from, row) in rows.iter().enumerate() {
+        for &to in row.keys() {
+            predecessors[to].insert(from);
+        }
+    }
+
+    // Eliminate the latest found states first, leaving the first state until last
+    for i in (0..num_states).rev() {
+        // Remove the self loop by summing the geometric series
+        let p_loop = rows[i].remove(&i).unwrap_or_default();
+        predecessors[i].remove(&i);
+        let scale = 1. / (1. - p_loop);
+        b[i].iter_mut().for_each(|x| *x *= scale);
+        rows[i].values_mut().for_each(|p| *p *= scale);
+
+        // Substitute into every state leading to this one
+        let row = std::mem::take(&mut rows[i]);
+        let b_i = b[i].clone();
+        for from in std::mem::take(&mut predecessors[i]) {
+            let p = rows[from].remove(&i).unwrap_or_default();
+            b[from].iter_mut().zip(&b_i).for_each(|(x, y)| *x += p * y);
+            for (&to, &q) in &row {
+                *rows[from].entry(to).or_default() += p * q;
+                predecessors[to].insert(from);
+            }
+        }
+        for to in row.keys() {
+            predecessors[*to].remove(&i);
+        }
+    }
+
+    let first = match &transitions[0] {
+        Transition::End(step) => {
+            let mut first = vec![0.; 2 * num_steps + 1];
+            first[*step] = 1.;
+            first
+        }
+        Transition::Craft { .. } if !can_finish[0] => {
+            let mut first = vec![0.; 2 * num_steps + 1];
+            first[stuck] = 1.;
+            first
+        }
+        Transition::Craft { .. } => b[0].clone(),
+    };
+    for (crafts, x) in expected_crafts.iter_mut().zip(&first[num_steps..stuck]) {
+        *crafts += x;
+    }
+
+    Solution {
+        end_probabilities: first[..num_steps].to_vec(),
+        expected_crafts,
+        unresolved: first[stuck],
+    }
+}
+
+fn get_transition(
+    strategy: &Strategy,
+    item: &ItemState,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+    num_samples: usize,
+    rng: &mut CraftRng,
+    approximate: &mut bool,
+) -> Result<Transition, Box<EvaluationError>> {
+    let Some(step) = strategy.get(item) else {
+        return Err(Box::new(EvaluationError::NoMatchingState {
+            item: item.clone(),
+        }));
+    };
+
+    let Some((omens, currency)) = &strategy.0[step].1 else {
+        return Ok(Transition::End(step));
+    };
+
+    if let Err(reason) = currency.can_be_used(item, candidate_tiers, omens) {
+        return Err(Box::new(EvaluationError::InvalidCraft {
+            item: item.clone(),
+            currency: currency.clone(),
+            omens: omens.clone(),
+            reason,
+        }));
+    }
+
+    let outcomes = currency
+        .outcomes(item, candidate_tiers, omens)
+        .unwrap_or_else(|| {
+            // Fall back to estimating the outcomes
+            *approximate = true;
+            (0..num_samples).fold(ItemDistribution::default(), |mut dist, _| {
+                let mut item = item.clone();
+                currency.craft(&mut item, candidate_tiers, omens, rng);
+                dist.add(item, 1. / num_samples as f64);
+                dist
+            })
+        });
+
+    Ok(Transition::Craft { step, outcomes })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::SeedableRng;
+
+    use crate::{
+        MODS,
+        currency::{CraftError, CurrencyType},
+        internal::test_data::{self, ring},
+        item_state::{ItemState, Rarity, get_valid_mods_for_item},
+        markov::{Evaluation, EvaluationError, evaluate},
+        strategy::{Condition, ConditionGroup, ModifierCondition, Strategy},
+        types::Omen,
+        util::rand::CraftRng,
+    };
+
+    fn condition(rarity: Rarity, groups: Vec<ConditionGroup>) -> Condition {
+        Condition {
+            rarity,
+            corrupted: None,
+            foreseen: None,
+            groups,
+        }
+    }
+
+    /// Has a tier 1 life mod
+    fn has_life() -> ConditionGroup {
+        ConditionGroup::Count {
+            count: 1..=1,
+            mods: vec![ModifierCondition {
+                mod_group: MODS.opaque("IncreasedLife"),
+                levels: vec![1],
+                fractured: None,
+                min_roll: None,
+            }],
+        }
+    }
+
+    fn craft(currency: CurrencyType) -> Option<(HashSet<Omen>, CurrencyType)> {
+        Some((HashSet::new(), currency))
+    }
+
+    /// A normal ring which can only roll the level 1 tiers: one life, one hybrid and one resistance
+    fn base_item() -> ItemState {
+        ItemState {
+            item_level: 1,
+            ..ring(Rarity::Normal, &[])
+        }
+    }
+
+    fn run(strategy: &Strategy) -> Result<Evaluation, Box<EvaluationError>> {
+        let item = base_item();
+        evaluate(
+            strategy,
+            &item,
+            &get_valid_mods_for_item(&item),
+            10,
+            &mut CraftRng::seed_from_u64(0),
+        )
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                actual == expected || (actual - expected).abs() < 1e-6,
+                "{actual} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_geometric() {
+        test_data::init();
+
+        // Transmute, then annul and augment until the mod is life
+        let strategy = Strategy(vec![
+            (
+                condition(Rarity::Normal, vec![]),
+                craft(CurrencyType::Transmute),
+            ),
+            (condition(Rarity::Magic, vec![has_life()]), None),
+            (
+                condition(
+                    Rarity::Magic,
+                    vec![ConditionGroup::AffixCount {
+                        suffixes: 0..=1,
+                        prefixes: 0..=1,
+                        affixes: 1..=1,
+                    }],
+                ),
+                craft(CurrencyType::Annulment),
+            ),
+            (
+                condition(Rarity::Magic, vec![]),
+                craft(CurrencyType::Augmentation),
+            ),
+        ]);
+        let evaluation = run(&strategy).unwrap();
+
+        // Each roll is life with p = 1/3, so after the transmute misses (2/3) it takes 1/p = 3
+        // tries on average: 2 expected augments, each preceded by an annul
+        assert!(evaluation.finished());
+        assert!(!evaluation.approximate);
+        assert_close(&evaluation.end_probabilities, &[0., 1., 0., 0.]);
+        assert_close(&evaluation.expected_crafts, &[1., 0., 2., 2.]);
+    }
+
+    #[test]
+    fn test_evaluate_stuck() {
+        test_data::init();
+
+        // Divines don't change the mods, so anything but life loops forever
+        let strategy = Strategy(vec![
+            (
+                condition(Rarity::Normal, vec![]),
+                craft(CurrencyType::Transmute),
+            ),
+            (condition(Rarity::Magic, vec![has_life()]), None),
+            (
+                condition(Rarity::Magic, vec![]),
+                craft(CurrencyType::Divine),
+            ),
+        ]);
+        let evaluation = run(&strategy).unwrap();
+
+        assert!(!evaluation.finished());
+        assert!((evaluation.unresolved - 2. / 3.).abs() < 1e-6);
+        assert_close(&evaluation.end_probabilities, &[0., 1. / 3., 0.]);
+        assert_close(&evaluation.expected_crafts, &[1., 0., f64::INFINITY]);
+    }
+
+    #[test]
+    fn test_evaluate_approximate() {
+        test_data::init();
+
+        // Alchemy has no exact outcomes, so is sampled
+        let strategy = Strategy(vec![
+            (
+                condition(Rarity::Normal, vec![]),
+                craft(CurrencyType::Alchemy),
+            ),
+            (condition(Rarity::Rare, vec![]), None),
+        ]);
+        let evaluation = run(&strategy).unwrap();
+
+        assert!(evaluation.approximate);
+        assert!(evaluation.finished());
+        assert_close(&evaluation.end_probabilities, &[0., 1.]);
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        test_data::init();
+
+        // Nothing matches the item once it's magic
+        let strategy = Strategy(vec![(
+            condition(Rarity::Normal, vec![]),
+            craft(CurrencyType::Transmute),
+        )]);
+        assert!(matches!(
+            *run(&strategy).unwrap_err(),
+            EvaluationError::NoMatchingState { item } if item.rarity == Rarity::Magic
+        ));
+
+        // Augmentations need a magic item
+        let strategy = Strategy(vec![(
+            condition(Rarity::Normal, vec![]),
+            craft(CurrencyType::Augmentation),
+        )]);
+        assert!(matches!(
+            *run(&strategy).unwrap_err(),
+            EvaluationError::InvalidCraft {
+                reason: CraftError::WrongRarity {
+                    expected: Rarity::Magic,
+                    actual: Rarity::Normal,
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_foreseen() {
+        test_data::init();
+        let has_fire = || ConditionGroup::Count {
+            count: 1..=1,
+            mods: vec![ModifierCondition {
+                mod_group: MODS.opaque("FireResistance"),
+                levels: vec![1],
+                fractured: None,
+                min_roll: None,
+            }],
+        };
+        let foreseen = |foreseen, groups| Condition {
+            foreseen: Some(foreseen),
+            ..condition(Rarity::Rare, groups)
+        };
+
+        // Preview an exalt, apply it if it's fire resistance or discard it with a divine otherwise
+        let strategy = Strategy(vec![
+            (foreseen(false, vec![has_fire()]), None),
+            (
+                foreseen(false, vec![]),
+                Some((HashSet::from([Omen::HinekorasLock]), CurrencyType::Exalt)),
+            ),
+            (foreseen(true, vec![has_fire()]), craft(CurrencyType::Exalt)),
+            (foreseen(true, vec![]), craft(CurrencyType::Divine)),
+        ]);
+        let item = ItemState {
+            item_level: 1,
+            ..ring(Rarity::Rare, &["IncreasedLife1"])
+        };
+        let evaluation = evaluate(
+            &strategy,
+            &item,
+            &get_valid_mods_for_item(&item),
+            10,
+            &mut CraftRng::seed_from_u64(0),
+        )
+        .unwrap();
+
+        // Half the previews are fire resistance, so it takes 2 on average
+        assert!(evaluation.finished());
+        assert!(!evaluation.approximate);
+        assert_close(&evaluation.end_probabilities, &[1., 0., 0., 0.]);
+        assert_close(&evaluation.expected_crafts, &[0., 2., 1., 1.]);
+    }
+}
@@ -96,8 +96,8 @@ impl Affix {
         match self {
             Affix::Prefix => types::Affix::Prefix,
             Affix::Suffix => types::Affix::Suffix,
-            // TODO:
-            Affix::Socket => types::Affix::Corrupted,
+            // Socketed stats are loaded from the soul cores table rather than as mods
+            Affix::Socket => types::Affix::Other,
         }
     }
 }
@@ -0,0 +1,321 @@
+/**
+.csd - the game's stat description files, Eg. Metadata/StatDescriptions/stat_descriptions.csd
+UTF-16 text made of blocks like:
+    description
+        2 local_minimum_added_physical_damage local_maximum_added_physical_damage
+        1
+            # # "Adds {0} to {1} Physical Damage"
+        lang "French"
+        1
+            # # "Ajoute {0} à {1} dégâts physiques"
+Each variant line is one condition per stat, the format string, then index handlers.
+Eg. 1|# "{0:+d}% increased Attack Speed" negate 1
+*/
+use std::{collections::BTreeMap, fs, iter::Peekable, path::Path};
+
+use regex::Regex;
+
+use crate::{
+    parsers::stat_desc::{Modifier, Root},
+    types::{Condition, DEFAULT_LANGUAGE, Language, StatFormatter},
+};
+
+/// A description block for one group of stats
+#[derive(Debug)]
+pub struct Description {
+    /// Eg. additional_strength
+    pub ids: Vec<String>,
+    /// Language -> formatters, the first matching one is used.
+    /// Blocks without a "lang" header are English
+    pub languages: BTreeMap<Language, Vec<StatFormatter>>,
+}
+
+/// Load a description file and everything it includes, in the format `init` expects
+/// `path` is relative to the root of the extracted bundle
+pub fn load(bundle_root: &Path, path: &str) -> Root {
+    to_root(load_descriptions(bundle_root, path))
+}
+
+/// Convert parsed descriptions to the format `init` expects
+pub fn to_root(descriptions: Vec<Description>) -> Root {
+    descriptions
+        .into_iter()
+        .map(|description| Modifier {
+            ids: description.ids,
+            languages: description.languages,
+            trade_stats: None,
+        })
+        .collect()
+}
+
+/// Load a description file, with the contents of included files first.
+/// Extracted bundle paths are lowercase, while includes are written with capitals
+pub fn load_descriptions(bundle_root: &Path, path: &str) -> Vec<Description> {
+    let full_path = bundle_root.join(path.to_lowercase());
+    let bytes = fs::read(&full_path).unwrap_or_else(|_| panic!("Failed to read: {:?}", full_path));
+    let text = decode(&bytes);
+
+    // Eg. include "Metadata/StatDescriptions/stat_descriptions.csd"
+    let mut descriptions = text
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("include "))
+        .flat_map(|include| load_descriptions(bundle_root, include.trim().trim_matches('"')))
+        .collect::<Vec<_>>();
+    descriptions.extend(parse(&text));
+
+    descriptions
+}
+
+/// Parse a single description file, ignoring includes
+pub fn parse(text: &str) -> Vec<Description> {
+    let mut lines = text
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .peekable();
+
+    let mut descriptions = vec![];
+    while let Some(line) = lines.next() {
+        if line.split_whitespace().next() == Some("description") {
+            descriptions.push(parse_description(&mut lines));
+        }
+    }
+
+    descriptions
+}
+
+/// Files are normally UTF-16 with a byte order mark
+fn decode(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => String::from_utf16_lossy(
+            &rest
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Parse the body of a description block, after the "description" line
+fn parse_description(lines: &mut Peekable<impl Iterator<Item = String>>) -> Description {
+    // Eg. 2 local_minimum_added_physical_damage local_maximum_added_physical_damage
+    let ids = lines
+        .next()
+        .expect("Description without stats")
+        .split_whitespace()
+        .skip(1)
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut languages = BTreeMap::new();
+    let mut language = DEFAULT_LANGUAGE.to_string();
+    loop {
+        let count = lines
+            .next()
+            .and_then(|line| line.parse::<usize>().ok())
+            .unwrap_or_else(|| panic!("Missing variant count for {:?}", ids));
+        let formatters = lines
+            .by_ref()
+            .take(count)
+            .map(|line| parse_variant(&line, ids.len()))
+            .collect::<Vec<_>>();
+        languages.insert(language, formatters);
+
+        // Translations follow as "lang" blocks
+        match lines.peek().and_then(|line| line.strip_prefix("lang ")) {
+            Some(name) => language = name.trim().trim_matches('"').to_string(),
+            None => break,
+        }
+        lines.next();
+    }
+
+    Description { ids, languages }
+}
+
+/// Eg. 1|# # "Adds {0} to {1} Physical Damage" negate 1
+fn parse_variant(line: &str, num_stats: usize) -> StatFormatter {
+    let (Some(start), Some(end)) = (line.find('"'), line.rfind('"')) else {
+        panic!("Variant without a format string: {line}");
+    };
+
+    let conditions = line[..start]
+        .split_whitespace()
+        .map(parse_condition)
+        .collect::<Vec<_>>();
+    let (string, formats) = parse_format_string(&line[start + 1..end], num_stats);
+
+    // Handlers are followed by the (1-based) stat index they apply to, others are skipped.
+    // Eg. reminderstring ReminderTextLifeLeech, canonical_line
+    let mut index_handlers = vec![vec![]; num_stats];
+    let tokens = line[end + 1..].split_whitespace().collect::<Vec<_>>();
+    for pair in tokens.windows(2) {
+        if let Ok(index) = pair[1].parse::<usize>()
+            && (1..=num_stats).contains(&index)
+        {
+            index_handlers[index - 1].push(pair[0].to_string());
+        }
+    }
+
+    StatFormatter {
+        conditions,
+        formats,
+        index_handlers,
+        string,
+    }
+}
+
+/// Eg. #, 5, 1|#, #|-1, !0
+fn parse_condition(token: &str) -> Condition {
+    let bound = |s: &str| s.parse::<i32>().ok();
+
+    if let Some(value) = token.strip_prefix('!') {
+        Condition {
+            min: bound(value),
+            max: bound(value),
+            negate: Some(true),
+        }
+    } else if let Some((min, max)) = token.split_once('|') {
+        Condition {
+            min: bound(min),
+            max: bound(max),
+            negate: None,
+        }
+    } else {
+        Condition {
+            min: bound(token),
+            max: bound(token),
+            negate: None,
+        }
+    }
+}
+
+/// Normalise placeholders to "{i}", and work out how each value is displayed.
+/// Eg. "{0:+d}% increased {1}" -> ("{0}% increased {1}", ["+#", "#"])
+/// Values without a placeholder are "ignore"d
+fn parse_format_string(string: &str, num_stats: usize) -> (String, Vec<String>) {
+    let regex_placeholder = Regex::new(r"\{(\d*)(?::([^}]*))?\}").unwrap();
+
+    let mut formats = vec!["ignore".to_string(); num_stats];
+    let mut next_index = 0;
+    let string = regex_placeholder
+        .replace_all(string, |caps: &regex::Captures| {
+            // "{}" uses the next value in order
+            let index = caps[1].parse::<usize>().unwrap_or(next_index);
+            next_index = index + 1;
+
+            if let Some(format) = formats.get_mut(index) {
+                *format = match caps.get(2).map(|m| m.as_str()) {
+                    Some(spec) if spec.contains('+') => "+#",
+                    _ => "#",
+                }
+                .to_string();
+            }
+
+            format!("{{{index}}}")
+        })
+        .into_owned();
+
+    (string, formats)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parsers::csd::parse,
+        types::{DEFAULT_LANGUAGE, get_matching_formatter},
+    };
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+no_description dummy_stat
+description
+	2 local_minimum_added_physical_damage local_maximum_added_physical_damage
+	1
+		# # "Adds {0} to {1} Physical Damage"
+description
+	1 attack_speed_+%
+	2
+		1|# "{0:+d}% increased Attack Speed"
+		#|-1 "{0}% reduced Attack Speed" negate 1 reminderstring ReminderTextAttackSpeed
+	lang "French"
+	1
+		# "{}% de vitesse d'attaque"
+"#;
+        let descriptions = parse(text);
+        assert_eq!(descriptions.len(), 2);
+
+        let damage = &descriptions[0].languages[DEFAULT_LANGUAGE][0];
+        assert_eq!(damage.formats, ["#", "#"]);
+        assert_eq!(damage.format_values(&[3, 5]), "Adds 3 to 5 Physical Damage");
+
+        assert_eq!(descriptions[1].ids, ["attack_speed_+%"]);
+        let speed = &descriptions[1].languages[DEFAULT_LANGUAGE];
+        assert_eq!(speed.len(), 2);
+        assert_eq!(speed[0].trade_string(), "+#% increased Attack Speed");
+        assert_eq!(speed[0].conditions[0].min, Some(1));
+        assert_eq!(speed[0].conditions[0].max, None);
+        assert_eq!(speed[1].index_handlers, [["negate"]]);
+
+        let french = &descriptions[1].languages["French"];
+        assert_eq!(french[0].string, "{0}% de vitesse d'attaque");
+    }
+
+    #[test]
+    fn test_format() {
+        let text = r#"
+description
+	1 attack_speed_+%
+	2
+		1|# "{0:+d}% increased Attack Speed"
+		#|-1 "{0}% reduced Attack Speed" negate 1
+description
+	1 base_skill_effect_duration
+	1
+		# "Base duration is {0} seconds" milliseconds_to_seconds_2dp_if_required 1
+description
+	1 life_regeneration_rate_per_minute_%
+	2
+		0 "No [Life] Regeneration"
+		!0 "Regenerate {0}% of maximum [Life|Life] per second" per_minute_to_per_second 1
+"#;
+        let descriptions = parse(text);
+        let format = |i: usize, values: &[i32]| {
+            let formatters = &descriptions[i].languages[DEFAULT_LANGUAGE];
+            get_matching_formatter(formatters, values)
+                .unwrap()
+                .format_values(values)
+        };
+
+        assert_eq!(format(0, &[12]), "+12% increased Attack Speed");
+        assert_eq!(format(0, &[-12]), "12% reduced Attack Speed");
+        assert_eq!(format(1, &[1500]), "Base duration is 1.5 seconds");
+        assert_eq!(format(2, &[0]), "No Life Regeneration");
+        assert_eq!(
+            format(2, &[90]),
+            "Regenerate 1.5% of maximum Life per second"
+        );
+
+        let speed = &descriptions[0].languages[DEFAULT_LANGUAGE][1];
+        assert_eq!(
+            speed.format_value_range(&[[-20, -10]]),
+            "(10-20)% reduced Attack Speed"
+        );
+
+        // Reading values back out of formatted lines
+        let speed = &descriptions[0].languages[DEFAULT_LANGUAGE];
+        assert_eq!(
+            speed[1].parse_values("12% reduced Attack Speed"),
+            Some(vec![Some(-12)])
+        );
+        assert_eq!(speed[0].parse_values("12% reduced Attack Speed"), None);
+        let duration = &descriptions[1].languages[DEFAULT_LANGUAGE][0];
+        assert_eq!(
+            duration.parse_values("Base duration is 1.5 seconds"),
+            Some(vec![Some(1500)])
+        );
+    }
+}
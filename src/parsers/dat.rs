@@ -1,13 +1,21 @@
 #![allow(non_snake_case)]
 use std::{collections::HashMap, fs, path::Path};
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use anyhow::Context;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{DeserializeOwned, IntoDeserializer, Visitor, value::MapDeserializer},
+    forward_to_deserialize_any,
+};
+use serde_json::{Map, Value};
 
 use crate::{
     currency::{CurrencyType, Essence, PerfectEssence},
     hashvec::HashVec,
     internal::TIERS,
-    types::{Affix, ModGroup, ModType, Modifier, StatID, Tier, TierId},
+    item_state::ItemClassRules,
+    parsers::datc64::{self, Schema},
+    types::{Affix, ModGroup, ModType, Modifier, Socketable, SocketableId, StatID, Tier, TierId},
 };
 
 /// Deserialise any json-encoded value
@@ -48,6 +56,114 @@ pub trait RecordLoader: DeserializeOwned {
 
 impl<T: DeserializeOwned> RecordLoader for T {}
 
+/// Load a table straight from the game's .datc64 file
+fn load_datc64_table<T: DeserializeOwned>(
+    bundle_root: &Path,
+    schema: &Schema,
+    name: &str,
+) -> anyhow::Result<Vec<T>> {
+    let table = schema
+        .table(name)
+        .with_context(|| format!("Table not in schema: {name}"))?;
+    let path = bundle_root.join(format!("data/{}.datc64", name.to_lowercase()));
+    deserialize_rows(name, datc64::load_table(&path, table)?)
+}
+
+/// Deserialise the rows of a .datc64 table into records
+fn deserialize_rows<T: DeserializeOwned>(
+    name: &str,
+    rows: Vec<Map<String, Value>>,
+) -> anyhow::Result<Vec<T>> {
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let columns = row
+                .into_iter()
+                .map(|(column, value)| (column.clone(), ColumnValue { column, value }));
+            T::deserialize(MapDeserializer::new(columns))
+                .with_context(|| format!("Failed to deserialise {name} row {i}"))
+        })
+        .collect()
+}
+
+/// Load a table which may be missing from the schema or the bundle, as if it were empty
+fn optional_datc64_table<T: DeserializeOwned>(
+    bundle_root: &Path,
+    schema: &Schema,
+    name: &str,
+) -> anyhow::Result<Vec<T>> {
+    let path = bundle_root.join(format!("data/{}.datc64", name.to_lowercase()));
+    if schema.table(name).is_some() && path.exists() {
+        load_datc64_table(bundle_root, schema, name)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// A value read from a .datc64 column, which names the column in deserialisation errors.
+/// Lists read as strings are json-encoded to match the CSV exports, so the same records can be
+/// used for both
+struct ColumnValue {
+    column: String,
+    value: Value,
+}
+
+impl ColumnValue {
+    fn error(column: &str, error: serde_json::Error) -> serde_json::Error {
+        serde::de::Error::custom(format!("column {column}: {error}"))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for ColumnValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ColumnValue {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.value
+            .deserialize_any(visitor)
+            .map_err(|e| Self::error(&self.column, e))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Array(_) => visitor.visit_string(self.value.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Load a table which may be missing from the data directory, as if it were empty
+fn optional_table<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    if path.exists() {
+        T::load_from_path(path).collect()
+    } else {
+        vec![]
+    }
+}
+
 pub trait RecordSaver {
     fn save_to_csv(&self, path: &Path);
 }
@@ -85,6 +201,8 @@ pub struct ModsRecord {
     pub GenerationType: u32,
     /// Min ilvl
     pub Level: u32,
+    /// Can only be added by an essence, never rolled randomly
+    pub IsEssenceOnlyModifier: bool,
     /// Eg. of the Mongoose
     pub Name: String,
     /// Index into Stats table
@@ -113,6 +231,17 @@ pub struct ModsRecord {
         serialize_with = "serialize_json_encoded"
     )]
     pub Stat4Value: [i32; 2],
+    /// Index into Tags table, the first one the base item has decides the spawn weight
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub SpawnWeight_Tags: Vec<usize>,
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub SpawnWeight_Values: Vec<u32>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -135,6 +264,12 @@ pub struct BaseItemTypesRecord {
     pub Name: String,
     pub ItemClass: usize,
     pub ModDomain: usize,
+    /// Index into Tags table
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub Tags: Vec<usize>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -180,6 +315,43 @@ pub struct EssenceModsRecord {
     pub OutcomeMods: Vec<usize>,
 }
 
+/// Runes, soul cores and talismans
+#[derive(Deserialize, Serialize)]
+pub struct SoulCoresRecord {
+    pub BaseItemType: usize,
+    /// Index into Stats table
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub StatsWeapon: Vec<usize>,
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub StatsValuesWeapon: Vec<i32>,
+    /// Index into Stats table
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub StatsArmour: Vec<usize>,
+    #[serde(
+        deserialize_with = "deserialize_json_encoded",
+        serialize_with = "serialize_json_encoded"
+    )]
+    pub StatsValuesArmour: Vec<i32>,
+}
+
+/// Affix limits for each rarity, which apply to equipment
+#[derive(Deserialize, Serialize)]
+pub struct RarityRecord {
+    /// Eg. Magic
+    pub Id: String,
+    pub MaxPrefix: usize,
+    pub MaxSuffix: usize,
+}
+
 pub struct Dats {
     pub mods: Vec<ModsRecord>,
     pub mod_type: Vec<ModTypeRecord>,
@@ -191,6 +363,8 @@ pub struct Dats {
     pub essences: Vec<EssencesRecord>,
     pub essence_target_item_categories: Vec<EssenceTargetItemCategoriesRecord>,
     pub essence_mods: Vec<EssenceModsRecord>,
+    pub soul_cores: Vec<SoulCoresRecord>,
+    pub rarity: Vec<RarityRecord>,
 }
 
 impl Dats {
@@ -220,9 +394,39 @@ impl Dats {
                 &data_root.join("data/essencemods.csv"),
             )
             .collect(),
+            // Added after the other tables, so older data directories may not have it. Without
+            // it nothing can be socketed, until the tables are extracted again
+            soul_cores: optional_table(&data_root.join("data/soulcores.csv")),
+            // Likewise, equipment falls back to hand-written affix limits without it
+            rarity: optional_table(&data_root.join("data/rarity.csv")),
         }
     }
 
+    /// Load the tables from an extracted game bundle, laid out by the dat-schema at `schema_path`
+    pub fn load_datc64(bundle_root: &Path, schema_path: &Path) -> anyhow::Result<Self> {
+        let schema = Schema::load(schema_path)?;
+
+        Ok(Self {
+            mods: load_datc64_table(bundle_root, &schema, "Mods")?,
+            mod_type: load_datc64_table(bundle_root, &schema, "ModType")?,
+            mod_family: load_datc64_table(bundle_root, &schema, "ModFamily")?,
+            stats: load_datc64_table(bundle_root, &schema, "Stats")?,
+            base_item_types: load_datc64_table(bundle_root, &schema, "BaseItemTypes")?,
+            item_classes: load_datc64_table(bundle_root, &schema, "ItemClasses")?,
+            tags: load_datc64_table(bundle_root, &schema, "Tags")?,
+            essences: load_datc64_table(bundle_root, &schema, "Essences")?,
+            essence_target_item_categories: load_datc64_table(
+                bundle_root,
+                &schema,
+                "EssenceTargetItemCategories",
+            )?,
+            essence_mods: load_datc64_table(bundle_root, &schema, "EssenceMods")?,
+            // Newer tables, which are optional in the CSV exports too
+            soul_cores: optional_datc64_table(bundle_root, &schema, "SoulCores")?,
+            rarity: optional_datc64_table(bundle_root, &schema, "Rarity")?,
+        })
+    }
+
     pub fn save_to_csv(&self, root: &Path) {
         fs::create_dir_all(root.join("data")).unwrap();
 
@@ -241,6 +445,9 @@ impl Dats {
             .save_to_csv(&root.join("data/essencetargetitemcategories.csv"));
         self.essence_mods
             .save_to_csv(&root.join("data/essencemods.csv"));
+        self.soul_cores
+            .save_to_csv(&root.join("data/soulcores.csv"));
+        self.rarity.save_to_csv(&root.join("data/rarity.csv"));
     }
 
     #[cfg(feature = "embed_data")]
@@ -280,6 +487,14 @@ impl Dats {
                 "../../data/tables/data/essencemods.csv"
             ))
             .collect(),
+            soul_cores: SoulCoresRecord::load_from_bytes(include_bytes!(
+                "../../data/tables/data/soulcores.csv"
+            ))
+            .collect(),
+            rarity: RarityRecord::load_from_bytes(include_bytes!(
+                "../../data/tables/data/rarity.csv"
+            ))
+            .collect(),
         }
     }
 }
@@ -342,6 +557,47 @@ pub fn load_essences(dats: &Dats) -> Vec<CurrencyType> {
         .collect()
 }
 
+pub fn load_socketables(dats: &Dats) -> HashMap<SocketableId, Socketable> {
+    let stats = |stat_ids: &[usize], values: &[i32]| {
+        stat_ids
+            .iter()
+            .zip(values)
+            .map(|(&stat_id, &value)| (dats.stats[stat_id].Id.clone(), value))
+            .collect::<Vec<_>>()
+    };
+
+    dats.soul_cores
+        .iter()
+        .map(|row| {
+            // soulcores.BaseItemType -> baseitemtypes.Name
+            let name = dats.base_item_types[row.BaseItemType].Name.clone();
+            let socketable = Socketable {
+                name: name.clone(),
+                weapon_stats: stats(&row.StatsWeapon, &row.StatsValuesWeapon),
+                armour_stats: stats(&row.StatsArmour, &row.StatsValuesArmour),
+            };
+
+            (name, socketable)
+        })
+        .collect()
+}
+
+/// Affix limits on equipment, from the magic and rare rows of the Rarity table.
+/// None if the table is missing, as it is from data directories extracted before it was used
+pub fn load_equipment_rules(dats: &Dats) -> Option<ItemClassRules> {
+    let max_affixes = |id: &str| {
+        dats.rarity
+            .iter()
+            .find(|row| row.Id == id)
+            .map(|row| row.MaxPrefix.min(row.MaxSuffix))
+    };
+
+    Some(ItemClassRules {
+        magic_affixes: max_affixes("Magic")?,
+        rare_affixes: Some(max_affixes("Rare")?),
+    })
+}
+
 pub fn load_mod_tiers(dats: &Dats) -> (HashVec<TierId, Tier>, HashVec<ModGroup, Modifier>) {
     dats.mods.iter().fold(
         (HashVec::default(), HashVec::default()),
@@ -365,34 +621,57 @@ pub fn load_mod_tiers(dats: &Dats) -> (HashVec<TierId, Tier>, HashVec<ModGroup,
 
             let mod_group = &dats.mod_type[row.ModType].Name;
 
-            // TODO: Skip empty families?
-            let mod_family = &dats.mod_family[*row.Families.first().unwrap_or(&0)].Id;
+            let families = row
+                .Families
+                .iter()
+                .map(|&family| dats.mod_family[family].Id.clone())
+                .collect();
 
             let affix = match row.GenerationType {
                 1 => Affix::Prefix,
                 2 => Affix::Suffix,
+                5 => Affix::Corrupted,
                 // TODO: rest of affixes
-                _ => Affix::Corrupted,
+                _ => Affix::Other,
             };
 
+            let spawn_weights = row
+                .SpawnWeight_Tags
+                .iter()
+                .zip(&row.SpawnWeight_Values)
+                .map(|(&tag, &weight)| (dats.tags[tag].Id.clone(), weight))
+                .collect();
+
             let tags = row
                 .ImplicitTags
                 .iter()
                 .flat_map(|index| dats.tags[*index].DisplayString.clone())
                 .collect();
 
+            let mod_type = match affix {
+                Affix::Corrupted => ModType::Corrupted,
+                _ if row.IsEssenceOnlyModifier => ModType::Essence,
+                // Mods which are only revealed by unveiling
+                _ if row.Id.starts_with("Desecrated") => ModType::Desecrated,
+                _ => ModType::Normal,
+            };
+
             let modifier = Modifier {
                 group: mod_group.clone(),
                 tags,
-                // TODO: mod type
-                mod_type: ModType::Normal,
+                mod_type,
                 stats,
-                family: mod_family.clone(),
+                families,
             };
             let mod_id = if !mod_stats.contains_key(mod_group) {
                 mod_stats.insert(mod_group.clone(), modifier)
             } else {
-                mod_stats.opaque(mod_group)
+                let mod_id = mod_stats.opaque(mod_group);
+                // The group is only special if every one of its tiers is
+                if mod_type == ModType::Normal {
+                    mod_stats[mod_id].mod_type = ModType::Normal;
+                }
+                mod_id
             };
 
             tiers.insert(
@@ -404,8 +683,7 @@ pub fn load_mod_tiers(dats: &Dats) -> (HashVec<TierId, Tier>, HashVec<ModGroup,
                     ilvl: row.Level,
                     value_ranges,
                     mod_domain: row.Domain,
-                    // This will be filled in afterwards from poe2db source
-                    weight: 0,
+                    spawn_weights,
                     affix,
                 },
             );
@@ -414,3 +692,102 @@ pub fn load_mod_tiers(dats: &Dats) -> (HashVec<TierId, Tier>, HashVec<ModGroup,
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Map, Value, json};
+
+    use crate::{
+        item_state::ItemClassRules,
+        parsers::dat::{
+            Dats, EssenceModsRecord, RarityRecord, deserialize_rows, load_equipment_rules,
+        },
+    };
+
+    fn dats(rarity: Vec<RarityRecord>) -> Dats {
+        Dats {
+            mods: vec![],
+            mod_type: vec![],
+            mod_family: vec![],
+            stats: vec![],
+            base_item_types: vec![],
+            item_classes: vec![],
+            tags: vec![],
+            essences: vec![],
+            essence_target_item_categories: vec![],
+            essence_mods: vec![],
+            soul_cores: vec![],
+            rarity,
+        }
+    }
+
+    fn rarity(id: &str, max_prefix: usize, max_suffix: usize) -> RarityRecord {
+        RarityRecord {
+            Id: id.to_string(),
+            MaxPrefix: max_prefix,
+            MaxSuffix: max_suffix,
+        }
+    }
+
+    #[test]
+    fn test_equipment_rules() {
+        let rules = load_equipment_rules(&dats(vec![
+            rarity("Normal", 0, 0),
+            rarity("Magic", 1, 1),
+            rarity("Rare", 3, 3),
+            rarity("Unique", 6, 6),
+        ]));
+        assert_eq!(rules, Some(ItemClassRules::EQUIPMENT));
+
+        // Without the table, init() falls back to ItemClassRules::EQUIPMENT
+        assert_eq!(load_equipment_rules(&dats(vec![])), None);
+
+        // Jewels keep their own limits whatever equipment's are
+        let equipment = ItemClassRules {
+            magic_affixes: 2,
+            rare_affixes: Some(4),
+        };
+        assert_eq!(ItemClassRules::for_class("Ring", equipment), equipment);
+        assert_eq!(
+            ItemClassRules::for_class("Jewel", equipment).rare_affixes,
+            Some(2)
+        );
+    }
+
+    fn rows(rows: Value) -> Vec<Map<String, Value>> {
+        serde_json::from_value(rows).unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_rows() {
+        let records = deserialize_rows::<EssenceModsRecord>(
+            "EssenceMods",
+            rows(json!([
+                {"Essence": 1, "TargetItemCategory": 2, "Mod1": 3, "DisplayMod": null, "OutcomeMods": []},
+                {"Essence": 4, "TargetItemCategory": 5, "Mod1": null, "DisplayMod": 6, "OutcomeMods": [7, 8], "Unused": true},
+            ])),
+        )
+        .unwrap();
+        assert_eq!(records[0].Mod1, Some(3));
+        assert_eq!(records[0].DisplayMod, None);
+        assert_eq!(records[1].Mod1, None);
+        assert_eq!(records[1].OutcomeMods, [7, 8]);
+
+        // Errors name the table, row and column
+        let error = deserialize_rows::<EssenceModsRecord>(
+            "EssenceMods",
+            rows(json!([
+                {"Essence": 1, "TargetItemCategory": 2, "Mod1": 3, "DisplayMod": null, "OutcomeMods": []},
+                {"Essence": 1, "TargetItemCategory": "Ring", "Mod1": 3, "DisplayMod": null, "OutcomeMods": []},
+            ])),
+        )
+        .err().unwrap();
+        let message = format!("{error:#}");
+        assert!(message.starts_with("Failed to deserialise EssenceMods row 1"));
+        assert!(message.contains("column TargetItemCategory"), "{message}");
+        let error = deserialize_rows::<RarityRecord>("Rarity", rows(json!([{"Id": "Magic"}])))
+            .err()
+            .unwrap();
+        assert!(format!("{error:#}").contains("MaxPrefix"), "{error:#}");
+    }
+}
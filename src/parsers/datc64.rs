@@ -0,0 +1,319 @@
+/**
+.datc64 - the game's binary data tables, as found in the extracted bundle under data/
+Layout:
+    u32 row count
+    fixed-size rows, one after another
+    8 byte 0xBB marker, the start of the variable data section
+    variable data: strings (UTF-16, null terminated) and lists, referenced by offsets from the marker
+The columns of each table are described by the community dat-schema:
+https://github.com/poe-tool-dev/dat-schema/releases (schema.min.json)
+*/
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+const DATA_MARKER: [u8; 8] = [0xBB; 8];
+/// Row references with this value point at nothing
+const NULL_ROW: u64 = 0xFEFE_FEFE_FEFE_FEFE;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Bool,
+    String,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    I64,
+    U64,
+    /// Index into the same table
+    Row,
+    /// Index into another table
+    ForeignRow,
+    /// Index into an enumeration
+    EnumRow,
+    /// List of unknown contents
+    Array,
+}
+
+impl ColumnType {
+    /// Size of a single value in bytes
+    fn size(&self) -> usize {
+        match self {
+            ColumnType::Bool => 1,
+            ColumnType::I16 | ColumnType::U16 => 2,
+            ColumnType::I32 | ColumnType::U32 | ColumnType::F32 | ColumnType::EnumRow => 4,
+            ColumnType::I64 | ColumnType::U64 | ColumnType::String | ColumnType::Row => 8,
+            ColumnType::ForeignRow | ColumnType::Array => 16,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Column {
+    /// Unknown columns have no name
+    pub name: Option<String>,
+    /// Stored as a list in the variable data section
+    pub array: bool,
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+    /// Stored as a pair of values, Eg. a min-max range
+    #[serde(default)]
+    pub interval: bool,
+}
+
+impl Column {
+    /// Size of the column within a row in bytes
+    fn size(&self) -> usize {
+        if self.array {
+            16
+        } else if self.interval {
+            self.kind.size() * 2
+        } else {
+            self.kind.size()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TableSchema {
+    /// Eg. BaseItemTypes
+    pub name: String,
+    /// Bitflags: 1 = PoE 1, 2 = PoE 2
+    #[serde(rename = "validFor")]
+    pub valid_for: u32,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Deserialize)]
+struct SchemaFile {
+    tables: Vec<TableSchema>,
+}
+
+/// Column layouts for the PoE 2 tables
+pub struct Schema {
+    tables: HashMap<String, TableSchema>,
+}
+
+impl Schema {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("Failed to read: {path:?}"))?;
+        let schema = serde_json::from_str::<SchemaFile>(&contents)
+            .with_context(|| format!("Failed to parse schema: {path:?}"))?;
+
+        Ok(Self {
+            tables: schema
+                .tables
+                .into_iter()
+                .filter(|table| table.valid_for & 2 != 0)
+                .map(|table| (table.name.clone(), table))
+                .collect(),
+        })
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.get(name)
+    }
+}
+
+/// Load a .datc64 file into one json object per row, keyed by column name
+pub fn load_table(path: &Path, table: &TableSchema) -> anyhow::Result<Vec<Map<String, Value>>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read: {path:?}"))?;
+    parse_table(&bytes, table)
+}
+
+pub fn parse_table(bytes: &[u8], table: &TableSchema) -> anyhow::Result<Vec<Map<String, Value>>> {
+    let Some(data_start) = bytes
+        .windows(DATA_MARKER.len())
+        .skip(4)
+        .position(|window| window == DATA_MARKER)
+        .map(|pos| pos + 4)
+    else {
+        bail!("{}: Variable data marker not found", table.name);
+    };
+    let num_rows = read_u32(bytes, 0) as usize;
+    let variable_data = &bytes[data_start..];
+
+    // The schema can be missing trailing columns, so trust the file for the row size
+    let row_size = (data_start - 4).checked_div(num_rows).unwrap_or(0);
+    let schema_size = table.columns.iter().map(Column::size).sum::<usize>();
+    if num_rows != 0 && schema_size > row_size {
+        bail!(
+            "{}: Schema expects {schema_size} bytes per row, file has {row_size}",
+            table.name
+        );
+    }
+
+    let rows = (0..num_rows)
+        .map(|i| {
+            let row = &bytes[4 + i * row_size..4 + (i + 1) * row_size];
+
+            let mut offset = 0;
+            let mut record = Map::new();
+            for column in &table.columns {
+                if let Some(name) = &column.name {
+                    record.insert(
+                        name.clone(),
+                        read_column(row, offset, column, variable_data),
+                    );
+                }
+                offset += column.size();
+            }
+
+            record
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+fn read_column(row: &[u8], offset: usize, column: &Column, variable_data: &[u8]) -> Value {
+    if column.array {
+        let count = read_u64(row, offset) as usize;
+        let start = read_u64(row, offset + 8) as usize;
+        if let ColumnType::Array = column.kind {
+            // Element type is unknown
+            return Value::Array(vec![]);
+        }
+
+        let size = column.kind.size();
+        Value::Array(
+            (0..count)
+                .map(|i| read_value(variable_data, start + i * size, column.kind, variable_data))
+                .collect(),
+        )
+    } else if column.interval {
+        Value::Array(vec![
+            read_value(row, offset, column.kind, variable_data),
+            read_value(row, offset + column.kind.size(), column.kind, variable_data),
+        ])
+    } else {
+        read_value(row, offset, column.kind, variable_data)
+    }
+}
+
+fn read_value(bytes: &[u8], offset: usize, kind: ColumnType, variable_data: &[u8]) -> Value {
+    match kind {
+        ColumnType::Bool => Value::Bool(bytes[offset] != 0),
+        ColumnType::I16 => i16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap()).into(),
+        ColumnType::U16 => u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap()).into(),
+        ColumnType::I32 => (read_u32(bytes, offset) as i32).into(),
+        ColumnType::U32 | ColumnType::EnumRow => read_u32(bytes, offset).into(),
+        ColumnType::F32 => (f32::from_bits(read_u32(bytes, offset)) as f64).into(),
+        ColumnType::I64 => (read_u64(bytes, offset) as i64).into(),
+        ColumnType::U64 => read_u64(bytes, offset).into(),
+        ColumnType::String => {
+            Value::String(read_string(variable_data, read_u64(bytes, offset) as usize))
+        }
+        ColumnType::Row | ColumnType::ForeignRow => match read_u64(bytes, offset) {
+            NULL_ROW => Value::Null,
+            index => index.into(),
+        },
+        ColumnType::Array => Value::Null,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Read a null-terminated UTF-16 string
+fn read_string(bytes: &[u8], offset: usize) -> String {
+    let chars = bytes[offset..]
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect::<Vec<_>>();
+
+    String::from_utf16_lossy(&chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::parsers::datc64::{Column, ColumnType, NULL_ROW, TableSchema, parse_table};
+
+    #[test]
+    fn test_parse_table() {
+        let table = TableSchema {
+            name: "Test".to_string(),
+            valid_for: 2,
+            columns: vec![
+                Column {
+                    name: Some("Id".to_string()),
+                    array: false,
+                    kind: ColumnType::String,
+                    interval: false,
+                },
+                Column {
+                    name: None,
+                    array: false,
+                    kind: ColumnType::Bool,
+                    interval: false,
+                },
+                Column {
+                    name: Some("Value".to_string()),
+                    array: false,
+                    kind: ColumnType::I32,
+                    interval: true,
+                },
+                Column {
+                    name: Some("Parent".to_string()),
+                    array: false,
+                    kind: ColumnType::ForeignRow,
+                    interval: false,
+                },
+                Column {
+                    name: Some("Tags".to_string()),
+                    array: true,
+                    kind: ColumnType::ForeignRow,
+                    interval: false,
+                },
+            ],
+        };
+
+        // Variable data: marker, "Ab\0", then a list of two foreign rows
+        let mut variable_data = vec![0xBB; 8];
+        let string_offset = variable_data.len() as u64;
+        variable_data.extend([b'A', 0, b'b', 0, 0, 0, 0, 0]);
+        let list_offset = variable_data.len() as u64;
+        for index in [3u64, 5] {
+            variable_data.extend(index.to_le_bytes());
+            variable_data.extend(0u64.to_le_bytes());
+        }
+
+        let mut bytes = 1u32.to_le_bytes().to_vec();
+        bytes.extend(string_offset.to_le_bytes());
+        bytes.push(1);
+        bytes.extend((-4i32).to_le_bytes());
+        bytes.extend(7i32.to_le_bytes());
+        bytes.extend(NULL_ROW.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(2u64.to_le_bytes());
+        bytes.extend(list_offset.to_le_bytes());
+        // Trailing column missing from the schema
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(variable_data);
+
+        let rows = parse_table(&bytes, &table).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            serde_json::Value::Object(rows[0].clone()),
+            json!({"Id": "Ab", "Value": [-4, 7], "Parent": null, "Tags": [3, 5]})
+        );
+
+        // Without the marker there's no variable data
+        let error = parse_table(&bytes[..bytes.len() - 48], &table).unwrap_err();
+        assert_eq!(error.to_string(), "Test: Variable data marker not found");
+    }
+}
@@ -0,0 +1,808 @@
+/**
+In-game item text, as copied with Ctrl+C. Ctrl+Alt+C also adds mod headers and value ranges.
+    Item Class: Rings
+    Rarity: Rare
+    Dusk Loop
+    Sapphire Ring
+    --------
+    Item Level: 79
+    --------
+    +21% to Cold Resistance (implicit)
+    --------
+    { Prefix Modifier "Hale" (Tier: 9) — Life }
+    +52(50-59) to maximum Life
+    { Suffix Modifier "of the Salamander" (Tier: 1) — Elemental, Fire, Resistance }
+    +38(36-40)% to Fire Resistance (fractured)
+    --------
+    Corrupted
+Affix lines are matched back to tiers through the stat formatters of the selected language.
+The game sums the values of mods which share a stat onto one line, those can only be told apart
+in the advanced format.
+*/
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    sync::LazyLock,
+};
+
+use itertools::Itertools;
+use regex::Regex;
+
+use crate::{
+    ITEM_CLASSES, ITEM_TIERS, MODS, TIERS, get_formatters,
+    hashvec::OpaqueIndex,
+    item_state::{
+        ItemState, Rarity, get_all_mods_for_item, get_corrupted_implicits_for_item,
+        get_desecrated_mods_for_item,
+    },
+    types::{Affix, BaseItemId, ModType, Modifier, QualityType, Tier},
+};
+
+/// An item read from its text
+#[derive(Debug)]
+pub struct ParsedItem {
+    pub item: ItemState,
+    /// Affix lines which couldn't be matched to a tier
+    pub unmatched: Vec<String>,
+}
+
+/// Why item text couldn't be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemTextError {
+    /// Item class, rarity and name lines are missing
+    MissingHeader,
+    MissingItemLevel,
+    UnsupportedRarity {
+        rarity: String,
+    },
+    UnknownBase {
+        name: String,
+    },
+}
+
+impl Display for ItemTextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "Not an item, copy one in game with Ctrl+C"),
+            Self::MissingItemLevel => write!(f, "Item level is missing"),
+            Self::UnsupportedRarity { rarity } => write!(f, "{rarity} items aren't supported"),
+            Self::UnknownBase { name } => write!(f, "Unknown base item: {name}"),
+        }
+    }
+}
+
+/// Where an affix line came from, marked by its suffix. Eg. (fractured)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Explicit,
+    Fractured,
+    Desecrated,
+    /// Base implicits, which aren't modelled
+    Implicit,
+    /// Corrupted implicits
+    Enchant,
+    /// Socketed stats
+    Rune,
+}
+
+const LINE_KINDS: [(&str, LineKind); 5] = [
+    (" (fractured)", LineKind::Fractured),
+    (" (desecrated)", LineKind::Desecrated),
+    (" (implicit)", LineKind::Implicit),
+    (" (enchant)", LineKind::Enchant),
+    (" (rune)", LineKind::Rune),
+];
+
+/// Mod header from advanced copies. Eg. { Prefix Modifier "Hale" (Tier: 9) — Life }
+#[derive(Debug)]
+struct Header {
+    affix: Option<Affix>,
+    /// Eg. Hale, of the Whale
+    name: Option<String>,
+}
+
+#[derive(Debug)]
+struct AffixLine {
+    /// As it was copied
+    raw: String,
+    /// Without the kind suffix and value ranges, ready for matching
+    text: String,
+    kind: LineKind,
+    /// Index into the headers, for advanced copies
+    header: Option<usize>,
+}
+
+/// A tier picked for some of the lines
+#[derive(Debug)]
+struct Assignment {
+    tier_id: OpaqueIndex<Tier>,
+    lines: Vec<usize>,
+    /// One for each stat of the mod, None if it isn't displayed
+    values: Vec<Option<i32>>,
+}
+
+/// Eg. "+52(50-59) to maximum Life" -> "+52 to maximum Life"
+static REGEX_RANGE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d)\(-?[\d.]+--?[\d.]+\)").unwrap());
+
+pub fn parse(text: &str) -> Result<ParsedItem, ItemTextError> {
+    let lines = text.lines().map(str::trim).collect::<Vec<_>>();
+    let sections = lines
+        .split(|line| line.starts_with("--------"))
+        .map(|section| {
+            section
+                .iter()
+                .copied()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>();
+
+    // Eg. Item Class: Rings, Rarity: Rare, then the name and base lines
+    let Some(([class_line, rarity_line, names @ ..], sections)) = sections
+        .split_first()
+        .map(|(header, sections)| (header.as_slice(), sections))
+    else {
+        return Err(ItemTextError::MissingHeader);
+    };
+    if names.is_empty() {
+        return Err(ItemTextError::MissingHeader);
+    }
+    let item_class = property_value(class_line);
+    // Rarity is inferred from the names and mods on other clients
+    let rarity = match property_value(rarity_line) {
+        "Normal" => Some(Rarity::Normal),
+        "Magic" => Some(Rarity::Magic),
+        "Rare" => Some(Rarity::Rare),
+        "Unique" => {
+            return Err(ItemTextError::UnsupportedRarity {
+                rarity: "Unique".to_string(),
+            });
+        }
+        _ => None,
+    };
+
+    let mut item_level = None;
+    let mut quality = 0;
+    let mut quality_type = None;
+    let mut num_sockets = 0;
+    let mut corrupted = false;
+    let mut headers = vec![];
+    let mut affix_lines = vec![];
+    for section in sections {
+        match section.as_slice() {
+            ["Corrupted"] => corrupted = true,
+            ["Unidentified"] => {}
+            // Eg. Item Level: 79, but not mod headers like (Tier: 9)
+            section
+                if section
+                    .iter()
+                    .any(|line| !line.starts_with('{') && line.contains(": ")) =>
+            {
+                for line in section {
+                    let Some((label, value)) = line.split_once(": ") else {
+                        continue;
+                    };
+                    // Item level is the only number in a section of its own
+                    if let (Ok(level), 1) = (value.parse::<u32>(), section.len()) {
+                        item_level = Some(level);
+                    } else if label.starts_with("Quality") {
+                        // Eg. Quality (Life Modifiers): +20% (augmented)
+                        quality = value
+                            .trim_start_matches('+')
+                            .split('%')
+                            .next()
+                            .and_then(|q| q.parse::<u32>().ok())
+                            .unwrap_or(0);
+                        quality_type = QualityType::ALL.into_iter().find(|quality_type| {
+                            label.contains(&format!("({} Modifiers)", quality_type.tag()))
+                        });
+                    } else if label == "Sockets" {
+                        num_sockets = value.split_whitespace().count();
+                    }
+                }
+            }
+            section => {
+                let mut header = None;
+                for line in section {
+                    if let Some(parsed) = parse_header(line) {
+                        headers.push(parsed);
+                        header = Some(headers.len() - 1);
+                        continue;
+                    }
+
+                    let (text, kind) = LINE_KINDS
+                        .iter()
+                        .find_map(|(suffix, kind)| Some((line.strip_suffix(suffix)?, *kind)))
+                        .unwrap_or((line, LineKind::Explicit));
+                    // Eg. " — Unscalable Value"
+                    let text = text.split(" — ").next().unwrap();
+                    affix_lines.push(AffixLine {
+                        raw: line.to_string(),
+                        text: REGEX_RANGE.replace_all(text, "$1").into_owned(),
+                        kind,
+                        header,
+                    });
+                }
+            }
+        }
+    }
+
+    let item_level = item_level.ok_or(ItemTextError::MissingItemLevel)?;
+    let unknown_base = || ItemTextError::UnknownBase {
+        name: names.last().unwrap().to_string(),
+    };
+
+    // Magic names include the affixes, Eg. Sapphire Ring of the Whale
+    let mut bases = ITEM_TIERS.keys().collect::<Vec<_>>();
+    bases.sort_unstable();
+    let named = bases
+        .iter()
+        .copied()
+        .filter(|base| names.iter().any(|name| name.contains(base.as_str())))
+        .max_set_by_key(|base| base.len());
+    // poe2db bases without one in the tables are named by their attributes, Eg. Gloves_StrDex
+    let class = normalise_class(item_class);
+    let classed = bases
+        .iter()
+        .copied()
+        .filter(|base| {
+            ITEM_CLASSES
+                .get(*base)
+                .is_some_and(|base_class| class.starts_with(&normalise_class(base_class)))
+        })
+        .collect::<Vec<_>>();
+    let known = !named.is_empty() || !classed.is_empty();
+    let candidates = if !named.is_empty() {
+        named
+    } else if !classed.is_empty() {
+        classed
+    } else {
+        bases
+    };
+
+    // Pick the base which explains the most lines
+    let mut mod_matches = HashMap::new();
+    let (base, assignments) = candidates
+        .into_iter()
+        .map(|base| {
+            let assignments =
+                assign_tiers(base, item_level, &affix_lines, &headers, &mut mod_matches);
+            (base, assignments)
+        })
+        .min_by_key(|(_, assignments)| {
+            Reverse(
+                assignments
+                    .iter()
+                    .map(|assignment| assignment.lines.len())
+                    .sum::<usize>(),
+            )
+        })
+        .ok_or_else(unknown_base)?;
+    if !known && assignments.is_empty() {
+        return Err(unknown_base());
+    }
+
+    let mut item = ItemState {
+        base_type: base.clone(),
+        item_level,
+        rarity: Rarity::Normal,
+        mods: vec![],
+        corrupted,
+        implicits: vec![],
+        fractured: vec![],
+        desecrated: vec![],
+        veiled_min_level: None,
+        foreseen: None,
+        quality,
+        quality_type,
+        sockets: vec![None; num_sockets],
+        rolls: BTreeMap::new(),
+    };
+    let mut used = HashSet::new();
+    for assignment in assignments {
+        let tier = &TIERS[assignment.tier_id];
+        if tier.affix == Affix::Corrupted {
+            item.implicits.push(assignment.tier_id);
+        } else {
+            item.mods.push(assignment.tier_id);
+        }
+
+        for &line in &assignment.lines {
+            match affix_lines[line].kind {
+                LineKind::Fractured => item.fractured.push(assignment.tier_id),
+                LineKind::Desecrated => item.desecrated.push(assignment.tier_id),
+                _ => {}
+            }
+        }
+        item.fractured.dedup();
+        item.desecrated.dedup();
+
+        // Values which aren't displayed get the lowest roll
+        let rolls = tier
+            .value_ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &[min, _])| assignment.values.get(i).copied().flatten().unwrap_or(min))
+            .collect();
+        item.rolls.insert(assignment.tier_id, rolls);
+        used.extend(assignment.lines);
+    }
+    item.rarity = rarity.unwrap_or(if names.len() > 1 {
+        Rarity::Rare
+    } else if !item.mods.is_empty() {
+        Rarity::Magic
+    } else {
+        Rarity::Normal
+    });
+
+    let unmatched = affix_lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| {
+            !used.contains(i) && !matches!(line.kind, LineKind::Implicit | LineKind::Rune)
+        })
+        .map(|(_, line)| line.raw.clone())
+        .collect();
+
+    Ok(ParsedItem { item, unmatched })
+}
+
+/// Eg. "Item Class: Rings" -> "Rings"
+fn property_value(line: &str) -> &str {
+    line.split_once(": ").map_or(line, |(_, value)| value)
+}
+
+/// Eg. "Body Armours" -> "bodyarmours", so it starts with the singular class name
+fn normalise_class(class: &str) -> String {
+    class.to_lowercase().replace(' ', "")
+}
+
+/// Eg. { Desecrated Suffix Modifier "of the Whale" (Tier: 3) — Life }
+fn parse_header(line: &str) -> Option<Header> {
+    let inner = line.strip_prefix('{')?.strip_suffix('}')?;
+    let affix = if inner.contains("Prefix") {
+        Some(Affix::Prefix)
+    } else if inner.contains("Suffix") {
+        Some(Affix::Suffix)
+    } else {
+        None
+    };
+    let name = inner
+        .split('"')
+        .nth(1)
+        .filter(|name| !name.is_empty())
+        .map(String::from);
+
+    Some(Header { affix, name })
+}
+
+/// Match the lines to tiers that can roll on the base, mods covering the most stats first
+fn assign_tiers(
+    base: &BaseItemId,
+    item_level: u32,
+    lines: &[AffixLine],
+    headers: &[Header],
+    mod_matches: &mut HashMap<OpaqueIndex<Modifier>, ModMatches>,
+) -> Vec<Assignment> {
+    let item = ItemState {
+        base_type: base.clone(),
+        item_level,
+        rarity: Rarity::Rare,
+        mods: vec![],
+        corrupted: false,
+        implicits: vec![],
+        fractured: vec![],
+        desecrated: vec![],
+        veiled_min_level: None,
+        foreseen: None,
+        quality: 0,
+        quality_type: None,
+        sockets: vec![],
+        rolls: BTreeMap::new(),
+    };
+    let pool = get_all_mods_for_item(&item)
+        .into_iter()
+        .chain(get_desecrated_mods_for_item(&item))
+        .chain(get_corrupted_implicits_for_item(&item))
+        .collect::<HashSet<_>>();
+
+    // Mod -> [Tier], best tiers first
+    let mut mod_tiers = HashMap::<_, Vec<_>>::new();
+    for tier_id in pool {
+        mod_tiers
+            .entry(TIERS[tier_id].mod_id)
+            .or_default()
+            .push(tier_id);
+    }
+    for tiers in mod_tiers.values_mut() {
+        tiers.sort_unstable_by_key(|&tier_id| Reverse(TIERS[tier_id].ilvl));
+    }
+
+    let mut mod_ids = mod_tiers.keys().copied().collect::<Vec<_>>();
+    for &mod_id in &mod_ids {
+        mod_matches
+            .entry(mod_id)
+            .or_insert_with(|| ModMatches::new(&MODS[mod_id], lines));
+    }
+    // Hybrids first, so they aren't split up into single stat mods
+    mod_ids.sort_unstable_by_key(|&mod_id| {
+        let modifier = &MODS[mod_id];
+        (
+            Reverse(mod_matches[&mod_id].num_displayed()),
+            modifier.mod_type != ModType::Normal,
+            &modifier.group,
+        )
+    });
+
+    let mut used = vec![false; lines.len()];
+    let mut assignments = vec![];
+    for mod_id in mod_ids {
+        let matches = &mod_matches[&mod_id];
+        if matches.lines.is_empty() {
+            continue;
+        }
+
+        let assignment = mod_tiers[&mod_id].iter().find_map(|&tier_id| {
+            matches.assign(tier_id, &used, |line| {
+                let line = &lines[line];
+                let tier = &TIERS[tier_id];
+                let kind_ok = match line.kind {
+                    LineKind::Explicit | LineKind::Fractured | LineKind::Desecrated => {
+                        matches!(tier.affix, Affix::Prefix | Affix::Suffix)
+                    }
+                    LineKind::Enchant => tier.affix == Affix::Corrupted,
+                    LineKind::Implicit | LineKind::Rune => false,
+                };
+                let header_ok = line.header.is_none_or(|header| {
+                    let header = &headers[header];
+                    header.affix.is_none_or(|affix| affix == tier.affix)
+                        && header.name.as_ref().is_none_or(|name| *name == tier.name)
+                });
+                kind_ok && header_ok
+            })
+        });
+        if let Some(assignment) = assignment {
+            for &line in &assignment.lines {
+                used[line] = true;
+            }
+            assignments.push(assignment);
+        }
+    }
+
+    // Keep the order of the item text
+    assignments.sort_unstable_by_key(|assignment| assignment.lines.iter().min().copied());
+    assignments
+}
+
+/// The lines a mod's formatters can read values from
+#[derive(Debug)]
+struct ModMatches {
+    /// Whether each stat is displayed, so must be found on a line
+    displayed: Vec<bool>,
+    /// (line, value of each stat read from it)
+    lines: Vec<(usize, Vec<Option<i32>>)>,
+}
+
+impl ModMatches {
+    /// Use the formatter for all of the stats together if there is one, otherwise one per stat
+    fn new(modifier: &Modifier, lines: &[AffixLine]) -> Self {
+        let num_stats = modifier.stats.len();
+
+        let mut displayed = vec![false; num_stats];
+        let mut matches = vec![];
+        if let Some(formatters) = get_formatters(&modifier.stats.join("|")) {
+            for formatter in formatters {
+                for (i, format) in formatter.formats.iter().enumerate().take(num_stats) {
+                    displayed[i] |= format != "ignore";
+                }
+                for (l, line) in lines.iter().enumerate() {
+                    if let Some(mut values) = formatter.parse_values(&line.text) {
+                        values.resize(num_stats, None);
+                        matches.push((l, values));
+                    }
+                }
+            }
+        } else {
+            for (i, stat_id) in modifier.stats.iter().enumerate() {
+                // Formatters shared with other stats don't say which value is this one
+                let formatters = get_formatters(stat_id)
+                    .into_iter()
+                    .flatten()
+                    .filter(|formatter| formatter.formats.len() == 1);
+                for formatter in formatters {
+                    displayed[i] |= formatter.formats[0] != "ignore";
+                    for (l, line) in lines.iter().enumerate() {
+                        if let Some(Some(value)) = formatter.parse_values(&line.text).map(|v| v[0])
+                        {
+                            let mut values = vec![None; num_stats];
+                            values[i] = Some(value);
+                            matches.push((l, values));
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            displayed,
+            lines: matches,
+        }
+    }
+
+    fn num_displayed(&self) -> usize {
+        self.displayed
+            .iter()
+            .filter(|&&displayed| displayed)
+            .count()
+    }
+
+    /// Find unused lines showing every displayed stat, with values in the tier's ranges
+    fn assign(
+        &self,
+        tier_id: OpaqueIndex<Tier>,
+        used: &[bool],
+        line_ok: impl Fn(usize) -> bool,
+    ) -> Option<Assignment> {
+        let tier = &TIERS[tier_id];
+        let in_range = |i: usize, value: i32| {
+            tier.value_ranges
+                .get(i)
+                .is_none_or(|&[a, b]| a.min(b) <= value && value <= a.max(b))
+        };
+
+        let mut lines = vec![];
+        let mut values = vec![None; self.displayed.len()];
+        for stat in 0..self.displayed.len() {
+            if !self.displayed[stat] || values[stat].is_some() {
+                continue;
+            }
+
+            let (line, line_values) = self.lines.iter().find(|(line, line_values)| {
+                !used[*line]
+                    && !lines.contains(line)
+                    && line_values[stat].is_some()
+                    && line_values
+                        .iter()
+                        .enumerate()
+                        .all(|(i, value)| value.is_none_or(|value| in_range(i, value)))
+                    && line_ok(*line)
+            })?;
+            lines.push(*line);
+            for (value, line_value) in values.iter_mut().zip(line_values) {
+                if value.is_none() {
+                    *value = *line_value;
+                }
+            }
+        }
+
+        (!lines.is_empty()).then_some(Assignment {
+            tier_id,
+            lines,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        TIERS,
+        hashvec::OpaqueIndex,
+        internal::test_data,
+        item_state::Rarity,
+        parsers::item_text::{ItemTextError, parse},
+        types::{QualityType, Tier},
+    };
+
+    fn tier(id: &str) -> OpaqueIndex<Tier> {
+        TIERS.opaque(id)
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        test_data::init();
+        let text = "
+            Item Class: Rings
+            Rarity: Rare
+            Dusk Loop
+            Sapphire Ring
+            --------
+            Item Level: 79
+            --------
+            +21% to Cold Resistance (implicit)
+            --------
+            { Prefix Modifier \"Hale\" (Tier: 9) — Life }
+            +52(50-59) to maximum Life
+            { Suffix Modifier \"of the Salamander\" (Tier: 1) — Elemental, Fire, Resistance }
+            +38(36-40)% to Fire Resistance (fractured)
+            --------
+            Corrupted
+        ";
+        let parsed = parse(text).unwrap();
+        let item = &parsed.item;
+
+        assert_eq!(item.base_type, "Sapphire Ring");
+        assert_eq!(item.item_level, 79);
+        assert_eq!(item.rarity, Rarity::Rare);
+        assert!(item.corrupted);
+        assert_eq!(item.mods, [tier("IncreasedLife2"), tier("FireResist2")]);
+        assert_eq!(item.fractured, [tier("FireResist2")]);
+        assert_eq!(item.rolls[&tier("IncreasedLife2")], [52]);
+        // Base implicits aren't modelled, so aren't reported
+        assert!(parsed.unmatched.is_empty());
+    }
+
+    #[test]
+    fn test_parse_magic_name() {
+        test_data::init();
+        let text = "
+            Item Class: Rings
+            Rarity: Magic
+            Plump Ruby Ring of the Whelpling
+            --------
+            Item Level: 12
+            --------
+            +15 to maximum Life
+            +8% to Fire Resistance
+        ";
+        let item = parse(text).unwrap().item;
+
+        assert_eq!(item.base_type, "Ruby Ring");
+        assert_eq!(item.rarity, Rarity::Magic);
+        assert_eq!(item.mods, [tier("IncreasedLife1"), tier("FireResist1")]);
+    }
+
+    #[test]
+    fn test_parse_hybrid() {
+        test_data::init();
+        let text = "
+            Item Class: Rings
+            Rarity: Rare
+            Doom Band
+            Sapphire Ring
+            --------
+            Item Level: 30
+            --------
+            +10 to maximum Life
+            +11 to maximum Mana
+        ";
+        let parsed = parse(text).unwrap();
+
+        assert_eq!(parsed.item.mods, [tier("LifeAndMana1")]);
+        assert_eq!(parsed.item.rolls[&tier("LifeAndMana1")], [10, 11]);
+        assert!(parsed.unmatched.is_empty());
+    }
+
+    #[test]
+    fn test_parse_overlapping_tiers() {
+        test_data::init();
+        let text = |item_level: u32| {
+            format!(
+                "
+                Item Class: Rings
+                Rarity: Magic
+                Sapphire Ring
+                --------
+                Item Level: {item_level}
+                --------
+                +57 to maximum Life
+                "
+            )
+        };
+
+        // The best tier the item level allows is assumed
+        assert_eq!(
+            parse(&text(79)).unwrap().item.mods,
+            [tier("IncreasedLife3")]
+        );
+        assert_eq!(
+            parse(&text(50)).unwrap().item.mods,
+            [tier("IncreasedLife2")]
+        );
+    }
+
+    #[test]
+    fn test_parse_kinds() {
+        test_data::init();
+        let text = "
+            Item Class: Rings
+            Rarity: Rare
+            Doom Band
+            Ruby Ring
+            --------
+            Quality (Life Modifiers): +12% (augmented)
+            --------
+            Item Level: 70
+            --------
+            15% increased Critical Hit Chance (enchant)
+            --------
+            +57 to maximum Life
+            +38% to Fire Resistance (fractured)
+            14% increased Rarity of Items found (desecrated)
+            5% increased Attack Speed
+            --------
+            Corrupted
+        ";
+        let parsed = parse(text).unwrap();
+        let item = &parsed.item;
+
+        assert_eq!(item.quality, 12);
+        assert_eq!(item.quality_type, Some(QualityType::Life));
+        assert_eq!(item.implicits, [tier("CorruptedCritChance1")]);
+        assert_eq!(
+            item.mods,
+            [
+                tier("IncreasedLife3"),
+                tier("FireResist2"),
+                tier("DesecratedRarity1")
+            ]
+        );
+        assert_eq!(item.fractured, [tier("FireResist2")]);
+        assert_eq!(item.desecrated, [tier("DesecratedRarity1")]);
+        assert_eq!(parsed.unmatched, ["5% increased Attack Speed"]);
+    }
+
+    #[test]
+    fn test_parse_advanced() {
+        test_data::init();
+        // Ctrl+Alt+C, the header picks between tiers with overlapping ranges
+        let text = "
+            Item Class: Rings
+            Rarity: Rare
+            Storm Loop
+            Sapphire Ring
+            --------
+            Item Level: 79
+            --------
+            { Prefix Modifier \"Hale\" (Tier: 2) — Life }
+            +57(50-59) to maximum Life
+            { Suffix Modifier \"of the Salamander\" (Tier: 1) — Elemental, Fire, Resistance }
+            +38(36-40)% to Fire Resistance
+        ";
+        let parsed = parse(text).unwrap();
+
+        assert_eq!(
+            parsed.item.mods,
+            [tier("IncreasedLife2"), tier("FireResist2")]
+        );
+        assert_eq!(parsed.item.rolls[&tier("FireResist2")], [38]);
+        assert!(parsed.unmatched.is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        test_data::init();
+        let unique = "
+            Item Class: Rings
+            Rarity: Unique
+            Blackheart
+            Iron Ring
+            --------
+            Item Level: 40
+        ";
+        assert_eq!(
+            parse(unique).unwrap_err(),
+            ItemTextError::UnsupportedRarity {
+                rarity: "Unique".to_string()
+            }
+        );
+
+        assert_eq!(
+            parse("+52 to maximum Life").unwrap_err(),
+            ItemTextError::MissingHeader
+        );
+
+        let no_level = "
+            Item Class: Rings
+            Rarity: Normal
+            Sapphire Ring
+        ";
+        assert_eq!(
+            parse(no_level).unwrap_err(),
+            ItemTextError::MissingItemLevel
+        );
+    }
+}
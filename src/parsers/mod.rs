@@ -1,4 +1,7 @@
 pub mod coe;
+pub mod csd;
 pub mod dat;
+pub mod datc64;
+pub mod item_text;
 pub mod poe2db;
 pub mod stat_desc;
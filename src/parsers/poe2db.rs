@@ -12,6 +12,9 @@ pub type Root = HashMap<String, ItemRoot>;
 pub struct ItemRoot {
     pub opt: Opt,
     pub normal: Vec<Modifier>,
+    /// Mods which can be revealed by unveiling a desecrated mod
+    #[serde(default)]
+    pub desecrated: Vec<Modifier>,
 }
 
 #[serde_as]
@@ -1,20 +1,20 @@
-#![allow(non_snake_case)]
-use std::{fs::File, io::BufReader, path::Path};
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
 
 /**
 stat_descriptions.csd - format strings for stats
 https://repoe-fork.github.io/poe2/stat_translations/stat_descriptions.json
 ["ids"]: List of stat IDs this string applies to Eg. %_attack_damage_per_glory_consumed_for_6_seconds_up_to_100
-["English"][n]["string"]: Format string with {i} and [either|or] placeholders
-["English"][n]["condition"]: Conditions when this format string applies Eg. 0-99%, 100%
-["English"][n]["format"]: How to format the value
-["English"][n]["index_handlers"]: How the value should be pre-processed. Eg. "divide_by_100"
+[language][n]["string"]: Format string with {i} placeholders and [Keyword|Display text] links
+[language][n]["condition"]: Conditions when this format string applies Eg. 0-99%, 100%
+[language][n]["format"]: How to format the value
+[language][n]["index_handlers"]: How the value should be pre-processed. Eg. "divide_by_100"
+        One array per language present. Eg. "English", "German"
 ["trade_stats"][0]["text"]: Format strings as they appear on trade - this looks like the best
         bet for matching to CoE weights. Not present for all stats
 */
 use serde::{Deserialize, Serialize};
 
-use crate::types::StatFormatter;
+use crate::types::{Language, StatFormatter};
 
 pub type Root = Vec<Modifier>;
 
@@ -22,7 +22,8 @@ pub type Root = Vec<Modifier>;
 pub struct Modifier {
     /// Eg. additional_strength
     pub ids: Vec<String>,
-    pub English: Vec<StatFormatter>,
+    #[serde(flatten)]
+    pub languages: BTreeMap<Language, Vec<StatFormatter>>,
     pub trade_stats: Option<Vec<TradeStat>>,
 }
 
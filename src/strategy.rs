@@ -3,11 +3,11 @@ use std::{collections::HashSet, ops::RangeInclusive};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    TIERS,
+    MODS, TIERS,
     currency::CurrencyType,
     hashvec::OpaqueIndex,
     item_state::{ItemState, Rarity},
-    types::{Modifier, Omen, Tier},
+    types::{Modifier, Omen, SocketableId, Tier},
 };
 
 /// Eg. LocalAttackSpeed T2-T1
@@ -15,14 +15,59 @@ use crate::{
 pub struct ModifierCondition {
     pub mod_group: OpaqueIndex<Modifier>,
     pub levels: Vec<u32>,
+    /// Whether the mod must be fractured or not. None matches either.
+    #[serde(default)]
+    pub fractured: Option<bool>,
+    /// Minimum roll for every stat, as a percentage of the way through the tier's range.
+    /// Eg. 90 for a life roll of at least 90% of the max. Unrolled mods never pass.
+    #[serde(default)]
+    pub min_roll: Option<u8>,
 }
 
 impl ModifierCondition {
-    pub fn check(&self, tier: &Tier) -> bool {
-        tier.mod_id == self.mod_group && self.levels.contains(&tier.ilvl)
+    pub fn check(&self, item: &ItemState, tier_id: OpaqueIndex<Tier>) -> bool {
+        let tier = &TIERS[tier_id];
+
+        tier.mod_id == self.mod_group
+            && self.levels.contains(&tier.ilvl)
+            && self
+                .fractured
+                .is_none_or(|fractured| fractured == item.fractured.contains(&tier_id))
+            && self
+                .min_roll
+                .is_none_or(|min_roll| roll_percentile_ok(item, tier_id, min_roll))
+    }
+
+    /// Whether something socketed into the item grants every stat of the mod.
+    /// Socketed stats have no tier, fracture or roll, so the levels are ignored and conditions
+    /// on being fractured or the roll can't be met.
+    pub fn check_socketed(&self, item: &ItemState) -> bool {
+        let stats = &MODS[self.mod_group].stats;
+        let socketed_stats = item.socketed_stats();
+
+        self.fractured != Some(true)
+            && self.min_roll.is_none()
+            && !stats.is_empty()
+            && stats
+                .iter()
+                .all(|stat| socketed_stats.iter().any(|(stat_id, _)| stat_id == stat))
     }
 }
 
+fn roll_percentile_ok(item: &ItemState, tier_id: OpaqueIndex<Tier>, min_roll: u8) -> bool {
+    let Some(values) = item.rolls.get(&tier_id) else {
+        return false;
+    };
+
+    values
+        .iter()
+        .zip(&TIERS[tier_id].value_ranges)
+        .all(|(&value, &[min, max])| {
+            // Fixed values are always a perfect roll
+            min == max || (value - min) as f64 / (max - min) as f64 * 100. >= min_roll as f64
+        })
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ConditionGroup {
     Count {
@@ -36,12 +81,19 @@ pub enum ConditionGroup {
         prefixes: RangeInclusive<usize>,
         affixes: RangeInclusive<usize>,
     },
+    /// Number of sockets, which must have all of these socketed
+    Sockets {
+        sockets: RangeInclusive<usize>,
+        socketed: Vec<SocketableId>,
+    },
 }
 impl ConditionGroup {
     pub fn check(&self, item: &ItemState) -> bool {
+        // Implicits are checked along with the affixes
         let item_tiers = item
             .mods
             .iter()
+            .chain(&item.implicits)
             .map(|&tier_id| &TIERS[tier_id])
             .collect::<Vec<_>>();
 
@@ -49,7 +101,13 @@ impl ConditionGroup {
             ConditionGroup::Count { count, mods } => {
                 let num_passed = mods
                     .iter()
-                    .filter(|cond| item_tiers.iter().any(|tier| cond.check(tier)))
+                    .filter(|cond| {
+                        item.mods
+                            .iter()
+                            .chain(&item.implicits)
+                            .any(|&tier_id| cond.check(item, tier_id))
+                            || cond.check_socketed(item)
+                    })
                     .count();
 
                 count.contains(&num_passed)
@@ -76,6 +134,19 @@ impl ConditionGroup {
                     && suffixes.contains(&item_suffixes)
                     && affixes.contains(&(item_prefixes + item_suffixes))
             }
+            ConditionGroup::Sockets { sockets, socketed } => {
+                // Each socketed item can only satisfy one entry
+                let mut remaining = item.sockets.iter().flatten().collect::<Vec<_>>();
+                let all_socketed = socketed.iter().all(|name| {
+                    remaining
+                        .iter()
+                        .position(|&other| other == name)
+                        .map(|index| remaining.swap_remove(index))
+                        .is_some()
+                });
+
+                sockets.contains(&item.sockets.len()) && all_socketed
+            }
         }
     }
 }
@@ -84,13 +155,31 @@ impl ConditionGroup {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Condition {
     pub rarity: Rarity,
+    /// Whether the item must be corrupted or not. None matches either.
+    #[serde(default)]
+    pub corrupted: Option<bool>,
+    /// Whether the item must have a foreseen outcome from Hinekora's Lock. None matches either.
+    #[serde(default)]
+    pub foreseen: Option<bool>,
     /// All of these groups must be true
     pub groups: Vec<ConditionGroup>,
 }
 
 impl Condition {
     pub fn check(&self, item: &ItemState) -> bool {
-        item.rarity == self.rarity && self.groups.iter().all(|group| group.check(item))
+        // A foreseen outcome is checked instead of the item, so a step can decide whether to apply it
+        let target = item
+            .foreseen
+            .as_ref()
+            .map_or(item, |foreseen| &*foreseen.item);
+
+        self.foreseen
+            .is_none_or(|foreseen| foreseen == item.foreseen.is_some())
+            && target.rarity == self.rarity
+            && self
+                .corrupted
+                .is_none_or(|corrupted| corrupted == target.corrupted)
+            && self.groups.iter().all(|group| group.check(target))
     }
 }
 
@@ -98,6 +187,18 @@ impl Condition {
 pub struct Strategy(pub Vec<(Condition, Option<(HashSet<Omen>, CurrencyType)>)>);
 
 impl Strategy {
+    /// Whether any condition depends on rolled values, which exact evaluation doesn't track
+    pub fn uses_rolls(&self) -> bool {
+        self.0.iter().any(|(cond, _)| {
+            cond.groups.iter().any(|group| match group {
+                ConditionGroup::Count { mods, .. } => {
+                    mods.iter().any(|cond| cond.min_roll.is_some())
+                }
+                _ => false,
+            })
+        })
+    }
+
     /// Select a crafting method given the item's current state
     pub fn get_craft(&self, item: &ItemState) -> Option<&(HashSet<Omen>, CurrencyType)> {
         let index = self
@@ -118,3 +219,76 @@ impl Strategy {
             .next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        MODS, TIERS,
+        internal::test_data::{self, RUNE, item, ring},
+        item_state::{ItemState, Rarity},
+        strategy::{ConditionGroup, ModifierCondition},
+    };
+
+    fn has_mod(mod_group: &str, fractured: Option<bool>) -> ConditionGroup {
+        ConditionGroup::Count {
+            count: 1..=1,
+            mods: vec![ModifierCondition {
+                mod_group: MODS.opaque(mod_group),
+                levels: vec![1],
+                fractured,
+                min_roll: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_implicits() {
+        test_data::init();
+        let item = ItemState {
+            implicits: vec![TIERS.opaque("CorruptedCritChance1")],
+            ..ring(Rarity::Rare, &["IncreasedLife1"])
+        };
+
+        assert!(has_mod("CriticalStrikeChance", None).check(&item));
+        assert!(!ConditionGroup::Not(vec![MODS.opaque("CriticalStrikeChance")]).check(&item));
+        // Implicits aren't affixes
+        assert!(
+            ConditionGroup::AffixCount {
+                suffixes: 0..=0,
+                prefixes: 1..=1,
+                affixes: 1..=1,
+            }
+            .check(&item)
+        );
+    }
+
+    #[test]
+    fn test_socketed() {
+        test_data::init();
+        let vest = ItemState {
+            sockets: vec![Some(RUNE.to_string()), None],
+            ..item("Leather Vest", Rarity::Normal, &[])
+        };
+
+        // The rune grants fire resistance on armour
+        assert!(has_mod("FireResistance", None).check(&vest));
+        assert!(has_mod("FireResistance", Some(false)).check(&vest));
+        assert!(!has_mod("FireResistance", Some(true)).check(&vest));
+        assert!(!has_mod("LifeAndMana", None).check(&vest));
+
+        // Each socketed rune only counts once
+        let sockets = |sockets, socketed: &[&str]| ConditionGroup::Sockets {
+            sockets,
+            socketed: socketed.iter().map(|name| name.to_string()).collect(),
+        };
+        assert!(sockets(2..=2, &[]).check(&vest));
+        assert!(sockets(1..=2, &[RUNE]).check(&vest));
+        assert!(!sockets(2..=2, &[RUNE, RUNE]).check(&vest));
+        assert!(!sockets(0..=1, &[RUNE]).check(&vest));
+        let filled = ItemState {
+            sockets: vec![Some(RUNE.to_string()), Some(RUNE.to_string())],
+            ..vest
+        };
+        assert!(sockets(2..=2, &[RUNE, RUNE]).check(&filled));
+    }
+}
@@ -1,7 +1,11 @@
 /**
 *   All the data in a standardised format I want
 */
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::LazyLock,
+};
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -12,15 +16,30 @@ use crate::hashvec::OpaqueIndex;
 pub enum Affix {
     Prefix,
     Suffix,
+    /// Implicits added by corrupting the item
     Corrupted,
+    /// Generation types which aren't modelled yet. Eg. unique, enchantment
+    Other,
 }
 
-// Not sure if this is the best way to represent this
-#[derive(Debug, Clone)]
+/// How a mod can come to be on an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ModType {
+    /// Rolled randomly by currency
     Normal,
+    /// Only revealed by unveiling a desecrated mod
     Desecrated,
+    /// Only added by an essence
     Essence,
+    /// Implicits added by corrupting the item
+    Corrupted,
+}
+
+impl ModType {
+    /// Whether the mod can be rolled from the random pool by currencies like exalts
+    pub fn is_spawnable(&self) -> bool {
+        *self == Self::Normal
+    }
 }
 
 /// Eg. IncreasedLife
@@ -40,7 +59,8 @@ pub struct Modifier {
     /// The individual stats provided by this mod Eg. 2 for hybrid attack/accuracy
     pub stats: Vec<StatID>,
 
-    pub family: ModFamily,
+    /// Mods sharing any family can't roll together. Empty for mods which never conflict.
+    pub families: Vec<ModFamily>,
 }
 
 /// Eg. Strength7
@@ -60,12 +80,22 @@ pub struct Tier {
     pub ilvl: u32,
     /// One range for each stat provided by this mod
     pub value_ranges: Vec<[i32; 2]>,
-    /// Sample weight when rolling this mod
-    pub weight: u32,
+    /// Weight for each base item tag, in order of priority. Eg. (ring, 1000), (default, 0)
+    pub spawn_weights: Vec<(String, u32)>,
     /// The "rolling group" for this mod - maybe better to have in the Modifier struct?
     pub mod_domain: usize,
 }
 
+impl Tier {
+    /// Spawn weight on a base with these tags, from the first tag it has. 0 if none match.
+    pub fn spawn_weight(&self, base_tags: &HashSet<&String>) -> u32 {
+        self.spawn_weights
+            .iter()
+            .find(|(tag, _)| base_tags.contains(tag))
+            .map_or(0, |&(_, weight)| weight)
+    }
+}
+
 /// Eg. %_attack_damage_per_glory_consumed_for_6_seconds_up_to_100
 pub type StatID = String;
 
@@ -76,6 +106,19 @@ pub struct Stat {
     pub id: StatID,
 }
 
+/// Eg. Desert Rune, Soul Core of Tacati
+pub type SocketableId = String;
+
+/// A rune, soul core or talisman, which grants stats to the item it's socketed into
+#[derive(Debug, Clone)]
+pub struct Socketable {
+    pub name: SocketableId,
+    /// Stats granted when socketed into a weapon
+    pub weapon_stats: Vec<(StatID, i32)>,
+    /// Stats granted when socketed into armour
+    pub armour_stats: Vec<(StatID, i32)>,
+}
+
 /// Eg. Sceptre, Boots (dex)
 pub type BaseItemId = String;
 
@@ -85,9 +128,6 @@ struct BaseItem {
     name: BaseItemId,
 }
 
-/// Modifiers which cannot occur together
-type ConflictingMods = Vec<HashSet<ModGroup>>;
-
 /// Mods which can occur on a given base item type
 pub type ItemMods = HashMap<BaseItemId, Vec<TierId>>;
 
@@ -96,12 +136,14 @@ pub type ItemMods = HashMap<BaseItemId, Vec<TierId>>;
 ///     Eg. min/max added damage mods
 ///
 /// Eg. gloves with hybrid es/accuracy and flat damage will be:
-///     es/acc mod -> (es stat, acc stat) -> (es formatter, acc formatter)
-///     flat mod -> (min stat, max stat) -> flat formatter
+/// ```text
+/// es/acc mod -> (es stat, acc stat) -> (es formatter, acc formatter)
+/// flat mod -> (min stat, max stat) -> flat formatter
+/// ```
 ///
-///     Not sure if theres any weirder examples
-///     1) Attempt to look up by all [StatID]s for a mod
-///     2) Fall back to looking up each StatID individually
+/// Not sure if theres any weirder examples
+/// 1) Attempt to look up by all [StatID]s for a mod
+/// 2) Fall back to looking up each StatID individually
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct StatFormatter {
     /// When this formatter is applied
@@ -112,7 +154,7 @@ pub struct StatFormatter {
     pub formats: Vec<String>,
     /// Value pre-processing functions
     pub index_handlers: Vec<Vec<String>>,
-    /// Format string with {i} and [either/or] placeholders
+    /// Format string with {i} placeholders and [Keyword|Display text] links
     pub string: String,
 }
 
@@ -120,44 +162,16 @@ impl StatFormatter {
     /// Get the mod as it appears on the trade site
     /// Eg. "+# increased Attack Speed"
     pub fn trade_string(&self) -> String {
-        let string = self.string.clone();
-
         // "{i}" -> "#"
         let string = self
             .formats
             .iter()
             .enumerate()
-            .fold(string, |string, (i, replace)| {
+            .fold(self.string.clone(), |string, (i, replace)| {
                 string.replacen(&format!("{{{i}}}"), replace, 1)
             });
 
-        // "[A|B]" -> B
-        let regex_either = Regex::new(r"\[([^\]]+)\]").unwrap();
-        let replaces = regex_either
-            .captures_iter(&string)
-            .map(|hit| {
-                // First capture group is the entire thing
-                let find = hit.iter().next().unwrap().unwrap().as_str();
-                // Replace with last item in the list
-                let replace = hit
-                    .iter()
-                    .last()
-                    .unwrap()
-                    .unwrap()
-                    .as_str()
-                    .split("|")
-                    .last()
-                    .unwrap();
-
-                (find, replace)
-            })
-            .collect::<Vec<_>>();
-
-        replaces
-            .iter()
-            .fold(string.clone(), |acc, (find, replace)| {
-                acc.replacen(find, replace, 1)
-            })
+        resolve_links(&string)
     }
 
     /// A string ready to format!()
@@ -169,47 +183,264 @@ impl StatFormatter {
     /// Format with placeholder values for a tier
     /// Eg. "+(5-12) increased Attack Speed"
     pub fn format_value_range(&self, value_ranges: &[[i32; 2]]) -> String {
-        value_ranges
+        self.substitute(|i| {
+            let [min, max] = value_ranges.get(i)?;
+            let (min, max) = (self.handle_value(i, *min), self.handle_value(i, *max));
+            // Handlers like negate can flip the order
+            let (min, max) = if min.0 <= max.0 {
+                (min, max)
+            } else {
+                (max, min)
+            };
+
+            let (min, max) = (format_number(min), format_number(max));
+            let range = if min == max {
+                min
+            } else {
+                format!("({min}-{max})")
+            };
+            Some(self.sign(i, range))
+        })
+    }
+
+    /// Format with concrete rolled values
+    /// Eg. "+9 increased Attack Speed"
+    pub fn format_values(&self, values: &[i32]) -> String {
+        self.substitute(|i| {
+            let value = self.handle_value(i, *values.get(i)?);
+            Some(self.sign(i, format_number(value)))
+        })
+    }
+
+    /// Replace each "{i}" placeholder, then resolve links to their display text
+    fn substitute(&self, mut display: impl FnMut(usize) -> Option<String>) -> String {
+        let string = (0..self.formats.len()).fold(self.string.clone(), |string, i| {
+            let placeholder = format!("{{{i}}}");
+            match display(i) {
+                Some(value) if string.contains(&placeholder) => {
+                    string.replace(&placeholder, &value)
+                }
+                _ => string,
+            }
+        });
+
+        resolve_links(&string)
+    }
+
+    /// Reverse of format_values: the raw values of a line, if this formatter could have produced it.
+    /// Values which aren't displayed are None
+    /// Eg. "Adds 3 to 7 Physical Damage" -> [Some(3), Some(7)]
+    pub fn parse_values(&self, line: &str) -> Option<Vec<Option<i32>>> {
+        let template = resolve_links(&self.string);
+
+        let mut values = vec![None; self.formats.len()];
+        let mut rest = line;
+        let mut literal_start = 0;
+        for caps in REGEX_PLACEHOLDER.captures_iter(&template) {
+            let placeholder = caps.get(0).unwrap();
+            rest = rest.strip_prefix(&template[literal_start..placeholder.start()])?;
+            literal_start = placeholder.end();
+
+            // Eg. +12, -3, 1.5
+            let len = rest
+                .char_indices()
+                .take_while(|&(i, c)| {
+                    c.is_ascii_digit() || c == '.' || (i == 0 && "+-".contains(c))
+                })
+                .count();
+            // A full stop after the number belongs to the text
+            let len = len - usize::from(rest[..len].ends_with('.'));
+            let displayed = rest[..len].parse::<f64>().ok()?;
+            rest = &rest[len..];
+
+            let i = caps[1].parse::<usize>().ok()?;
+            *values.get_mut(i)? = Some(self.raw_value(i, displayed)?);
+        }
+        if rest != &template[literal_start..] {
+            return None;
+        }
+
+        // Eg. "reduced" formatters only apply to negative values
+        self.conditions
             .iter()
-            .fold(self.rust_fmt_string(), |string, value_range| {
-                string.replacen("{}", &format!("({}-{})", value_range[0], value_range[1]), 1)
-            })
+            .zip(&values)
+            .all(|(cond, value)| value.is_none_or(|value| cond.matches(value)))
+            .then_some(values)
+    }
+
+    /// Undo the index handlers for the i'th value. They're all linear apart from rounding
+    fn raw_value(&self, i: usize, displayed: f64) -> Option<i32> {
+        let offset = self.handle_value(i, 0).0;
+        let scale = (self.handle_value(i, 1000).0 - offset) / 1000.0;
+        (scale != 0.0).then(|| ((displayed - offset) / scale).round() as i32)
+    }
+
+    /// Apply the index handlers for the i'th value
+    fn handle_value(&self, i: usize, value: i32) -> (f64, Precision) {
+        self.index_handlers.get(i).into_iter().flatten().fold(
+            (value as f64, Precision::UpTo(2)),
+            |(value, precision), handler| {
+                let (handler, handler_precision) = Precision::split_handler(handler);
+                (
+                    apply_index_handler(handler, value),
+                    handler_precision.unwrap_or(precision),
+                )
+            },
+        )
     }
+
+    /// "+#" formats show the sign of positive values too
+    fn sign(&self, i: usize, value: String) -> String {
+        match self.formats.get(i).map(String::as_str) {
+            Some("+#") if !value.starts_with('-') => format!("+{value}"),
+            _ => value,
+        }
+    }
+}
+
+/// How many decimal places a handled value is displayed with
+#[derive(Debug, Clone, Copy)]
+enum Precision {
+    /// Always this many, truncating any more. Eg. _0dp, _1dp
+    Fixed(usize),
+    /// Up to this many, dropping trailing zeros. Eg. _2dp_if_required
+    UpTo(usize),
+}
+
+impl Precision {
+    /// Split the precision suffix off a handler
+    /// Eg. divide_by_ten_1dp -> (divide_by_ten, Fixed(1))
+    fn split_handler(handler: &str) -> (&str, Option<Self>) {
+        let (base, if_required) = match handler.strip_suffix("_if_required") {
+            Some(base) => (base, true),
+            None => (handler, false),
+        };
+        let Some((base, places)) = base
+            .rsplit_once('_')
+            .and_then(|(base, suffix)| Some((base, suffix.strip_suffix("dp")?.parse().ok()?)))
+        else {
+            return (handler, None);
+        };
+
+        let precision = if if_required {
+            Self::UpTo(places)
+        } else {
+            Self::Fixed(places)
+        };
+        (base, Some(precision))
+    }
+}
+
+fn format_number((value, precision): (f64, Precision)) -> String {
+    match precision {
+        Precision::Fixed(places) => {
+            let scale = 10f64.powi(places as i32);
+            format!("{:.places$}", (value * scale).trunc() / scale)
+        }
+        Precision::UpTo(places) => {
+            let string = format!("{value:.places$}");
+            if string.contains('.') {
+                string
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string()
+            } else {
+                string
+            }
+        }
+    }
+}
+
+/// Pre-process a value before it's displayed, without any precision suffix.
+/// Unknown handlers leave the value alone
+/// Eg. divide_by_one_hundred, milliseconds_to_seconds
+fn apply_index_handler(handler: &str, value: f64) -> f64 {
+    match handler {
+        "negate" => -value,
+        "double" => value * 2.0,
+        "negate_and_double" => -value * 2.0,
+        "times_twenty" => value * 20.0,
+        "times_one_point_five" => value * 1.5,
+        "multiply_by_four" => value * 4.0,
+        "30%_of_value" => value * 0.3,
+        "60%_of_value" => value * 0.6,
+        "plus_two_hundred" => value + 200.0,
+        "multiplicative_damage_modifier" => value + 100.0,
+        "multiplicative_permyriad_damage_modifier" => value / 100.0 + 100.0,
+        "old_leech_percent" => value / 5.0,
+        "old_leech_permyriad" => value / 500.0,
+        "per_minute_to_per_second" => value / 60.0,
+        "milliseconds_to_seconds" => value / 1000.0,
+        "deciseconds_to_seconds" => value / 10.0,
+        "divide_by_one_hundred_and_negate" => -value / 100.0,
+        "divide_by_twenty_then_double" => (value / 20.0).trunc() * 2.0,
+        _ => match handler.strip_prefix("divide_by_").and_then(number_word) {
+            Some(divisor) => value / divisor,
+            None => value,
+        },
+    }
+}
+
+/// Eg. one_hundred -> 100
+fn number_word(word: &str) -> Option<f64> {
+    Some(match word {
+        "two" => 2.0,
+        "three" => 3.0,
+        "four" => 4.0,
+        "five" => 5.0,
+        "six" => 6.0,
+        "ten" => 10.0,
+        "twelve" => 12.0,
+        "fifteen" => 15.0,
+        "twenty" => 20.0,
+        "fifty" => 50.0,
+        "one_hundred" => 100.0,
+        "one_thousand" => 1000.0,
+        _ => return None,
+    })
+}
+
+static REGEX_LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]+)\]").unwrap());
+static REGEX_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\d+)\}").unwrap());
+
+/// Links to keywords are written as [Keyword|Display text] or just [Keyword]
+/// Eg. "[Critical|Critical Hit] Chance" -> "Critical Hit Chance"
+fn resolve_links(string: &str) -> String {
+    REGEX_LINK
+        .replace_all(string, |caps: &regex::Captures| {
+            caps[1].split('|').next_back().unwrap().to_string()
+        })
+        .into_owned()
 }
 
+/// The first formatter whose conditions match the values, if any
 pub fn get_matching_formatter<'a>(
     formatters: &'a [StatFormatter],
     values: &[i32],
-) -> &'a StatFormatter {
-    formatters
-        .iter()
-        .find(|formatter| {
-            formatter
-                .conditions
-                .iter()
-                .zip(values)
-                .all(|(cond, val)| cond.matches(*val))
-        })
-        .unwrap_or_else(|| {
-            panic!(
-                "No matching formatter! values {:?} formatters {:?}",
-                values, formatters
-            )
-        })
+) -> Option<&'a StatFormatter> {
+    formatters.iter().find(|formatter| {
+        formatter
+            .conditions
+            .iter()
+            .zip(values)
+            .all(|(cond, val)| cond.matches(*val))
+    })
 }
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct Condition {
     pub min: Option<i32>,
     pub max: Option<i32>,
+    /// Matches values outside of the range instead. Eg. !0
     pub negate: Option<bool>,
 }
 
 impl Condition {
     pub fn matches(&self, value: i32) -> bool {
-        self.min.map(|min| value >= min).unwrap_or(true)
-            && self.max.map(|max| value <= max).unwrap_or(true)
-        // TODO: Not sure how negated works
+        let in_range = self.min.map(|min| value >= min).unwrap_or(true)
+            && self.max.map(|max| value <= max).unwrap_or(true);
+
+        in_range != self.negate.unwrap_or(false)
     }
 }
 
@@ -217,11 +448,370 @@ impl Condition {
 /// LUT key is "|" delimited StatID's
 pub type StatFormatters = HashMap<String, Vec<StatFormatter>>;
 
+/// Client language of the stat descriptions. Eg. English, German, Korean
+pub type Language = String;
+
+/// Language stat text is shown in by default, and the fallback for untranslated stats
+pub const DEFAULT_LANGUAGE: &str = "English";
+
+/// Omens, which change the behaviour of one kind of currency while they're active
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Omen {
-    Sinistral,
-    Dextral,
-    Homogenous,
-    Greater,
+    // Exalted Orb
+    SinistralExaltation,
+    DextralExaltation,
+    GreaterExaltation,
+    HomogenisingExaltation,
+    CatalysingExaltation,
+    // Regal Orb
+    SinistralCoronation,
+    DextralCoronation,
+    HomogenisingCoronation,
+    // Orb of Annulment
+    SinistralAnnulment,
+    DextralAnnulment,
+    GreaterAnnulment,
+    Light,
+    // Chaos Orb
+    SinistralErasure,
+    DextralErasure,
     Whittling,
+    // Orb of Alchemy
+    SinistralAlchemy,
+    DextralAlchemy,
+    // Perfect Essence
+    SinistralCrystallisation,
+    DextralCrystallisation,
+    // Desecration
+    SinistralNecromancy,
+    DextralNecromancy,
+    // Vaal Orb
+    Corruption,
+    // Any currency
+    HinekorasLock,
+}
+
+impl Omen {
+    /// Name of the omen item
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SinistralExaltation => "Omen of Sinistral Exaltation",
+            Self::DextralExaltation => "Omen of Dextral Exaltation",
+            Self::GreaterExaltation => "Omen of Greater Exaltation",
+            Self::HomogenisingExaltation => "Omen of Homogenising Exaltation",
+            Self::CatalysingExaltation => "Omen of Catalysing Exaltation",
+            Self::SinistralCoronation => "Omen of Sinistral Coronation",
+            Self::DextralCoronation => "Omen of Dextral Coronation",
+            Self::HomogenisingCoronation => "Omen of Homogenising Coronation",
+            Self::SinistralAnnulment => "Omen of Sinistral Annulment",
+            Self::DextralAnnulment => "Omen of Dextral Annulment",
+            Self::GreaterAnnulment => "Omen of Greater Annulment",
+            Self::Light => "Omen of Light",
+            Self::SinistralErasure => "Omen of Sinistral Erasure",
+            Self::DextralErasure => "Omen of Dextral Erasure",
+            Self::Whittling => "Omen of Whittling",
+            Self::SinistralAlchemy => "Omen of Sinistral Alchemy",
+            Self::DextralAlchemy => "Omen of Dextral Alchemy",
+            Self::SinistralCrystallisation => "Omen of Sinistral Crystallisation",
+            Self::DextralCrystallisation => "Omen of Dextral Crystallisation",
+            Self::SinistralNecromancy => "Omen of Sinistral Necromancy",
+            Self::DextralNecromancy => "Omen of Dextral Necromancy",
+            Self::Corruption => "Omen of Corruption",
+            Self::HinekorasLock => "Hinekora's Lock",
+        }
+    }
+
+    /// Affix a Sinistral (prefix) or Dextral (suffix) omen restricts its currency to
+    pub fn affix(&self) -> Option<Affix> {
+        use Omen::*;
+        match self {
+            SinistralExaltation
+            | SinistralCoronation
+            | SinistralAnnulment
+            | SinistralErasure
+            | SinistralAlchemy
+            | SinistralCrystallisation
+            | SinistralNecromancy => Some(Affix::Prefix),
+            DextralExaltation
+            | DextralCoronation
+            | DextralAnnulment
+            | DextralErasure
+            | DextralAlchemy
+            | DextralCrystallisation
+            | DextralNecromancy => Some(Affix::Suffix),
+            GreaterExaltation
+            | HomogenisingExaltation
+            | HomogenisingCoronation
+            | CatalysingExaltation
+            | GreaterAnnulment
+            | Light
+            | Whittling
+            | Corruption
+            | HinekorasLock => None,
+        }
+    }
+
+    /// Whether the omen restricts added mods to the tags already on the item
+    pub fn is_homogenising(&self) -> bool {
+        matches!(
+            self,
+            Self::HomogenisingExaltation | Self::HomogenisingCoronation
+        )
+    }
+
+    /// Whether both omens can't be active at once. Eg. a Sinistral and Dextral omen
+    pub fn conflicts_with(&self, other: &Omen) -> bool {
+        self.affix().zip(other.affix()).is_some_and(|(a, b)| a != b)
+    }
+}
+
+impl Display for Omen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Catalyst quality on jewellery, which makes mods with the matching tag more likely
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum QualityType {
+    Life,
+    Mana,
+    Defences,
+    Physical,
+    Fire,
+    Cold,
+    Lightning,
+    Chaos,
+    Attack,
+    Caster,
+    Speed,
+    Attribute,
+}
+
+impl QualityType {
+    pub const ALL: [QualityType; 12] = [
+        Self::Life,
+        Self::Mana,
+        Self::Defences,
+        Self::Physical,
+        Self::Fire,
+        Self::Cold,
+        Self::Lightning,
+        Self::Chaos,
+        Self::Attack,
+        Self::Caster,
+        Self::Speed,
+        Self::Attribute,
+    ];
+
+    /// Mod tag which the quality applies to
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::Life => "Life",
+            Self::Mana => "Mana",
+            Self::Defences => "Defences",
+            Self::Physical => "Physical",
+            Self::Fire => "Fire",
+            Self::Cold => "Cold",
+            Self::Lightning => "Lightning",
+            Self::Chaos => "Chaos",
+            Self::Attack => "Attack",
+            Self::Caster => "Caster",
+            Self::Speed => "Speed",
+            Self::Attribute => "Attribute",
+        }
+    }
+
+    /// Name of the catalyst which adds this quality
+    pub fn catalyst(&self) -> &'static str {
+        match self {
+            Self::Life => "Flesh Catalyst",
+            Self::Mana => "Neural Catalyst",
+            Self::Defences => "Carapace Catalyst",
+            Self::Physical => "Uul-Netol's Catalyst",
+            Self::Fire => "Xoph's Catalyst",
+            Self::Cold => "Tul's Catalyst",
+            Self::Lightning => "Esh's Catalyst",
+            Self::Chaos => "Chayula's Catalyst",
+            Self::Attack => "Reaver Catalyst",
+            Self::Caster => "Sibilant Catalyst",
+            Self::Speed => "Skittering Catalyst",
+            Self::Attribute => "Adaptive Catalyst",
+        }
+    }
+}
+
+impl Display for QualityType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tag())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Condition, StatFormatter, apply_index_handler, resolve_links};
+
+    fn formatter(formats: &[&str], index_handlers: &[&[&str]], string: &str) -> StatFormatter {
+        StatFormatter {
+            conditions: vec![],
+            formats: formats.iter().map(|format| format.to_string()).collect(),
+            index_handlers: index_handlers
+                .iter()
+                .map(|handlers| handlers.iter().map(|handler| handler.to_string()).collect())
+                .collect(),
+            string: string.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_index_handler() {
+        assert_eq!(apply_index_handler("negate", 5.0), -5.0);
+        assert_eq!(apply_index_handler("divide_by_one_hundred", 250.0), 2.5);
+        assert_eq!(
+            apply_index_handler("multiplicative_permyriad_damage_modifier", 2500.0),
+            125.0
+        );
+        assert_eq!(
+            apply_index_handler("divide_by_twenty_then_double", 45.0),
+            4.0
+        );
+        // Unknown handlers and divisors leave the value alone
+        assert_eq!(apply_index_handler("divide_by_seven", 7.0), 7.0);
+        assert_eq!(apply_index_handler("canonical_line", 7.0), 7.0);
+    }
+
+    #[test]
+    fn test_index_handlers() {
+        let format = |handlers: &[&str], value: i32| {
+            formatter(&["#"], &[handlers], "{0}").format_values(&[value])
+        };
+        // Up to 2 decimal places by default
+        assert_eq!(format(&["divide_by_three"], 10), "3.33");
+        assert_eq!(format(&["divide_by_ten"], 20), "2");
+        // A fixed precision is truncated and always shown
+        assert_eq!(format(&["divide_by_ten_1dp"], 25), "2.5");
+        assert_eq!(format(&["divide_by_ten_1dp"], 20), "2.0");
+        assert_eq!(format(&["milliseconds_to_seconds_0dp"], 1999), "1");
+        assert_eq!(format(&["divide_by_ten_1dp_if_required"], 20), "2");
+        // Handlers are applied in order
+        assert_eq!(format(&["negate", "divide_by_one_hundred"], -1234), "12.34");
+        assert_eq!(format(&["divide_by_two", "double"], 5), "5");
+
+        // Each value has its own handlers
+        let damage = formatter(
+            &["#", "#"],
+            &[&[], &["divide_by_one_hundred"]],
+            "{0} to {1}",
+        );
+        assert_eq!(damage.format_values(&[150, 150]), "150 to 1.5");
+        assert_eq!(
+            damage.format_value_range(&[[1, 2], [100, 250]]),
+            "(1-2) to (1-2.5)"
+        );
+    }
+
+    #[test]
+    fn test_negate_condition() {
+        let not_zero = Condition {
+            min: Some(0),
+            max: Some(0),
+            negate: Some(true),
+        };
+        assert!(not_zero.matches(5));
+        assert!(not_zero.matches(-5));
+        assert!(!not_zero.matches(0));
+
+        let outside = Condition {
+            min: Some(1),
+            max: Some(10),
+            negate: Some(true),
+        };
+        assert!(outside.matches(0));
+        assert!(outside.matches(11));
+        assert!(!outside.matches(1));
+        assert!(!outside.matches(10));
+
+        let inside = Condition {
+            negate: Some(false),
+            ..outside
+        };
+        assert!(inside.matches(1));
+        assert!(!inside.matches(11));
+    }
+
+    #[test]
+    fn test_resolve_links() {
+        assert_eq!(
+            resolve_links("[Critical|Critical Hit] Chance"),
+            "Critical Hit Chance"
+        );
+        assert_eq!(
+            resolve_links("No [Life] Regeneration"),
+            "No Life Regeneration"
+        );
+        assert_eq!(
+            resolve_links("[Fire] and [Cold|Cold Damage]"),
+            "Fire and Cold Damage"
+        );
+        assert_eq!(resolve_links("+{0} to Strength"), "+{0} to Strength");
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let damage = formatter(&["#", "#"], &[], "Adds {0} to {1} Physical Damage");
+        assert_eq!(
+            damage.parse_values("Adds 3 to 7 Physical Damage"),
+            Some(vec![Some(3), Some(7)])
+        );
+        assert_eq!(damage.parse_values("Adds 3 to 7 Fire Damage"), None);
+        assert_eq!(damage.parse_values("Adds 3 to 7 Physical Damage!"), None);
+        assert_eq!(damage.parse_values("Adds a to 7 Physical Damage"), None);
+
+        // Links are matched by their display text, and signs are kept
+        let crit = formatter(
+            &["+#"],
+            &[],
+            "{0}% increased [Critical|Critical Hit] Chance",
+        );
+        assert_eq!(
+            crit.parse_values("+25% increased Critical Hit Chance"),
+            Some(vec![Some(25)])
+        );
+
+        // Values which aren't displayed are None
+        let strength = formatter(&["#", "#"], &[], "{1} to Strength");
+        assert_eq!(
+            strength.parse_values("5 to Strength"),
+            Some(vec![None, Some(5)])
+        );
+
+        // Handlers are undone, leaving a full stop after the number to the text
+        let duration = formatter(
+            &["#"],
+            &[&["milliseconds_to_seconds"]],
+            "Lasts {0} seconds.",
+        );
+        assert_eq!(
+            duration.parse_values("Lasts 2.5 seconds."),
+            Some(vec![Some(2500)])
+        );
+        let third = formatter(&["#"], &[&["divide_by_three"]], "{0} per second");
+        let line = third.format_values(&[10]);
+        assert_eq!(third.parse_values(&line), Some(vec![Some(10)]));
+
+        // Values have to match the conditions
+        let reduced = StatFormatter {
+            conditions: vec![Condition {
+                min: None,
+                max: Some(-1),
+                negate: None,
+            }],
+            ..formatter(&["#"], &[&["negate"]], "{0}% reduced Attack Speed")
+        };
+        assert_eq!(
+            reduced.parse_values("12% reduced Attack Speed"),
+            Some(vec![Some(-12)])
+        );
+        assert_eq!(reduced.parse_values("-12% reduced Attack Speed"), None);
+    }
 }
@@ -22,10 +22,22 @@ pub fn currency_dropdown<'a>(
     //          Perfect
     //      Hysteria
     //      Horror
-    //  Desecrate
+    // Desecrate
     //      Jawbone
+    //          Gnawed
     //          Preserved
     //          Ancient
+    //      Rib
+    //      Collarbone
+    // Catalyst
+    //      Flesh Catalyst
+    //      ...
+    // Socketable
+    //      Desert Rune
+    //          Lesser
+    //          Base
+    //          Greater
+    //      Soul Core of Tacati
 
     // Bunch of pre-processing of the currencies to group them up nicely
     use CurrencyType::*;
@@ -33,10 +45,16 @@ pub fn currency_dropdown<'a>(
         .iter()
         .sorted_unstable_by_key(|c| match c {
             Essence(_) | PerfectEssence(_) => "Essence",
+            Desecrate(_) => "Desecrate",
+            Catalyst(_) => "Catalyst",
+            Rune(_) => "Socketable",
             _ => "Standard",
         })
         .chunk_by(|c| match c {
             Essence(_) | PerfectEssence(_) => "Essence",
+            Desecrate(_) => "Desecrate",
+            Catalyst(_) => "Catalyst",
+            Rune(_) => "Socketable",
             _ => "Standard",
         });
 
@@ -46,6 +64,9 @@ pub fn currency_dropdown<'a>(
         let prefixes = match category {
             "Standard" => vec!["Greater ", "Perfect "],
             "Essence" => vec!["Lesser ", "Greater ", "Perfect "],
+            "Desecrate" => vec!["Gnawed ", "Preserved ", "Ancient "],
+            "Catalyst" => vec![],
+            "Socketable" => vec!["Lesser ", "Greater "],
             _ => unreachable!(),
         };
         let base_types = group
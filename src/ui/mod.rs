@@ -3,10 +3,10 @@ pub mod pages;
 
 use std::{collections::HashSet, mem::replace, ops::RangeInclusive};
 
-use egui::{ComboBox, DragValue, Ui};
+use egui::{Checkbox, ComboBox, DragValue, Ui};
 
 use crate::{
-    currency::{Currency, CurrencyType},
+    currency::{CraftError, Currency, CurrencyType},
     item_state::{ItemState, Rarity, get_valid_mods_for_item},
     strategy::Strategy,
     types::Omen,
@@ -20,12 +20,16 @@ pub enum Page {
     CraftProbability {
         selected_currency: CurrencyType,
         selected_omens: HashSet<Omen>,
+        exact_cache: pages::currency_sim::ExactCache,
         simulation_state: Option<pages::currency_sim::SimState>,
         num_iters_exp: u32,
+        seed: u64,
     },
     StrategyBuilder {
         strategy: Strategy,
         simulation_state: Option<pages::strategy_sim::SimState>,
+        evaluation: Option<pages::strategy_sim::EvalState>,
+        seed: u64,
     },
     UIDebug(ui_debug::PageState),
 }
@@ -39,12 +43,16 @@ impl Page {
             CraftProbability {
                 selected_currency: CurrencyType::Transmute,
                 selected_omens: HashSet::new(),
+                exact_cache: Default::default(),
                 simulation_state: None,
                 num_iters_exp: 5,
+                seed: 0,
             },
             StrategyBuilder {
                 strategy: Strategy(vec![]),
                 simulation_state: None,
+                evaluation: None,
+                seed: 0,
             },
             UIDebug(ui_debug::PageState::default()),
         ]
@@ -103,6 +111,20 @@ where
     }
 }
 
+/// Seed for the simulation RNG, so runs can be reproduced
+pub fn seed_selection(ui: &mut Ui, seed: &mut u64) {
+    // DragValue goes through f64, so larger seeds wouldn't survive being edited
+    const MAX_SEED: u64 = (1 << f64::MANTISSA_DIGITS) - 1;
+
+    ui.horizontal(|ui| {
+        ui.label("Seed");
+        ui.add(DragValue::new(seed).range(0..=MAX_SEED));
+        if ui.button("Random").clicked() {
+            *seed = rand::random::<u64>() & MAX_SEED;
+        }
+    });
+}
+
 /// Selection for an inclusive range
 pub fn range_selector(
     ui: &mut Ui,
@@ -155,24 +177,43 @@ pub fn omen_selection(
     omens: &mut HashSet<Omen>,
     item_filter: Option<&ItemState>,
 ) {
-    let possible_omens = currency.possible_omens();
-    let mut possible_omens = possible_omens.iter().collect::<Vec<_>>();
-    if let Some(item) = item_filter {
-        let candidate_tiers = get_valid_mods_for_item(item);
-        possible_omens.retain(|&&o| {
-            currency.can_be_used(
-                item,
-                &candidate_tiers,
-                &HashSet::from_iter(std::iter::once(o)),
-            )
-        });
-    }
+    let mut possible_omens = currency.possible_omens().into_iter().collect::<Vec<_>>();
     possible_omens.sort();
 
-    let mut selected_omens = omens.iter().copied().collect::<Vec<_>>();
-    multi_select_checkboxes(ui, &mut selected_omens, &possible_omens, |omen| {
-        format!("{omen:?}")
-    });
+    let candidate_tiers = item_filter.map(get_valid_mods_for_item);
+    ui.horizontal(|ui| {
+        for omen in possible_omens {
+            // Omens which can't be added alongside the selected ones are disabled, with the reason
+            // on hover. Selected omens can always be deselected.
+            let reason = if omens.contains(&omen) {
+                None
+            } else if let Some(&other) = omens.iter().find(|other| other.conflicts_with(&omen)) {
+                Some(CraftError::ConflictingOmens {
+                    first: other,
+                    second: omen,
+                })
+            } else {
+                item_filter
+                    .zip(candidate_tiers.as_ref())
+                    .and_then(|(item, candidate_tiers)| {
+                        let mut omens = omens.clone();
+                        omens.insert(omen);
+                        currency.can_be_used(item, candidate_tiers, &omens).err()
+                    })
+            };
+
+            let mut selected = omens.contains(&omen);
+            let response =
+                ui.add_enabled(reason.is_none(), Checkbox::new(&mut selected, omen.name()));
+            if let Some(reason) = reason {
+                response.on_disabled_hover_text(reason.to_string());
+            }
 
-    *omens = HashSet::from_iter(selected_omens)
+            if selected {
+                omens.insert(omen);
+            } else {
+                omens.remove(&omen);
+            }
+        }
+    });
 }
@@ -1,35 +1,76 @@
 #[cfg(not(target_arch = "wasm32"))]
 use std::thread::{self, JoinHandle};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
 use egui::{self, DragValue, Grid, ScrollArea, Ui};
 use itertools::Itertools;
+use rand::SeedableRng;
 
 use crate::{
     CURRENCIES, MODS, TIERS,
-    currency::{Currency, CurrencyType},
+    currency::{CraftOutcome, Currency, CurrencyType},
     hashvec::OpaqueIndex,
     item_state::{ItemState, get_valid_mods_for_item},
     types::{Omen, Tier},
-    ui::{Page, components::currency_selection::currency_dropdown, omen_selection},
+    ui::{Page, components::currency_selection::currency_dropdown, omen_selection, seed_selection},
+    util::rand::CraftRng,
 };
 
+/// How often each tier was added to or removed from the item
+#[derive(Debug, Default)]
+pub struct TierResults<T> {
+    added: HashMap<OpaqueIndex<Tier>, T>,
+    removed: HashMap<OpaqueIndex<Tier>, T>,
+}
+
+impl TierResults<usize> {
+    fn record(&mut self, outcome: &CraftOutcome) {
+        for &tier_id in &outcome.added {
+            *self.added.entry(tier_id).or_default() += 1;
+        }
+        for &tier_id in &outcome.removed {
+            *self.removed.entry(tier_id).or_default() += 1;
+        }
+    }
+
+    fn extend(&mut self, other: Self) {
+        for (tier_id, count) in other.added {
+            *self.added.entry(tier_id).or_default() += count;
+        }
+        for (tier_id, count) in other.removed {
+            *self.removed.entry(tier_id).or_default() += count;
+        }
+    }
+
+    fn probabilities(&self, num_iters: u64) -> TierResults<f64> {
+        let normalise = |counts: &HashMap<OpaqueIndex<Tier>, usize>| {
+            counts
+                .iter()
+                .map(|(&tier_id, &count)| (tier_id, count as f64 / num_iters as f64))
+                .collect()
+        };
+
+        TierResults {
+            added: normalise(&self.added),
+            removed: normalise(&self.removed),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SimStatus {
-    Done {
-        results: HashMap<OpaqueIndex<Tier>, usize>,
-    },
-    Running {
-        iterations_done: usize,
-    },
+    Done { results: TierResults<usize> },
+    Running { iterations_done: usize },
 }
 
 #[derive(Debug)]
 pub struct SimState {
     _base_item: ItemState,
+    num_iters: u64,
+    seed: u64,
     status: Arc<Mutex<SimStatus>>,
     #[cfg(not(target_arch = "wasm32"))]
     _handle: JoinHandle<()>,
@@ -41,20 +82,14 @@ fn sim_batch(
     omens: &HashSet<Omen>,
     candidate_tiers: &[OpaqueIndex<Tier>],
     num_iters: usize,
-) -> HashMap<OpaqueIndex<Tier>, usize> {
-    let mut results = HashMap::new();
-    let before_mods = base_item.mods.iter().copied().collect::<HashSet<_>>();
+    rng: &mut CraftRng,
+) -> TierResults<usize> {
+    let mut results = TierResults::default();
     for _ in 0..num_iters {
         // Apply the currency
         let mut item = base_item.clone();
-        currency.craft(&mut item, candidate_tiers, omens);
-
-        // Figure out which mod was added
-        let after_mods = item.mods.iter().copied().collect::<HashSet<_>>();
-        let added = after_mods.difference(&before_mods);
-        for tier_id in added {
-            *results.entry(*tier_id).or_default() += 1;
-        }
+        let outcome = currency.craft(&mut item, candidate_tiers, omens, rng);
+        results.record(&outcome);
     }
 
     results
@@ -67,28 +102,25 @@ fn run_sim(
     currency: CurrencyType,
     omens: HashSet<Omen>,
     num_iters: u64,
+    seed: u64,
 ) -> SimState {
     let candidate_tiers = get_valid_mods_for_item(&base_item);
 
     let status = Arc::new(Mutex::new(SimStatus::Running { iterations_done: 0 }));
     SimState {
         _base_item: base_item.clone(),
+        num_iters,
+        seed,
         status: status.clone(),
         _handle: thread::spawn({
             move || {
-                let mut results = HashMap::new();
-                let before_mods = base_item.mods.iter().copied().collect::<HashSet<_>>();
+                let mut rng = CraftRng::seed_from_u64(seed);
+                let mut results = TierResults::default();
                 for _ in 0..num_iters {
                     // Apply the currency
                     let mut item = base_item.clone();
-                    currency.craft(&mut item, &candidate_tiers, &omens);
-
-                    // Figure out which mod was added
-                    let after_mods = item.mods.iter().copied().collect::<HashSet<_>>();
-                    let added = after_mods.difference(&before_mods);
-                    for tier_id in added {
-                        *results.entry(*tier_id).or_default() += 1;
-                    }
+                    let outcome = currency.craft(&mut item, &candidate_tiers, &omens, &mut rng);
+                    results.record(&outcome);
 
                     // Update status
                     let mut status = status.lock().unwrap();
@@ -114,6 +146,7 @@ fn run_sim(
     currency: CurrencyType,
     omens: HashSet<Omen>,
     num_iters: u64,
+    seed: u64,
 ) -> SimState {
     let candidate_tiers = get_valid_mods_for_item(&base_item);
 
@@ -121,6 +154,8 @@ fn run_sim(
 
     let state = SimState {
         _base_item: base_item.clone(),
+        num_iters,
+        seed,
         status: status.clone(),
     };
 
@@ -131,24 +166,23 @@ fn run_sim(
         let batch_sizes = std::iter::repeat_n(batch_size, (num_iters / batch_size) as usize)
             .chain(std::iter::once(num_iters % batch_size));
 
-        let mut results = HashMap::new();
+        let mut rng = CraftRng::seed_from_u64(seed);
+        let mut results = TierResults::default();
         for batch_size in batch_sizes {
             // Run batch of simulations
-            async {
+            let batch_results = async {
                 sim_batch(
                     &base_item,
                     &currency,
                     &omens,
                     &candidate_tiers,
                     batch_size as usize,
+                    &mut rng,
                 )
             }
-            .await
-            .into_iter()
+            .await;
             // Coalesce results
-            .for_each(|(tier_id, count)| {
-                *results.entry(tier_id).or_default() += count;
-            });
+            results.extend(batch_results);
 
             // Update status
             {
@@ -172,42 +206,149 @@ fn run_sim(
     state
 }
 
-/// A grid showing the % chance for each mod to roll
-fn display_sim_results(ui: &mut Ui, results: &HashMap<OpaqueIndex<Tier>, usize>) {
-    let total_iters = results.values().sum::<usize>();
+/// None if the currency doesn't support exact outcomes
+type ExactResults = Option<TierResults<f64>>;
+/// Shared with the background calculation, None until it finishes
+type PendingResults = Arc<Mutex<Option<ExactResults>>>;
+/// (item, currency name, omens)
+type ExactKey = (ItemState, String, BTreeSet<Omen>);
+
+/// Exact results for every craft that's been selected, so switching back and forth is instant
+#[derive(Debug, Default)]
+pub struct ExactCache {
+    results: HashMap<ExactKey, PendingResults>,
+}
+
+impl ExactCache {
+    /// Most crafts kept before the cache is cleared
+    const MAX_ENTRIES: usize = 256;
+
+    /// Results for the craft, starting the calculation if it hasn't been seen before
+    fn get(
+        &mut self,
+        #[cfg(target_arch = "wasm32")] ctx: &egui::Context,
+        base_item: &ItemState,
+        currency: &CurrencyType,
+        omens: &HashSet<Omen>,
+    ) -> PendingResults {
+        let key = (
+            base_item.clone(),
+            currency.name().to_string(),
+            omens.iter().copied().collect(),
+        );
+        if !self.results.contains_key(&key) && self.results.len() >= Self::MAX_ENTRIES {
+            self.results.clear();
+        }
+
+        self.results
+            .entry(key)
+            .or_insert_with(|| {
+                run_exact(
+                    #[cfg(target_arch = "wasm32")]
+                    ctx,
+                    base_item.clone(),
+                    currency.clone(),
+                    omens.clone(),
+                )
+            })
+            .clone()
+    }
+}
+
+fn exact_results(
+    base_item: &ItemState,
+    currency: &CurrencyType,
+    omens: &HashSet<Omen>,
+) -> ExactResults {
+    let candidate_tiers = get_valid_mods_for_item(base_item);
+    currency
+        .outcomes(base_item, &candidate_tiers, omens)
+        .map(|outcomes| TierResults {
+            added: outcomes.added_tiers(base_item),
+            removed: outcomes.removed_tiers(base_item),
+        })
+}
+
+/// Calculate exact results in a new thread
+#[cfg(not(target_arch = "wasm32"))]
+fn run_exact(base_item: ItemState, currency: CurrencyType, omens: HashSet<Omen>) -> PendingResults {
+    let results = Arc::new(Mutex::new(None));
+    thread::spawn({
+        let results = results.clone();
+        move || {
+            let exact = exact_results(&base_item, &currency, &omens);
+            *results.lock().unwrap() = Some(exact);
+        }
+    });
+
+    results
+}
+
+/// Calculate exact results once the UI has shown that they're pending
+#[cfg(target_arch = "wasm32")]
+fn run_exact(
+    ctx: &egui::Context,
+    base_item: ItemState,
+    currency: CurrencyType,
+    omens: HashSet<Omen>,
+) -> PendingResults {
+    let results = Arc::new(Mutex::new(None));
+
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local({
+        let results = results.clone();
+        async move {
+            gloo_timers::future::TimeoutFuture::new(0).await;
+            let exact = exact_results(&base_item, &currency, &omens);
+            *results.lock().unwrap() = Some(exact);
+            ctx.request_repaint();
+        }
+    });
+
+    results
+}
 
+/// Chances for mods to be added, then removed if there are any
+fn display_tier_results(ui: &mut Ui, key: &str, results: &TierResults<f64>) {
+    display_sim_results(ui, &format!("{key}_added"), &results.added);
+    if !results.removed.is_empty() {
+        ui.separator();
+        ui.heading("Removed");
+        display_sim_results(ui, &format!("{key}_removed"), &results.removed);
+    }
+}
+
+/// A grid showing the % chance for each mod to roll
+fn display_sim_results(ui: &mut Ui, key: &str, results: &HashMap<OpaqueIndex<Tier>, f64>) {
     let affix_groups = results
         .iter()
-        .map(|(&tier_id, &count)| (&TIERS[tier_id], count))
+        .map(|(&tier_id, &prob)| (&TIERS[tier_id], prob))
         .sorted_unstable_by_key(|(tier, _)| (tier.affix, &tier.mod_id, tier.ilvl))
         .chunk_by(|(tier, _)| tier.affix);
 
-    ScrollArea::new([false, true]).show(ui, |ui| {
+    ScrollArea::new([false, true]).id_salt(key).show(ui, |ui| {
         for (affix, mod_group) in &affix_groups {
             ui.heading(format!("{:?}", affix));
-            Grid::new(format!("results_grid_{:?}", affix))
+            Grid::new(format!("{key}_grid_{:?}", affix))
                 .num_columns(2)
                 .show(ui, |ui| {
                     for (&mod_id, group) in &mod_group.chunk_by(|(tier, _)| &tier.mod_id) {
                         let modifier = &MODS[mod_id];
                         ui.label(&modifier.group);
 
-                        let tier_counts = group.collect::<Vec<_>>();
-                        Grid::new(format!("results_grid_{:?}_{}", affix, modifier.group))
-                            .num_columns(tier_counts.len())
+                        let tier_probs = group.collect::<Vec<_>>();
+                        Grid::new(format!("{key}_grid_{:?}_{}", affix, modifier.group))
+                            .num_columns(tier_probs.len())
                             .show(ui, |ui| {
                                 // Ilvls on top row
-                                tier_counts.iter().for_each(|(tier, _)| {
+                                tier_probs.iter().for_each(|(tier, _)| {
                                     ui.label(format!("{}", tier.ilvl));
                                 });
                                 ui.end_row();
 
                                 // Roll % on bottom row
-                                tier_counts.into_iter().for_each(|(_, count)| {
-                                    ui.label(format!(
-                                        "{:.1}%",
-                                        (count as f32 / total_iters as f32) * 100.
-                                    ));
+                                tier_probs.into_iter().for_each(|(_, prob)| {
+                                    ui.label(format!("{:.1}%", prob * 100.));
                                 });
                             });
 
@@ -223,8 +364,10 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &ItemState) {
     let Page::CraftProbability {
         selected_currency,
         selected_omens,
+        exact_cache,
         simulation_state,
         num_iters_exp,
+        seed,
     } = page_state
     else {
         unreachable!()
@@ -234,7 +377,10 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &ItemState) {
 
     let currencies = CURRENCIES
         .iter()
-        .filter(|c| c.can_be_used(item, &candidate_tiers, &HashSet::new()))
+        .filter(|c| {
+            c.can_be_used(item, &candidate_tiers, &HashSet::new())
+                .is_ok()
+        })
         .copied()
         .collect::<Vec<_>>();
 
@@ -252,6 +398,21 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &ItemState) {
         // Select Omens
         omen_selection(ui, selected_currency, selected_omens, Some(item));
 
+        // Exact probabilities
+        let exact = match selected_currency.can_be_used(item, &candidate_tiers, selected_omens) {
+            Ok(()) => Some(exact_cache.get(
+                #[cfg(target_arch = "wasm32")]
+                ctx,
+                item,
+                selected_currency,
+                selected_omens,
+            )),
+            Err(reason) => {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("Can't craft: {reason}"));
+                None
+            }
+        };
+
         // 10^N iterations
         ui.add(
             DragValue::new(num_iters_exp)
@@ -259,6 +420,7 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &ItemState) {
                 .custom_formatter(|n, _| format!("{:?}", 10_u64.pow(n as u32))),
         );
         let n = 10_u64.pow(*num_iters_exp);
+        seed_selection(ui, seed);
 
         // Simulation
         if ui.button("Go!").clicked() {
@@ -269,20 +431,48 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &ItemState) {
                 selected_currency.clone(),
                 selected_omens.clone(),
                 n,
+                *seed,
             );
             *simulation_state = Some(state);
         }
 
-        if let Some(sim_state) = simulation_state {
-            match &*sim_state.status.lock().unwrap() {
-                SimStatus::Done { results } => {
-                    display_sim_results(ui, results);
+        // Exact & simulated results side by side
+        ui.columns(2, |columns| {
+            if let Some(exact) = &exact {
+                match &*exact.lock().unwrap() {
+                    Some(Some(results)) => {
+                        columns[0].heading("Exact");
+                        display_tier_results(&mut columns[0], "exact", results);
+                    }
+                    Some(None) => {
+                        columns[0].label(
+                            "Too many outcomes to calculate exactly, use the simulation instead.",
+                        );
+                    }
+                    None => {
+                        columns[0].horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Calculating...");
+                        });
+                    }
                 }
-                SimStatus::Running { iterations_done } => {
-                    ui.spinner();
-                    ui.label(format!("{} / {}", iterations_done, n));
+            }
+
+            if let Some(sim_state) = simulation_state {
+                let ui = &mut columns[1];
+                match &*sim_state.status.lock().unwrap() {
+                    SimStatus::Done { results } => {
+                        let results = results.probabilities(sim_state.num_iters);
+
+                        ui.heading(format!("Simulated (seed {})", sim_state.seed));
+                        display_tier_results(ui, "simulated", &results);
+                    }
+                    SimStatus::Running { iterations_done } => {
+                        ui.spinner();
+                        ui.label(format!("{} / {}", iterations_done, n));
+                    }
                 }
             }
-        }
+        });
     });
 }
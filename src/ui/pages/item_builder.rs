@@ -1,17 +1,18 @@
-use egui::{self, Align, Checkbox, DragValue, Grid, Layout, Ui};
+use egui::{self, Align, Checkbox, CollapsingHeader, DragValue, Grid, Layout, TextEdit, Ui};
 use itertools::Itertools;
 
 use crate::{
-    ITEM_TIERS, MODS, TIERS,
-    item_state::{ItemState, Rarity, get_valid_mods_for_item},
-    types::Affix,
+    ITEM_TIERS, MODS, SOCKETABLES, TIERS,
+    item_state::{ItemState, Rarity, get_all_mods_for_item, get_corrupted_implicits_for_item},
+    parsers::item_text,
+    types::{Affix, QualityType},
     ui::{dropdown, rarity_dropdown},
 };
 
 /// A grid of all the mods that can roll on the item with some checkboxes to let the user modify
 /// the item
 fn display_mod_select_grid(ui: &mut Ui, item: &mut ItemState) {
-    let candidate_tiers = get_valid_mods_for_item(item);
+    let candidate_tiers = get_all_mods_for_item(item);
 
     let affix_groups = candidate_tiers
         .iter()
@@ -83,8 +84,44 @@ fn display_mod_select_grid(ui: &mut Ui, item: &mut ItemState) {
     }
 }
 
+/// Text box to load an item copied in game, rather than building it by hand
+fn display_paste_item(ui: &mut Ui, item: &mut ItemState) {
+    let id = ui.make_persistent_id("paste_item");
+    // (pasted text, result message)
+    let (mut text, mut message) = ui
+        .data_mut(|data| data.get_temp::<(String, String)>(id))
+        .unwrap_or_default();
+
+    CollapsingHeader::new("Paste Item").show(ui, |ui| {
+        ui.label(
+            "Copy an item in game with Ctrl+C, or Ctrl+Alt+C to tell mods sharing a line apart",
+        );
+        ui.add(TextEdit::multiline(&mut text).desired_rows(8));
+        if ui.button("Load").clicked() {
+            message = match item_text::parse(&text) {
+                Ok(parsed) if parsed.unmatched.is_empty() => {
+                    *item = parsed.item;
+                    String::new()
+                }
+                Ok(parsed) => {
+                    *item = parsed.item;
+                    format!("Couldn't match:\n{}", parsed.unmatched.join("\n"))
+                }
+                Err(e) => e.to_string(),
+            };
+        }
+        if !message.is_empty() {
+            ui.label(&message);
+        }
+    });
+
+    ui.data_mut(|data| data.insert_temp(id, (text, message)));
+}
+
 pub fn show_page(ctx: &egui::Context, item: &mut ItemState) {
     egui::CentralPanel::default().show(ctx, |ui| {
+        display_paste_item(ui, item);
+
         // ========== BASE ITEM ==============
         Grid::new("base_grid").num_columns(2).show(ui, |ui| {
             let mut base_items = ITEM_TIERS.keys().collect::<Vec<_>>();
@@ -98,7 +135,14 @@ pub fn show_page(ctx: &egui::Context, item: &mut ItemState) {
 
             if old_base.is_some() {
                 // Base changed, clear mods from item
+                if !item.rules().allows_rarity(item.rarity) {
+                    item.rarity = Rarity::Magic;
+                }
                 item.mods.clear();
+                item.implicits.clear();
+                item.fractured.clear();
+                item.desecrated.clear();
+                item.sockets.clear();
             }
 
             // Ilvl
@@ -117,16 +161,20 @@ pub fn show_page(ctx: &egui::Context, item: &mut ItemState) {
 
             // Rarity
             ui.label("Rarity");
-            let old_rarity = rarity_dropdown(ui, &mut item.rarity, "rarity_dropdown");
+            let mut old_rarity = rarity_dropdown(ui, &mut item.rarity, "rarity_dropdown");
             ui.end_row();
 
+            // Some item classes can't be every rarity, so undo the change
+            if let Some(old) = old_rarity
+                && !item.rules().allows_rarity(item.rarity)
+            {
+                item.rarity = old;
+                old_rarity = None;
+            }
+
             if old_rarity.is_some() {
                 // Rarity changed, limit the mods
-                let max_affixes = match item.rarity {
-                    Rarity::Normal => 0,
-                    Rarity::Magic => 1,
-                    Rarity::Rare => 3,
-                };
+                let max_affixes = item.rules().max_affixes(item.rarity);
                 let prefixes = item.mods.iter().copied().filter(|tier_id| {
                     let tier = &TIERS[*tier_id];
                     tier.affix == Affix::Prefix
@@ -140,9 +188,154 @@ pub fn show_page(ctx: &egui::Context, item: &mut ItemState) {
                     .chain(suffixes.take(max_affixes))
                     .collect::<Vec<_>>();
             }
+
+            // Quality
+            ui.label("Quality");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut item.quality).range(0..=ItemState::MAX_QUALITY));
+                let quality_types = std::iter::once(None)
+                    .chain(QualityType::ALL.into_iter().map(Some))
+                    .collect::<Vec<_>>();
+                dropdown(
+                    ui,
+                    &mut item.quality_type,
+                    &quality_types.iter().collect::<Vec<_>>(),
+                    "combo_quality_type",
+                    |quality_type| quality_type.map_or("No Type".to_string(), |q| q.to_string()),
+                );
+            });
+            ui.end_row();
+
+            // Sockets
+            ui.label("Sockets");
+            ui.horizontal(|ui| {
+                let mut num_sockets = item.sockets.len();
+                ui.add(DragValue::new(&mut num_sockets).range(0..=item.max_sockets()));
+                item.sockets.resize(num_sockets, None);
+
+                let socketables = std::iter::once(None)
+                    .chain(SOCKETABLES.keys().sorted().cloned().map(Some))
+                    .collect::<Vec<_>>();
+                for (i, socket) in item.sockets.iter_mut().enumerate() {
+                    dropdown(
+                        ui,
+                        socket,
+                        &socketables.iter().collect::<Vec<_>>(),
+                        &format!("combo_socket_{i}"),
+                        |name| name.clone().unwrap_or("Empty".to_string()),
+                    );
+                }
+            });
+            ui.end_row();
+
+            // Fractured mod
+            ui.label("Fractured Mod");
+            let mod_options = std::iter::once(None)
+                .chain(item.mods.iter().copied().map(Some))
+                .collect::<Vec<_>>();
+            let mut fractured = item.fractured.first().copied();
+            dropdown(
+                ui,
+                &mut fractured,
+                &mod_options.iter().collect::<Vec<_>>(),
+                "combo_fractured",
+                |tier_id| tier_id.map_or("None".to_string(), |t| TIERS[t].id.clone()),
+            );
+            item.fractured = fractured.into_iter().collect();
+            ui.end_row();
+
+            // Desecrated mod
+            ui.label("Desecrated Mod");
+            let mut desecrated = item.desecrated.first().copied();
+            dropdown(
+                ui,
+                &mut desecrated,
+                &mod_options.iter().collect::<Vec<_>>(),
+                "combo_desecrated",
+                |tier_id| tier_id.map_or("None".to_string(), |t| TIERS[t].id.clone()),
+            );
+            item.desecrated = desecrated.into_iter().collect();
+            ui.end_row();
+
+            // Corruption
+            ui.label("Corrupted");
+            ui.checkbox(&mut item.corrupted, "");
+            ui.end_row();
+
+            if item.corrupted {
+                ui.label("Corrupted Implicit");
+                let implicits = std::iter::once(None)
+                    .chain(get_corrupted_implicits_for_item(item).into_iter().map(Some))
+                    .collect::<Vec<_>>();
+                let mut implicit = item.implicits.first().copied();
+                dropdown(
+                    ui,
+                    &mut implicit,
+                    &implicits.iter().collect::<Vec<_>>(),
+                    "combo_implicit",
+                    |tier_id| tier_id.map_or("None".to_string(), |t| TIERS[t].id.clone()),
+                );
+                item.implicits = implicit.into_iter().collect();
+                ui.end_row();
+            } else {
+                item.implicits.clear();
+            }
+
+            // Hinekora's Lock
+            if let Some(foreseen) = &item.foreseen {
+                ui.label(format!("Foreseen {}", foreseen.currency));
+                if ui.button("Discard").clicked() {
+                    item.foreseen = None;
+                }
+                ui.end_row();
+            }
         });
 
         // ============= Mods ====================
         display_mod_select_grid(ui, item);
+
+        // Fractured and desecrated mods must still be on the item
+        let mods = &item.mods;
+        item.fractured.retain(|tier_id| mods.contains(tier_id));
+        item.desecrated.retain(|tier_id| mods.contains(tier_id));
+        if item.desecrated.is_empty() {
+            item.veiled_min_level = None;
+        }
+
+        // ============= Rolls ====================
+        display_roll_grid(ui, item);
+    });
+}
+
+/// Let the user enter the concrete values rolled on each mod
+fn display_roll_grid(ui: &mut Ui, item: &mut ItemState) {
+    let ItemState {
+        mods,
+        implicits,
+        rolls,
+        ..
+    } = item;
+    rolls.retain(|tier_id, _| mods.contains(tier_id) || implicits.contains(tier_id));
+    if mods.is_empty() && implicits.is_empty() {
+        return;
+    }
+
+    ui.heading("Rolled Values");
+    Grid::new("roll_grid").num_columns(2).show(ui, |ui| {
+        for tier_id in implicits.iter().chain(mods.iter()) {
+            let tier = &TIERS[*tier_id];
+            // Newly added mods start with a perfect roll
+            let values = rolls
+                .entry(*tier_id)
+                .or_insert_with(|| tier.value_ranges.iter().map(|&[_, max]| max).collect());
+
+            ui.label(&tier.id);
+            ui.horizontal(|ui| {
+                for (value, &[a, b]) in values.iter_mut().zip(&tier.value_ranges) {
+                    ui.add(DragValue::new(value).range(a.min(b)..=a.max(b)));
+                }
+            });
+            ui.end_row();
+        }
     });
 }
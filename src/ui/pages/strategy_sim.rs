@@ -6,21 +6,27 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use egui::{self, CentralPanel, Color32, Frame, Grid, ScrollArea, Ui};
+use egui::{self, CentralPanel, Color32, DragValue, Frame, Grid, ScrollArea, Ui};
 use itertools::Itertools;
+use rand::SeedableRng;
 
 use crate::{
-    CURRENCIES, MODS, TIERS,
-    currency::{Currency, CurrencyType},
+    CURRENCIES, MODS, SOCKETABLES, TIERS,
+    currency::{CraftError, Currency, CurrencyType},
     hashvec::OpaqueIndex,
     io::SavedStrategy,
-    item_state::{ItemState, Rarity, get_valid_mods_for_item},
+    item_state::{
+        ItemState, Rarity, get_all_mods_for_item, get_desecrated_mods_for_item,
+        get_valid_mods_for_item,
+    },
+    markov::{self, Evaluation, EvaluationError},
     strategy::{Condition, ConditionGroup, ModifierCondition, Strategy},
     types::{Modifier, Omen, Tier},
     ui::{
         Page, components::currency_selection::currency_dropdown, dropdown, multi_select_checkboxes,
-        omen_selection, range_selector, rarity_dropdown,
+        omen_selection, range_selector, rarity_dropdown, seed_selection,
     },
+    util::rand::CraftRng,
 };
 
 #[derive(Debug)]
@@ -29,6 +35,7 @@ pub enum SimStatus {
         item: ItemState,
         currency: CurrencyType,
         omens: HashSet<Omen>,
+        reason: CraftError,
     },
     NoMatchingState {
         item: ItemState,
@@ -51,6 +58,14 @@ pub struct SimState {
     handle: JoinHandle<()>,
 }
 
+/// Exact evaluation running in the background. None until it finishes
+#[derive(Debug)]
+pub struct EvalState {
+    result: Arc<Mutex<Option<Result<Evaluation, Box<EvaluationError>>>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    _handle: JoinHandle<()>,
+}
+
 fn show_strategy_step(
     ui: &mut Ui,
     key: &str,
@@ -76,7 +91,31 @@ fn show_strategy_step(
                     })
                     .inner;
 
-                rarity_dropdown(ui, &mut condition.rarity, &format!("rarity_{key}"));
+                ui.horizontal(|ui| {
+                    rarity_dropdown(ui, &mut condition.rarity, &format!("rarity_{key}"));
+                    dropdown(
+                        ui,
+                        &mut condition.corrupted,
+                        &[None, Some(false), Some(true)].iter().collect::<Vec<_>>(),
+                        &format!("corrupted_{key}"),
+                        |corrupted| match corrupted {
+                            None => "Corrupted: Any".to_string(),
+                            Some(false) => "Not Corrupted".to_string(),
+                            Some(true) => "Corrupted".to_string(),
+                        },
+                    );
+                    dropdown(
+                        ui,
+                        &mut condition.foreseen,
+                        &[None, Some(false), Some(true)].iter().collect::<Vec<_>>(),
+                        &format!("foreseen_{key}"),
+                        |foreseen| match foreseen {
+                            None => "Foreseen: Any".to_string(),
+                            Some(false) => "Not Foreseen".to_string(),
+                            Some(true) => "Foreseen".to_string(),
+                        },
+                    );
+                });
 
                 // Condition groups
                 let to_remove = condition
@@ -125,8 +164,11 @@ fn show_strategy_group(
                 ConditionGroup::Count { .. } => "Count",
                 ConditionGroup::Not(_) => "Not",
                 ConditionGroup::AffixCount { .. } => "Affix Count",
+                ConditionGroup::Sockets { .. } => "Sockets",
             };
-            let group_types = ["Count", "Not", "Affix Count"].iter().collect::<Vec<_>>();
+            let group_types = ["Count", "Not", "Affix Count", "Sockets"]
+                .iter()
+                .collect::<Vec<_>>();
             let old = dropdown(
                 ui,
                 &mut dropdown_type,
@@ -146,6 +188,10 @@ fn show_strategy_group(
                         prefixes: 0..=3,
                         affixes: 0..=6,
                     },
+                    "Sockets" => ConditionGroup::Sockets {
+                        sockets: 0..=2,
+                        socketed: vec![],
+                    },
                     _ => unreachable!(),
                 }
             }
@@ -179,6 +225,8 @@ fn show_strategy_group(
                         mod_conds.push(ModifierCondition {
                             mod_group: candidate_mods.first().unwrap().0,
                             levels: vec![],
+                            fractured: None,
+                            min_roll: None,
                         });
                     }
                 }
@@ -238,6 +286,44 @@ fn show_strategy_group(
                         range_selector(ui, affixes, 0..=100);
                     });
                 }
+                ConditionGroup::Sockets { sockets, socketed } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Sockets");
+                        range_selector(ui, sockets, 0..=2);
+                    });
+
+                    let socketables = SOCKETABLES.keys().sorted().collect::<Vec<_>>();
+                    let to_remove = socketed
+                        .iter_mut()
+                        .enumerate()
+                        .flat_map(|(i, name)| {
+                            ui.horizontal(|ui| {
+                                // Button to remove this socketable
+                                let remove = ui.button("X").clicked();
+
+                                dropdown(
+                                    ui,
+                                    name,
+                                    &socketables,
+                                    &format!("dropdown_socketable_{key}_{i}"),
+                                    |name| name.clone(),
+                                );
+
+                                remove
+                            })
+                            .inner
+                            .then_some(i)
+                        })
+                        .next();
+
+                    if let Some(index) = to_remove {
+                        socketed.remove(index);
+                    }
+
+                    if ui.button("Add socketable").clicked() && !socketables.is_empty() {
+                        socketed.push(socketables[0].clone());
+                    }
+                }
             }
 
             remove
@@ -288,11 +374,92 @@ fn show_strategy_mod(
             format!("{}", ilvl)
         });
 
+        dropdown(
+            ui,
+            &mut mod_condition.fractured,
+            &[None, Some(false), Some(true)].iter().collect::<Vec<_>>(),
+            &format!("fractured_{key}"),
+            |fractured| match fractured {
+                None => "Fractured: Any".to_string(),
+                Some(false) => "Not Fractured".to_string(),
+                Some(true) => "Fractured".to_string(),
+            },
+        );
+
+        // Optional minimum roll, as a percentage of the tier's range
+        let mut use_min_roll = mod_condition.min_roll.is_some();
+        ui.checkbox(&mut use_min_roll, "Min roll %");
+        mod_condition.min_roll = use_min_roll.then(|| {
+            let mut min_roll = mod_condition.min_roll.unwrap_or(90);
+            ui.add(DragValue::new(&mut min_roll).range(0..=100));
+            min_roll
+        });
+
         remove
     })
     .inner
 }
 
+/// Ranking of the desecrated mods an Unveil keeps when they're revealed, best first
+fn show_priority_list(
+    ui: &mut Ui,
+    key: &str,
+    priority_list: &mut Vec<OpaqueIndex<Tier>>,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+) {
+    ui.label("Unveil priority");
+    let order_action = priority_list
+        .iter()
+        .enumerate()
+        .flat_map(|(i, tier_id)| {
+            ui.horizontal(|ui| {
+                let order_action = [
+                    ui.button("X").clicked().then_some(OrderAction::Remove(i)),
+                    ui.button("^").clicked().then_some(OrderAction::MoveUp(i)),
+                    ui.button("v").clicked().then_some(OrderAction::MoveDown(i)),
+                ]
+                .into_iter()
+                .flatten()
+                .next();
+                ui.label(format!("{}. {}", i + 1, TIERS[*tier_id].id));
+
+                order_action
+            })
+            .inner
+        })
+        .next();
+
+    match order_action {
+        Some(OrderAction::Remove(i)) => {
+            priority_list.remove(i);
+        }
+        Some(OrderAction::MoveUp(i)) if i > 0 => priority_list.swap(i - 1, i),
+        Some(OrderAction::MoveDown(i)) if i + 1 < priority_list.len() => {
+            priority_list.swap(i, i + 1)
+        }
+        _ => (),
+    }
+
+    // Add mods which haven't been ranked yet
+    let unranked = candidate_tiers
+        .iter()
+        .filter(|tier_id| !priority_list.contains(tier_id))
+        .collect::<Vec<_>>();
+    if !unranked.is_empty() {
+        ui.push_id(format!("priority_list_{key}"), |ui| {
+            ui.menu_button("Add mod", |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    for &&tier_id in &unranked {
+                        if ui.button(&TIERS[tier_id].id).clicked() {
+                            priority_list.push(tier_id);
+                        }
+                    }
+                });
+            });
+        });
+    }
+}
+
 enum OrderRequest {
     Remove,
     MoveUp,
@@ -314,7 +481,9 @@ fn get_tiers_mods(
     Vec<(OpaqueIndex<Modifier>, Vec<OpaqueIndex<Tier>>)>,
 ) {
     let candidate_tiers = get_valid_mods_for_item(item);
-    let candidate_mods = candidate_tiers
+    // Conditions can also check for mods which don't roll randomly, eg. from essences
+    let all_tiers = get_all_mods_for_item(item);
+    let candidate_mods = all_tiers
         .iter()
         .sorted_unstable_by_key(|&&tier_id| {
             let tier = &TIERS[tier_id];
@@ -338,12 +507,15 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
     let Page::StrategyBuilder {
         strategy,
         simulation_state,
+        evaluation,
+        seed,
     } = page_state
     else {
         unreachable!()
     };
 
     let (mut candidate_tiers, mut candidate_mods) = get_tiers_mods(item);
+    let mut desecrated_tiers = get_desecrated_mods_for_item(item);
 
     CentralPanel::default().show(ctx, |ui| {
         ScrollArea::vertical().show(ui, |ui| {
@@ -363,6 +535,7 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
                         *strategy = saved_strategy.strategy;
                         *item = saved_strategy.base_item;
                         (candidate_tiers, candidate_mods) = get_tiers_mods(item);
+                        desecrated_tiers = get_desecrated_mods_for_item(item);
                     }
                 }
             });
@@ -409,6 +582,15 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
 
                                 // Select Omens
                                 omen_selection(ui, currency, selected_omens, None);
+
+                                if let CurrencyType::Unveil(unveil) = currency {
+                                    show_priority_list(
+                                        ui,
+                                        &format!("{i}"),
+                                        &mut unveil.priority_list,
+                                        &desecrated_tiers,
+                                    );
+                                }
                             } else {
                                 // No action - end state
                                 *action = None;
@@ -445,12 +627,16 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
                 strategy.0.push((
                     Condition {
                         rarity: Rarity::Normal,
+                        corrupted: None,
+                        foreseen: None,
                         groups: vec![],
                     },
                     None,
                 ));
             }
 
+            seed_selection(ui, seed);
+
             // Strategy simulation
             if ui.button("Go!").clicked() {
                 *simulation_state = Some(run_sim(
@@ -460,19 +646,68 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
                     strategy.clone(),
                     100,
                     &candidate_tiers,
+                    *seed,
+                ));
+            }
+
+            // Exact strategy evaluation
+            if ui.button("Evaluate").clicked() {
+                *evaluation = Some(run_eval(
+                    #[cfg(target_arch = "wasm32")]
+                    ctx,
+                    item.clone(),
+                    strategy.clone(),
+                    &candidate_tiers,
+                    *seed,
                 ));
             }
 
+            if let Some(eval_state) = evaluation {
+                match &*eval_state.result.lock().unwrap() {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Evaluating...");
+                        });
+                    }
+                    Some(Ok(evaluation)) => display_evaluation(ui, strategy, evaluation),
+                    Some(Err(err)) => match &**err {
+                        EvaluationError::InvalidCraft {
+                            item,
+                            currency,
+                            omens,
+                            reason,
+                        } => {
+                            ui.label("Invalid craft:");
+                            ui.label(format!("{}", item));
+                            ui.label(format!("{} {}", currency.name(), omens.iter().join(", ")));
+                            ui.label(format!("{}", reason));
+                        }
+                        EvaluationError::NoMatchingState { item } => {
+                            ui.label("No matching condition for item:");
+                            ui.label(format!("{}", item));
+                        }
+                        EvaluationError::UsesRolls => {
+                            ui.label(
+                                "Can't evaluate conditions on rolled values exactly, use the simulation instead",
+                            );
+                        }
+                    },
+                }
+            }
+
             if let Some(sim_state) = simulation_state {
                 match &*sim_state.status.lock().unwrap() {
                     SimStatus::InvalidCraft {
                         item,
                         currency,
                         omens,
+                        reason,
                     } => {
                         ui.label("Invalid craft:");
                         ui.label(format!("{}", item));
-                        ui.label(format!("{} {:?}", currency.name(), omens));
+                        ui.label(format!("{} {}", currency.name(), omens.iter().join(", ")));
+                        ui.label(format!("{}", reason));
                     }
                     SimStatus::NoMatchingState { item } => {
                         ui.label("No matching condition for item:");
@@ -505,11 +740,136 @@ pub fn show_page(page_state: &mut Page, ctx: &egui::Context, item: &mut ItemStat
     });
 }
 
+/// Number of crafts sampled when evaluating currencies without exact outcomes
+const EVAL_FALLBACK_SAMPLES: usize = 1000;
+
+/// Start an exact evaluation in a new thread
+#[cfg(not(target_arch = "wasm32"))]
+fn run_eval(
+    base_item: ItemState,
+    strategy: Strategy,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+    seed: u64,
+) -> EvalState {
+    let candidate_tiers = candidate_tiers.to_vec();
+    let result = Arc::new(Mutex::new(None));
+
+    EvalState {
+        result: result.clone(),
+        _handle: thread::spawn(move || {
+            let evaluation = markov::evaluate(
+                &strategy,
+                &base_item,
+                &candidate_tiers,
+                EVAL_FALLBACK_SAMPLES,
+                &mut CraftRng::seed_from_u64(seed),
+            );
+            *result.lock().unwrap() = Some(evaluation);
+        }),
+    }
+}
+
+/// Start an exact evaluation once the UI has shown that it's running
+#[cfg(target_arch = "wasm32")]
+fn run_eval(
+    ctx: &egui::Context,
+    base_item: ItemState,
+    strategy: Strategy,
+    candidate_tiers: &[OpaqueIndex<Tier>],
+    seed: u64,
+) -> EvalState {
+    let candidate_tiers = candidate_tiers.to_vec();
+    let result = Arc::new(Mutex::new(None));
+
+    let state = EvalState {
+        result: result.clone(),
+    };
+
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        gloo_timers::future::TimeoutFuture::new(0).await;
+        let evaluation = markov::evaluate(
+            &strategy,
+            &base_item,
+            &candidate_tiers,
+            EVAL_FALLBACK_SAMPLES,
+            &mut CraftRng::seed_from_u64(seed),
+        );
+        *result.lock().unwrap() = Some(evaluation);
+        ctx.request_repaint();
+    });
+
+    state
+}
+
+/// Ending probabilities & expected currency usage of the strategy
+fn display_evaluation(ui: &mut Ui, strategy: &Strategy, evaluation: &Evaluation) {
+    if evaluation.approximate {
+        ui.label("Some crafts were estimated by sampling, so these results are approximate.");
+    }
+    if !evaluation.finished() {
+        ui.label(format!(
+            "Strategy doesn't always finish: {:.3}% of items get stuck in a loop.",
+            evaluation.unresolved * 100.
+        ));
+    }
+    ui.label(format!("{} unique item states", evaluation.num_states));
+
+    Grid::new("evaluation_steps_grid")
+        .num_columns(3)
+        .show(ui, |ui| {
+            ui.label("Step");
+            ui.label("End chance");
+            ui.label("Expected crafts");
+            ui.end_row();
+
+            for (i, (_, action)) in strategy.0.iter().enumerate() {
+                ui.label(format!("{i}"));
+                match action {
+                    Some((omens, currency)) => {
+                        ui.label("");
+                        ui.label(format!(
+                            "{:.3} {} {:?}",
+                            evaluation.expected_crafts[i],
+                            currency.name(),
+                            omens
+                        ));
+                    }
+                    None => {
+                        ui.label(format!("{:.3}%", evaluation.end_probabilities[i] * 100.));
+                        ui.label("");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+    Grid::new("evaluation_currency_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (currency, count) in &evaluation.expected_currencies {
+                ui.label(currency.name());
+                ui.label(format!("{count:.3}"));
+                ui.end_row();
+            }
+            for (omen, count) in evaluation
+                .expected_omens
+                .iter()
+                .sorted_unstable_by_key(|(omen, _)| **omen)
+            {
+                ui.label(omen.name());
+                ui.label(format!("{count:.3}"));
+                ui.end_row();
+            }
+        });
+}
+
 fn sim_batch(
     strategy: &Strategy,
     base_item: &ItemState,
     candidate_tiers: &[OpaqueIndex<Tier>],
     num_iters: usize,
+    rng: &mut CraftRng,
 ) -> SimStatus {
     let mut state_transitions = vec![vec![0_usize; strategy.0.len()]; strategy.0.len()];
     for _ in 0..num_iters {
@@ -531,16 +891,17 @@ fn sim_batch(
                 break;
             };
 
-            if !currency.can_be_used(&item, candidate_tiers, omens) {
+            if let Err(reason) = currency.can_be_used(&item, candidate_tiers, omens) {
                 // Condition matched but the currency can't be used on it
                 return SimStatus::InvalidCraft {
                     item,
                     currency: currency.clone(),
                     omens: omens.clone(),
+                    reason,
                 };
             }
 
-            currency.craft(&mut item, candidate_tiers, omens);
+            currency.craft(&mut item, candidate_tiers, omens, rng);
         }
 
         if !finished_state {
@@ -558,6 +919,7 @@ fn run_sim(
     strategy: Strategy,
     num_iters: usize,
     candidate_tiers: &[OpaqueIndex<Tier>],
+    seed: u64,
 ) -> SimState {
     let candidate_tiers = candidate_tiers.to_vec();
     let status = Arc::new(Mutex::new(SimStatus::Running {
@@ -570,13 +932,20 @@ fn run_sim(
         strategy: strategy.clone(),
         status: status.clone(),
         handle: thread::spawn(move || {
+            let mut rng = CraftRng::seed_from_u64(seed);
             let batch_size = 1000;
             let batch_sizes = std::iter::repeat_n(batch_size, num_iters / batch_size)
                 .chain(std::iter::once(num_iters % batch_size));
 
             let mut state_transitions = vec![vec![0_usize; strategy.0.len()]; strategy.0.len()];
             for batch_size in batch_sizes {
-                let batch_results = sim_batch(&strategy, &base_item, &candidate_tiers, batch_size);
+                let batch_results = sim_batch(
+                    &strategy,
+                    &base_item,
+                    &candidate_tiers,
+                    batch_size,
+                    &mut rng,
+                );
                 match batch_results {
                     // Happy path
                     SimStatus::Done {
@@ -610,6 +979,7 @@ fn run_sim(
     strategy: Strategy,
     num_iters: usize,
     candidate_tiers: &[OpaqueIndex<Tier>],
+    seed: u64,
 ) -> SimState {
     let candidate_tiers = candidate_tiers.to_vec();
     let status = Arc::new(Mutex::new(SimStatus::Running {
@@ -625,14 +995,23 @@ fn run_sim(
 
     let ctx = ctx.clone();
     wasm_bindgen_futures::spawn_local(async move {
+        let mut rng = CraftRng::seed_from_u64(seed);
         let batch_size = 1000;
         let batch_sizes = std::iter::repeat_n(batch_size, num_iters / batch_size)
             .chain(std::iter::once(num_iters % batch_size));
 
         let mut state_transitions = vec![vec![0_usize; strategy.0.len()]; strategy.0.len()];
         for batch_size in batch_sizes {
-            let batch_results =
-                async { sim_batch(&strategy, &base_item, &candidate_tiers, batch_size) }.await;
+            let batch_results = async {
+                sim_batch(
+                    &strategy,
+                    &base_item,
+                    &candidate_tiers,
+                    batch_size,
+                    &mut rng,
+                )
+            }
+            .await;
 
             match batch_results {
                 // Happy path
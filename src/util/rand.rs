@@ -1,4 +1,9 @@
-pub fn choice<'a, T>(choices: &'a [T], weights: &[u32]) -> &'a T {
+use rand::{Rng, rngs::StdRng};
+
+/// RNG used for all crafting randomness, so that results can be reproduced from a seed
+pub type CraftRng = StdRng;
+
+pub fn choice<'a, T>(rng: &mut CraftRng, choices: &'a [T], weights: &[u32]) -> &'a T {
     assert!(!choices.is_empty());
     assert_eq!(choices.len(), weights.len());
 
@@ -12,9 +17,71 @@ pub fn choice<'a, T>(choices: &'a [T], weights: &[u32]) -> &'a T {
         },
     );
 
-    let t = rand::random_range(0..sum);
+    let t = rng.random_range(0..sum);
 
     let i = cumsum.iter().position(|&x| x > t).unwrap();
 
     &choices[i]
 }
+
+/// Roll a concrete value within each of a tier's value ranges
+pub fn roll_values(rng: &mut CraftRng, value_ranges: &[[i32; 2]]) -> Vec<i32> {
+    value_ranges
+        .iter()
+        .map(|&[a, b]| rng.random_range(a.min(b)..=a.max(b)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::SeedableRng;
+
+    use crate::{
+        currency::{Currency, CurrencyType},
+        internal::test_data::{self, ring},
+        item_state::{ItemState, Rarity, get_valid_mods_for_item},
+        util::rand::CraftRng,
+    };
+
+    /// Craft a ring up to a rare and reroll it, recording every step
+    fn craft_sequence(seed: u64) -> Vec<ItemState> {
+        let mut rng = CraftRng::seed_from_u64(seed);
+        let mut item = ring(Rarity::Normal, &[]);
+        let candidate_tiers = get_valid_mods_for_item(&item);
+
+        let currencies = [
+            CurrencyType::Transmute,
+            CurrencyType::Augmentation,
+            CurrencyType::Regal,
+            CurrencyType::Divine,
+            CurrencyType::Chaos,
+        ];
+        currencies
+            .iter()
+            .map(|currency| {
+                assert!(
+                    currency
+                        .can_be_used(&item, &candidate_tiers, &HashSet::new())
+                        .is_ok()
+                );
+                currency.craft(&mut item, &candidate_tiers, &HashSet::new(), &mut rng);
+                item.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_seed_determinism() {
+        test_data::init();
+
+        // Same seed, same mods and rolled values at every step
+        let items = craft_sequence(1234);
+        assert_eq!(items, craft_sequence(1234));
+        assert!(items.iter().all(|item| item.is_valid()));
+
+        // Different seeds shouldn't all give the same item
+        assert!((0..10).any(|seed| craft_sequence(seed) != items));
+    }
+}